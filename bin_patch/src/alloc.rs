@@ -0,0 +1,328 @@
+//! Allocation of executable memory within reach of a `rel32` branch from the
+//! modules being patched. Memory is taken from the padding between functions in
+//! a loaded module's code sections, or reserved from the system near the
+//! module.
+//!
+//! Code is only freed when asked to after its patch has been restored. A patch
+//! can be restored while a thread is still running its trampoline or thunk, or
+//! will return into one, and only the caller can know when every thread has
+//! left.
+
+use crate::{pe, sys, Memory};
+use core::{ffi::c_void, ops::Range, ptr};
+use std::sync::{Mutex, MutexGuard};
+
+/// The size of each block of memory requested from the system. Matches the
+/// system's allocation granularity.
+const BLOCK_SIZE: usize = 0x10000;
+
+/// Allocations are aligned the same as a function would be.
+const ALIGN: usize = 16;
+
+/// The smallest run of `int3`s in a module's code which is used for code.
+const MIN_PADDING: usize = 32;
+
+/// The byte unused memory is filled with. Traps if it's ever run.
+const INT3: u8 = 0xcc;
+
+const fn align_up(x: usize, align: usize) -> usize {
+  (x + (align - 1)) & !(align - 1)
+}
+
+/// Whether a `rel32` branch at one address can reach the other.
+fn in_reach(from: usize, to: usize) -> bool {
+  i32::try_from(to.wrapping_sub(from) as isize).is_ok()
+}
+
+/// A range of executable memory which allocations are made from.
+struct Block {
+  range: Range<usize>,
+  /// The unused ranges within the block, sorted by address.
+  free: Vec<Range<usize>>,
+}
+impl Block {
+  fn new(range: Range<usize>) -> Self {
+    Self { free: vec![range.clone()], range }
+  }
+
+  /// Takes an aligned chunk from the block's unused memory.
+  fn alloc(&mut self, near: usize, len: usize) -> Option<usize> {
+    let (i, start) = self.free.iter().enumerate().find_map(|(i, r)| {
+      let start = align_up(r.start, ALIGN);
+      let end = start.checked_add(len)?;
+      (end <= r.end && in_reach(near, start) && in_reach(near, end)).then_some((i, start))
+    })?;
+    let r = self.free[i].clone();
+    let head = r.start..start;
+    let tail = start + len..r.end;
+    self
+      .free
+      .splice(i..=i, [head, tail].into_iter().filter(|r| !r.is_empty()));
+    Some(start)
+  }
+
+  /// Returns a chunk taken by `alloc` to the block's unused memory.
+  fn free(&mut self, chunk: Range<usize>) {
+    let i = self.free.partition_point(|r| r.end <= chunk.start);
+    self.free.insert(i, chunk);
+    // Merge with the following range first so the index stays valid.
+    if i + 1 < self.free.len() && self.free[i].end == self.free[i + 1].start {
+      self.free[i].end = self.free.remove(i + 1).end;
+    }
+    if i > 0 && self.free[i - 1].end == self.free[i].start {
+      self.free[i - 1].end = self.free.remove(i).end;
+    }
+  }
+}
+
+struct Allocator {
+  blocks: Vec<Block>,
+  /// The base address of each module whose padding has been added.
+  modules: Vec<usize>,
+}
+
+static ALLOCATOR: Mutex<Allocator> =
+  Mutex::new(Allocator { blocks: Vec::new(), modules: Vec::new() });
+
+fn allocator() -> MutexGuard<'static, Allocator> {
+  ALLOCATOR.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Finds each run of `int3`s which is long enough to hold code.
+fn find_padding(bytes: &[u8], start: usize) -> Vec<Range<usize>> {
+  let mut found = Vec::new();
+  let mut pos = 0;
+  while pos < bytes.len() {
+    let len = bytes[pos..].iter().take_while(|&&x| x == INT3).count();
+    if len >= MIN_PADDING {
+      found.push(start + pos..start + pos + len);
+    }
+    pos += len.max(1);
+  }
+  found
+}
+
+/// Adds the padding between functions in a loaded module's code sections to
+/// the memory allocations are made from. Each module is only searched once.
+///
+/// # Safety
+/// The memory must be the module loaded in the current process, and the module
+/// must never be unloaded.
+pub(crate) unsafe fn add_padding(mem: &impl Memory) {
+  let base = mem.base();
+  let mut allocator = allocator();
+  if allocator.modules.contains(&base) {
+    return;
+  }
+  allocator.modules.push(base);
+  for range in pe::code_ranges(mem).unwrap_or_default() {
+    let padding = mem
+      .read(range.start, range.len(), |bytes| {
+        find_padding(bytes, base.wrapping_add(range.start))
+      })
+      .unwrap_or_default();
+    allocator.blocks.extend(padding.into_iter().map(Block::new));
+  }
+}
+
+/// Reserves executable memory which can be reached by a `rel32` branch from
+/// the given address. Searches the free regions above the address, then below
+/// it.
+fn reserve_near(near: usize, size: usize) -> Option<usize> {
+  let fits = |x: usize| in_reach(near, x) && in_reach(near, x + size);
+
+  let mut address = near;
+  while let Some(region) = sys::query(address) {
+    let candidate = align_up(region.start.max(address), BLOCK_SIZE);
+    if !in_reach(near, candidate) {
+      break;
+    }
+    if region.free && candidate + size <= region.end && fits(candidate) {
+      if let Some(block) = sys::reserve_exec(candidate, size) {
+        return Some(block);
+      }
+    }
+    address = region.end;
+  }
+
+  let mut address = near;
+  while let Some(region) = sys::query(address) {
+    if let Some(candidate) = region.end.checked_sub(size).map(|x| x & !(BLOCK_SIZE - 1)) {
+      if !in_reach(near, candidate) {
+        break;
+      }
+      if region.free && candidate >= region.start && fits(candidate) {
+        if let Some(block) = sys::reserve_exec(candidate, size) {
+          return Some(block);
+        }
+      }
+    }
+    address = region.start.checked_sub(1)?;
+  }
+  None
+}
+
+/// Allocates executable memory which can be reached by a `rel32` branch from
+/// the given address. Returns `None` if no memory is available in range. The
+/// memory stays allocated until it's passed to `free_exec`.
+pub(crate) fn alloc_exec(near: usize, len: usize) -> Option<usize> {
+  let len = align_up(len.max(1), ALIGN);
+  let mut allocator = allocator();
+  let blocks = &mut allocator.blocks;
+  if let Some(address) = blocks.iter_mut().find_map(|b| b.alloc(near, len)) {
+    return Some(address);
+  }
+  let size = align_up(len, BLOCK_SIZE);
+  let start = reserve_near(near, size)?;
+  let mut block = Block::new(start..start + size);
+  let address = block.alloc(near, len);
+  blocks.push(block);
+  address
+}
+
+/// Frees memory allocated by `alloc_exec`, filling it with `int3`s. The memory
+/// stays reserved for later allocations.
+///
+/// # Safety
+/// The memory must have been returned by `alloc_exec` with the same length and
+/// not already freed. No thread may be running the code in it or be about to
+/// return into it.
+pub(crate) unsafe fn free_exec(address: usize, len: usize) {
+  let len = align_up(len.max(1), ALIGN);
+  let mut allocator = allocator();
+  if let Some(block) = allocator.blocks.iter_mut().find(|b| b.range.contains(&address)) {
+    if let Some(_mem) = sys::MemUnlock::new(address as *const c_void, len) {
+      ptr::write_bytes(address as *mut u8, INT3, len);
+    }
+    block.free(address..address + len);
+  }
+}
+
+/// Checks if the address is within memory allocated by `alloc_exec`.
+pub(crate) fn is_generated(address: usize) -> bool {
+  allocator().blocks.iter().any(|b| b.range.contains(&address))
+}
+
+/// Somewhere the code referenced by a patch can be placed.
+pub(crate) trait CodeSpace {
+  /// Gets the address a patch should use to call the given function.
+  fn resolve(&mut self, target: usize) -> Option<usize>;
+
+  /// Reserves space for code of the given size which can be reached from the
+  /// given address. Returns the code's address.
+  fn alloc(&mut self, near: usize, len: usize) -> Option<usize>;
+
+  /// Writes code to space previously returned by `alloc`.
+  ///
+  /// # Safety
+  /// The address and code must be from a matching call to `alloc`.
+  unsafe fn write(&mut self, address: usize, code: &[u8]);
+}
+
+/// Places code in executable memory allocated in the current process. Keeps
+/// track of each allocation so it can be freed with its patch.
+#[derive(Default)]
+pub(crate) struct ProcessCode {
+  pub chunks: Vec<Range<usize>>,
+}
+impl CodeSpace for ProcessCode {
+  #[inline]
+  fn resolve(&mut self, target: usize) -> Option<usize> {
+    Some(target)
+  }
+
+  fn alloc(&mut self, near: usize, len: usize) -> Option<usize> {
+    let address = alloc_exec(near, len)?;
+    self.chunks.push(address..address + len);
+    Some(address)
+  }
+
+  unsafe fn write(&mut self, address: usize, code: &[u8]) {
+    let _mem = sys::MemUnlock::new(address as *const c_void, code.len());
+    ptr::copy_nonoverlapping(code.as_ptr(), address as *mut u8, code.len());
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn block() {
+    let mut block = Block::new(0x1000..0x1100);
+    assert_eq!(block.alloc(0x1000, 0x10), Some(0x1000));
+    assert_eq!(block.alloc(0x1000, 3), Some(0x1010));
+    // Allocations are aligned, leaving a gap.
+    assert_eq!(block.alloc(0x1000, 0x20), Some(0x1020));
+    assert_eq!(block.free, [0x1013..0x1020, 0x1040..0x1100]);
+    assert_eq!(block.alloc(0x1000, 0xd0), None);
+    assert_eq!(block.alloc(0x1000, 0xc0), Some(0x1040));
+    assert_eq!(block.free.len(), 1);
+    assert_eq!(block.free[0], 0x1013..0x1020);
+    assert_eq!(block.alloc(0x1000, 1), None);
+
+    // Freed chunks are merged with their neighbours.
+    block.free(0x1040..0x1100);
+    assert_eq!(block.free, [0x1013..0x1020, 0x1040..0x1100]);
+    block.free(0x1000..0x1010);
+    block.free(0x1020..0x1040);
+    assert_eq!(block.free, [0x1000..0x1010, 0x1013..0x1100]);
+    block.free(0x1010..0x1013);
+    assert_eq!(block.free.len(), 1);
+    assert_eq!(block.free[0], 0x1000..0x1100);
+    assert_eq!(block.alloc(0x1000, 0x100), Some(0x1000));
+  }
+
+  #[test]
+  fn padding() {
+    let mut bytes = vec![0x90; 0x100];
+    bytes[0x10..0x30].fill(INT3);
+    bytes[0x40..0x5f].fill(INT3);
+    bytes[0xe0..].fill(INT3);
+    assert_eq!(
+      find_padding(&bytes, 0x1000),
+      [0x1010..0x1030, 0x10e0..0x1100]
+    );
+    assert_eq!(find_padding(&[INT3; MIN_PADDING - 1], 0), []);
+    assert_eq!(find_padding(&[], 0), []);
+  }
+
+  #[cfg(target_pointer_width = "64")]
+  #[test]
+  fn block_reach() {
+    let mut block = Block::new(0x1_0000_0000..0x1_0000_1000);
+    assert_eq!(block.alloc(0x1000, 0x10), None);
+    // Both ends of the allocation must be in reach.
+    assert_eq!(block.alloc(0x8000_0010, 0x10), None);
+    assert_eq!(block.alloc(0x8000_0011, 0x10), Some(0x1_0000_0000));
+  }
+
+  #[test]
+  fn reserve() {
+    let near = reserve as *const () as usize;
+    let block = reserve_near(near, BLOCK_SIZE).unwrap();
+    assert_eq!(block % BLOCK_SIZE, 0);
+    assert!(in_reach(near, block) && in_reach(near, block + BLOCK_SIZE));
+    assert!(!sys::query(block).unwrap().free);
+
+    let x = alloc_exec(near, 5).unwrap();
+    let y = alloc_exec(near, 0x20).unwrap();
+    assert_eq!((x % ALIGN, y % ALIGN), (0, 0));
+    assert!(x + ALIGN <= y || y + 0x20 <= x);
+    assert!(in_reach(near, x) && in_reach(near, y + 0x20));
+    assert!(is_generated(x) && is_generated(y + 0x1f));
+
+    unsafe {
+      ProcessCode::default().write(x, &[0xc3; 5]);
+      free_exec(x, 5);
+    }
+    // Freed memory is filled with `int3`s.
+    assert_eq!(unsafe { *(x as *const [u8; ALIGN]) }, [INT3; ALIGN]);
+    let block = allocator()
+      .blocks
+      .iter()
+      .find(|b| b.range.contains(&x))
+      .map(|b| b.free.iter().any(|r| r.contains(&x)));
+    assert_eq!(block, Some(true));
+  }
+}
@@ -0,0 +1,277 @@
+use crate::{x86, Uint2Iter};
+use core::fmt;
+
+/// How a byte at a patch location is checked.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ByteKind {
+  Exact,
+  Masked,
+  /// The first byte of a relocated value.
+  Reloc,
+  /// The remaining bytes of a relocated value.
+  RelocTail,
+}
+
+/// Iterates over how each of the patch's bytes is checked.
+pub(crate) fn byte_kinds(control_stream: &[u8], len: usize) -> impl '_ + Iterator<Item = ByteKind> {
+  let mut control_stream = Uint2Iter::new(control_stream);
+  let mut reloc_remain = 0u8;
+  (0..len).map(move |_| {
+    if reloc_remain != 0 {
+      reloc_remain -= 1;
+      ByteKind::RelocTail
+    } else {
+      match control_stream.next().unwrap_or(0) {
+        0 => ByteKind::Exact,
+        1 => ByteKind::Masked,
+        _ => {
+          reloc_remain = 3;
+          ByteKind::Reloc
+        }
+      }
+    }
+  })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForeignHookKind {
+  Call,
+  Jmp,
+}
+impl ForeignHookKind {
+  pub const fn name(self) -> &'static str {
+    match self {
+      Self::Call => "call",
+      Self::Jmp => "jmp",
+    }
+  }
+}
+
+/// A relative `call` or `jmp` found at a patch location which isn't part of the
+/// expected code. Usually means another mod has already patched the location.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ForeignHook {
+  pub kind: ForeignHookKind,
+  /// The instruction's position relative to the patch location.
+  pub pos: usize,
+  /// The absolute address the instruction targets.
+  pub target: usize,
+}
+
+/// What was found at a patch location which doesn't contain the expected
+/// bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Found {
+  /// The bytes at the location.
+  Bytes(Box<[u8]>),
+  /// The memory couldn't be read.
+  Unreadable,
+  /// The relocation distance doesn't fit in 32 bits, so relocated values
+  /// couldn't be compared.
+  RelocOverflow,
+}
+
+/// Details on why a patch location doesn't contain the expected bytes.
+#[derive(Debug, Clone)]
+pub struct Mismatch {
+  pub(crate) offset: usize,
+  pub(crate) address: usize,
+  pub(crate) reloc_dist: i32,
+  pub(crate) found: Found,
+  pub(crate) expected: &'static [u8],
+  pub(crate) control_stream: &'static [u8],
+}
+impl Mismatch {
+  /// The offset of the patch within the module.
+  #[inline]
+  pub fn offset(&self) -> usize {
+    self.offset
+  }
+
+  /// What was found at the patch location.
+  #[inline]
+  pub fn found(&self) -> &Found {
+    &self.found
+  }
+
+  /// The bytes found at the patch location. `None` if the bytes couldn't be
+  /// compared.
+  #[inline]
+  pub fn actual(&self) -> Option<&[u8]> {
+    match &self.found {
+      Found::Bytes(bytes) => Some(bytes),
+      Found::Unreadable | Found::RelocOverflow => None,
+    }
+  }
+
+  /// The expected bytes with masked bytes set to zero. `None` unless the
+  /// `expected-bytes` feature is enabled.
+  #[inline]
+  pub fn expected(&self) -> Option<&'static [u8]> {
+    (!self.expected.is_empty()).then_some(self.expected)
+  }
+
+  /// The bytes found at the patch location with relocated values adjusted back
+  /// to the module's preferred base address.
+  pub fn actual_unrelocated(&self) -> Option<Box<[u8]>> {
+    let mut bytes = Box::<[u8]>::from(self.actual()?);
+    let len = bytes.len();
+    for (i, kind) in byte_kinds(self.control_stream, len).enumerate() {
+      if kind == ByteKind::Reloc {
+        let value = &mut bytes[i..i + 4];
+        let x = i32::from_le_bytes((&*value).try_into().unwrap()).wrapping_sub(self.reloc_dist);
+        value.copy_from_slice(&x.to_le_bytes());
+      }
+    }
+    Some(bytes)
+  }
+
+  /// The position of the first byte which differs from the expected bytes.
+  /// `None` if either the expected or actual bytes are unavailable.
+  pub fn first_difference(&self) -> Option<usize> {
+    let expected = self.expected()?;
+    let actual = self.actual_unrelocated()?;
+    byte_kinds(self.control_stream, actual.len())
+      .zip(actual.iter().zip(expected))
+      .position(|(kind, (x, y))| kind != ByteKind::Masked && x != y)
+  }
+
+  /// Searches for a relative `call` or `jmp` at the first differing byte. Also
+  /// detects an expected `call` or `jmp` which has been retargeted.
+  pub fn foreign_hook(&self) -> Option<ForeignHook> {
+    let actual = self.actual()?;
+    let diff = self.first_difference().unwrap_or(0);
+    (diff.saturating_sub(4)..=diff)
+      .filter(|&pos| pos + 5 <= actual.len())
+      .find_map(|pos| {
+        let kind = match actual[pos] {
+          0xe8 => ForeignHookKind::Call,
+          0xe9 => ForeignHookKind::Jmp,
+          _ => return None,
+        };
+        let rel = i32::from_le_bytes(actual[pos + 1..pos + 5].try_into().unwrap());
+        let target = self.address.wrapping_add(pos + 5).wrapping_add(rel as usize);
+        Some(ForeignHook { kind, pos, target })
+      })
+  }
+
+  /// Searches for a relative `call` or `jmp` written over the expected code,
+  /// presumably by another mod. Only succeeds if the instruction replaced
+  /// whole instructions, padding any remaining space with `nop`s, and every
+  /// other byte matches. Requires the expected bytes.
+  pub fn overwritten_hook(&self) -> Option<ForeignHook> {
+    let expected = self.expected()?;
+    let actual = self.actual_unrelocated()?;
+    if actual.len() != expected.len() {
+      return None;
+    }
+    let hook = self.foreign_hook()?;
+    let hook_end = hook.pos + 5;
+    // Find the instructions the hook was written over.
+    let (mut start, mut end) = (0, 0);
+    while end < hook_end {
+      let inst = x86::decode(&expected[end..])?;
+      if end <= hook.pos {
+        start = end;
+      }
+      end += usize::from(inst.len);
+    }
+    byte_kinds(self.control_stream, actual.len())
+      .enumerate()
+      .all(|(i, kind)| {
+        (hook.pos..hook_end).contains(&i)
+          || kind == ByteKind::Masked
+          || actual[i] == expected[i]
+          || ((start..end).contains(&i) && actual[i] == 0x90)
+      })
+      .then_some(hook)
+  }
+
+  /// Searches for a relative `call` or `jmp` in the expected code which has been
+  /// retargeted, presumably by another mod. Only succeeds if that is the only
+  /// difference. Requires the expected bytes.
+  pub fn retargeted_hook(&self) -> Option<ForeignHook> {
+    let expected = self.expected()?;
+    let actual = self.actual_unrelocated()?;
+    if actual.len() != expected.len() {
+      return None;
+    }
+    let kinds: Vec<_> = byte_kinds(self.control_stream, actual.len()).collect();
+    let mut hook = None;
+    let mut pos = 0;
+    while pos < expected.len() {
+      let inst = x86::decode(&expected[pos..])?;
+      let end = pos + usize::from(inst.len);
+      let kind = match expected[pos] {
+        0xe8 => Some(ForeignHookKind::Call),
+        0xe9 => Some(ForeignHookKind::Jmp),
+        _ => None,
+      };
+      let differs = (pos..end).any(|i| kinds[i] != ByteKind::Masked && actual[i] != expected[i]);
+      match kind {
+        Some(kind) if differs && inst.len == 5 && actual[pos] == expected[pos] => {
+          if hook.is_some() {
+            return None;
+          }
+          let rel = i32::from_le_bytes(actual[pos + 1..end].try_into().unwrap());
+          let target = self.address.wrapping_add(end).wrapping_add(rel as usize);
+          hook = Some(ForeignHook { kind, pos, target });
+        }
+        _ if differs => return None,
+        _ => {}
+      }
+      pos = end;
+    }
+    hook
+  }
+
+  fn write_bytes(&self, f: &mut fmt::Formatter<'_>, bytes: &[u8], mask: bool) -> fmt::Result {
+    for (i, (kind, x)) in byte_kinds(self.control_stream, bytes.len()).zip(bytes).enumerate() {
+      if i != 0 && kind != ByteKind::RelocTail {
+        f.write_str(" ")?;
+      }
+      match kind {
+        ByteKind::Masked if mask => f.write_str("xx")?,
+        ByteKind::Reloc => write!(f, "${x:02x}")?,
+        _ => write!(f, "{x:02x}")?,
+      }
+    }
+    Ok(())
+  }
+}
+impl fmt::Display for Mismatch {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let Some(actual) = self.actual_unrelocated() else {
+      return match self.found {
+        Found::RelocOverflow => write!(
+          f,
+          "at {:#x}: the relocation distance doesn't fit in 32 bits",
+          self.offset
+        ),
+        _ => write!(f, "failed to read memory at {:#x}", self.offset),
+      };
+    };
+    write!(f, "at {:#x}: ", self.offset)?;
+    if let Some(expected) = self.expected() {
+      f.write_str("expected `")?;
+      self.write_bytes(f, expected, true)?;
+      f.write_str("`, ")?;
+    }
+    f.write_str("found `")?;
+    self.write_bytes(f, &actual, false)?;
+    f.write_str("`")?;
+    if let Some(diff) = self.first_difference() {
+      write!(f, "; first difference at +{diff:#x}")?;
+    }
+    if let Some(hook) = self.foreign_hook() {
+      write!(
+        f,
+        "; found a `{}` to {:#x} at +{:#x}",
+        hook.kind.name(),
+        hook.target,
+        hook.pos
+      )?;
+    }
+    Ok(())
+  }
+}
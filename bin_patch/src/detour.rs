@@ -0,0 +1,222 @@
+use crate::{
+  alloc::CodeSpace,
+  x86::{self, Branch, Instruction},
+};
+
+/// The general purpose registers and flags as saved by a detour before calling
+/// its target. Changes to any field other than `esp` are applied when the
+/// target returns.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Registers {
+  pub eflags: u32,
+  pub edi: u32,
+  pub esi: u32,
+  pub ebp: u32,
+  /// The stack pointer before the registers were saved.
+  pub esp: u32,
+  pub ebx: u32,
+  pub edx: u32,
+  pub ecx: u32,
+  pub eax: u32,
+}
+
+/// A general purpose register which holds a function argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reg {
+  Eax = 0,
+  Ecx = 1,
+  Edx = 2,
+  Ebx = 3,
+  Ebp = 5,
+  Esi = 6,
+  Edi = 7,
+}
+impl Reg {
+  /// Gets a register by its name (e.g. `esi`).
+  pub fn from_name(name: &str) -> Option<Self> {
+    Some(match name {
+      "eax" => Self::Eax,
+      "ecx" => Self::Ecx,
+      "edx" => Self::Edx,
+      "ebx" => Self::Ebx,
+      "ebp" => Self::Ebp,
+      "esi" => Self::Esi,
+      "edi" => Self::Edi,
+      _ => return None,
+    })
+  }
+}
+
+/// Copies the instructions from one address to another. Any relative branches
+/// will be adjusted to keep the same target. Fails if the code doesn't end on an
+/// instruction boundary or if it contains a branch which can't be moved.
+fn relocate(mut code: &[u8], from: usize, to: usize, dst: &mut Vec<u8>) -> Option<()> {
+  let from_end = from.wrapping_add(code.len());
+  let mut pos = from;
+  while !code.is_empty() {
+    let inst = x86::decode(code)?;
+    let (head, tail) = code.split_at(usize::from(inst.len));
+    let next = pos.wrapping_add(head.len());
+    match inst.branch {
+      Some(branch) => {
+        let imm = &head[usize::from(inst.imm.offset)..];
+        let rel = match inst.imm.size {
+          1 => imm[0] as i8 as isize,
+          2 => i16::from_le_bytes([imm[0], imm[1]]) as isize,
+          _ => i32::from_le_bytes([imm[0], imm[1], imm[2], imm[3]]) as isize,
+        };
+        let target = next.wrapping_add_signed(rel);
+        // The moved code will be replaced, so nothing can branch into it.
+        if from <= target && target < from_end {
+          return None;
+        }
+        match branch {
+          Branch::Call => push_branch(dst, 0xe8, to, target),
+          Branch::Jmp => push_branch(dst, 0xe9, to, target),
+          Branch::Jcc(cc) => {
+            dst.push(0x0f);
+            push_branch(dst, 0x80 | cc, to, target);
+          }
+          Branch::Loop => return None,
+        }
+      }
+      None => dst.extend_from_slice(head),
+    }
+    code = tail;
+    pos = next;
+  }
+  Some(())
+}
+
+/// Writes a `call` or `jmp` to the target at the end of the buffer.
+fn push_branch(dst: &mut Vec<u8>, op: u8, address: usize, target: usize) {
+  dst.push(op);
+  let next = address.wrapping_add(dst.len() + 4);
+  dst.extend_from_slice(&(target.wrapping_sub(next) as u32).to_le_bytes());
+}
+
+/// Builds a trampoline which saves all registers, calls the target, restores
+/// the registers, runs the code displaced by the detour and jumps back to the
+/// patched location. Returns the trampoline's address.
+///
+/// # Safety
+/// `target` must be safe to call with the saved registers.
+pub(crate) unsafe fn build_trampoline(
+  code: &[u8],
+  address: usize,
+  target: usize,
+  call_after: bool,
+  space: &mut impl CodeSpace,
+) -> Option<usize> {
+  // Branches are always written as rel32 so the size is independent of the
+  // trampoline's address.
+  let mut buf = Vec::with_capacity(code.len() * 2 + 18);
+  relocate(code, address, 0, &mut buf)?;
+  // The register saving and restoring, the call and the jump back.
+  let size = buf.len() + 18;
+  let trampoline = space.alloc(address, size)?;
+
+  buf.clear();
+  if call_after {
+    relocate(code, address, trampoline, &mut buf)?;
+  }
+  // pushad; pushfd; push esp
+  buf.extend_from_slice(&[0x60, 0x9c, 0x54]);
+  push_branch(&mut buf, 0xe8, trampoline, target);
+  // add esp, 4; popfd; popad
+  buf.extend_from_slice(&[0x83, 0xc4, 0x04, 0x9d, 0x61]);
+  if !call_after {
+    relocate(code, address, trampoline, &mut buf)?;
+  }
+  push_branch(&mut buf, 0xe9, trampoline, address.wrapping_add(code.len()));
+  debug_assert_eq!(buf.len(), size);
+
+  space.write(trampoline, &buf);
+  Some(trampoline)
+}
+
+/// Builds a stub for a conditional jump which was moved to change its target.
+/// The stub takes the jump to the new target, then runs the rest of the
+/// displaced code and jumps back to the patched location. Returns the stub's
+/// address.
+///
+/// # Safety
+/// `target` must be safe to jump to with the registers and stack at the jump.
+pub(crate) unsafe fn build_jcc_stub(
+  code: &[u8],
+  address: usize,
+  jcc: Instruction,
+  cc: u8,
+  target: usize,
+  space: &mut impl CodeSpace,
+) -> Option<usize> {
+  let rest = &code[usize::from(jcc.len)..];
+  let rest_address = address.wrapping_add(usize::from(jcc.len));
+  let build = |stub: usize, buf: &mut Vec<u8>| {
+    buf.push(0x0f);
+    push_branch(buf, 0x80 | cc, stub, target);
+    relocate(rest, rest_address, stub, buf)?;
+    push_branch(buf, 0xe9, stub, address.wrapping_add(code.len()));
+    Some(())
+  };
+
+  let mut buf = Vec::with_capacity(rest.len() * 2 + 11);
+  build(0, &mut buf)?;
+  let stub = space.alloc(address, buf.len())?;
+  buf.clear();
+  build(stub, &mut buf)?;
+  space.write(stub, &buf);
+  Some(stub)
+}
+
+/// Builds a thunk which calls another mod's hook, then calls the target and
+/// jumps back to `ret`. Without `ret` the thunk jumps to the target instead.
+/// Returns the thunk's address.
+///
+/// # Safety
+/// Both `hook` and `target` must be safe to call at the patched location.
+pub(crate) unsafe fn build_chain_thunk(
+  address: usize,
+  hook: usize,
+  target: usize,
+  ret: Option<usize>,
+  space: &mut impl CodeSpace,
+) -> Option<usize> {
+  let size = if ret.is_some() { 15 } else { 10 };
+  let thunk = space.alloc(address, size)?;
+  let mut buf = Vec::with_capacity(size);
+  push_branch(&mut buf, 0xe8, thunk, hook);
+  match ret {
+    Some(ret) => {
+      push_branch(&mut buf, 0xe8, thunk, target);
+      push_branch(&mut buf, 0xe9, thunk, ret);
+    }
+    None => push_branch(&mut buf, 0xe9, thunk, target),
+  }
+  space.write(thunk, &buf);
+  Some(thunk)
+}
+
+/// Builds a stub which pushes the arguments from registers, calls a `cdecl`
+/// target, then pops the arguments and returns. Returns the stub's address.
+///
+/// # Safety
+/// `target` must be safe to call with the arguments.
+pub(crate) unsafe fn build_reg_call_stub(
+  address: usize,
+  args: &[Reg],
+  target: usize,
+  space: &mut impl CodeSpace,
+) -> Option<usize> {
+  let size = args.len() + 9;
+  let stub = space.alloc(address, size)?;
+  let mut buf = Vec::with_capacity(size);
+  // Arguments are pushed from last to first.
+  buf.extend(args.iter().rev().map(|&r| 0x50 | r as u8));
+  push_branch(&mut buf, 0xe8, stub, target);
+  // add esp, 4 * args; ret
+  buf.extend_from_slice(&[0x83, 0xc4, 4 * args.len() as u8, 0xc3]);
+  space.write(stub, &buf);
+  Some(stub)
+}
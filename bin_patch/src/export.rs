@@ -0,0 +1,428 @@
+use crate::{alloc::CodeSpace, pe, Memory, Mismatch, ModuleImage, Patch, PatchData};
+use core::fmt;
+
+/// The reason a patch couldn't be exported.
+#[derive(Debug, Clone)]
+pub enum ExportError {
+  /// The patch location doesn't contain the expected bytes.
+  Mismatch(Mismatch),
+  /// The patch location doesn't contain only whole instructions.
+  PartialInstruction,
+  /// The patch location isn't stored in the module's file.
+  NotInFile,
+  /// The patch calls a function with no replacement in the module. Contains
+  /// the function's address in the current process.
+  Unresolved(usize),
+  /// The code displaced by a detour or moved conditional jump contains
+  /// relocated values, which would not be relocated in the trampoline.
+  RelocatedDetour,
+  /// The patch data contains addresses, which would not be relocated if the
+  /// module is loaded at a different address.
+  RelocatedData,
+  /// The patch data couldn't be written.
+  Write,
+  /// The module doesn't import a function needed by the lazy loader.
+  MissingImport(&'static str),
+}
+impl fmt::Display for ExportError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Mismatch(m) => write!(f, "unexpected code {m}"),
+      Self::PartialInstruction => f.write_str("location doesn't contain only whole instructions"),
+      Self::NotInFile => f.write_str("location isn't stored in the module's file"),
+      Self::Unresolved(target) => write!(f, "no replacement for the function at {target:#x}"),
+      Self::RelocatedDetour => f.write_str("detour displaces relocated code"),
+      Self::RelocatedData => f.write_str("patch data contains addresses"),
+      Self::Write => f.write_str("failed to write the patch"),
+      Self::MissingImport(name) => write!(f, "the module doesn't import `{name}`"),
+    }
+  }
+}
+
+/// Places code in the section added to an exported module.
+struct SectionCode<'a> {
+  /// The address the section is loaded at.
+  address: usize,
+  code: &'a mut Vec<u8>,
+  targets: &'a [(usize, usize)],
+}
+impl CodeSpace for SectionCode<'_> {
+  fn resolve(&mut self, target: usize) -> Option<usize> {
+    self
+      .targets
+      .iter()
+      .find_map(|&(x, address)| (x == target).then_some(address))
+  }
+
+  fn alloc(&mut self, _near: usize, len: usize) -> Option<usize> {
+    Some(push_code(self.address, self.code, len))
+  }
+
+  unsafe fn write(&mut self, address: usize, code: &[u8]) {
+    let start = address - self.address;
+    self.code[start..start + code.len()].copy_from_slice(code);
+  }
+}
+
+/// Reserves space for code at the end of the section. Returns the code's
+/// address.
+fn push_code(section: usize, code: &mut Vec<u8>, len: usize) -> usize {
+  // Align each function the same as a compiler would. Padding is filled with
+  // `int3` instructions.
+  let start = (code.len() + 15) & !15;
+  code.resize(start, 0xcc);
+  code.resize(start + len, 0);
+  section + start
+}
+
+/// Builds the code shared by the lazy loader's stubs. The code saves every
+/// register, loads the library and calls its resolver with the stub's address
+/// and the function's address relative to the library. The call then continues
+/// to the address the resolver returns. Hits a breakpoint if the library or
+/// the resolver can't be loaded.
+///
+/// Imports and strings are addressed relative to the code, so this still works
+/// if the module is relocated.
+fn lazy_resolver(
+  address: usize,
+  load_library: usize,
+  get_proc_address: usize,
+  library: &str,
+  resolver: &str,
+) -> Vec<u8> {
+  const STRINGS: usize = 69;
+  // The address loaded into `ebx`.
+  let here = address + 7;
+  let rel = |x: usize| (x.wrapping_sub(here) as u32).to_le_bytes();
+
+  // pushad; pushfd; call $+5; pop ebx
+  let mut code = vec![0x60, 0x9c, 0xe8, 0x00, 0x00, 0x00, 0x00, 0x5b];
+  // lea eax, [ebx + library]; push eax; call [ebx + LoadLibraryA]
+  code.extend_from_slice(&[0x8d, 0x83]);
+  code.extend_from_slice(&rel(address + STRINGS));
+  code.extend_from_slice(&[0x50, 0xff, 0x93]);
+  code.extend_from_slice(&rel(load_library));
+  // test eax, eax; jz fail; lea ecx, [ebx + resolver]; push ecx; push eax;
+  // call [ebx + GetProcAddress]
+  code.extend_from_slice(&[0x85, 0xc0, 0x74, 0x2b, 0x8d, 0x8b]);
+  code.extend_from_slice(&rel(address + STRINGS + library.len() + 1));
+  code.extend_from_slice(&[0x51, 0x50, 0xff, 0x93]);
+  code.extend_from_slice(&rel(get_proc_address));
+  #[rustfmt::skip]
+  const CALL_RESOLVER: [u8; 30] = [
+    // test eax, eax; jz fail
+    0x85, 0xc0, 0x74, 0x19,
+    // mov ecx, [esp + 0x24]; sub ecx, 18
+    0x8b, 0x4c, 0x24, 0x24, 0x83, 0xe9, 0x12,
+    // push [esp + 0x28]; push ecx; call eax
+    0xff, 0x74, 0x24, 0x28, 0x51, 0xff, 0xd0,
+    // mov [esp + 0x28], eax; popfd; popad
+    0x89, 0x44, 0x24, 0x28, 0x9d, 0x61,
+    // lea esp, [esp + 4]; ret
+    0x8d, 0x64, 0x24, 0x04, 0xc3,
+    // fail: int3
+    0xcc,
+  ];
+  code.extend_from_slice(&CALL_RESOLVER);
+  debug_assert_eq!(code.len(), STRINGS);
+  code.extend_from_slice(library.as_bytes());
+  code.push(0);
+  code.extend_from_slice(resolver.as_bytes());
+  code.push(0);
+  code
+}
+
+/// Builds a stub which jumps to the lazy loader's resolver until the resolver
+/// overwrites the jump with one to the function. The jump's offset is aligned
+/// so it can be overwritten while other threads run the stub.
+fn lazy_stub(address: usize, rva: u32, resolver: usize) -> [u8; 18] {
+  let call = (resolver.wrapping_sub(address + 18) as u32).to_le_bytes();
+  let rva = rva.to_le_bytes();
+  #[rustfmt::skip]
+  let stub = [
+    // nop; nop; nop; jmp $+5
+    0x90, 0x90, 0x90, 0xe9, 0x00, 0x00, 0x00, 0x00,
+    // push rva
+    0x68, rva[0], rva[1], rva[2], rva[3],
+    // call resolver
+    0xe8, call[0], call[1], call[2], call[3],
+  ];
+  stub
+}
+
+/// A module's file with patches applied to it. Used to distribute patches as a
+/// static patch file for setups which can't load the patches at runtime.
+///
+/// Any functions called by the patches must be given a replacement within the
+/// module, either via `add_code` and `resolve`, or by loading them from a
+/// library with `add_lazy_loader`. Trampolines for detours are placed in the
+/// same section as the added code.
+pub struct PatchedFile {
+  file: Vec<u8>,
+  image: ModuleImage<Vec<u8>>,
+  /// The code to add to the module in a new section.
+  code: Vec<u8>,
+  /// The address the new section is loaded at.
+  code_address: usize,
+  /// The replacement address for each function called by the patches.
+  targets: Vec<(usize, usize)>,
+  /// The address of the lazy loader's resolver and the base address of the
+  /// library functions are loaded from.
+  lazy_loader: Option<(usize, usize)>,
+}
+impl PatchedFile {
+  /// Loads the contents of a PE file (e.g. `D2Client.dll`). Addresses are
+  /// relative to the module's preferred base address.
+  pub fn new(file: Vec<u8>) -> Option<Self> {
+    let image = ModuleImage::from_pe_file(&file)?;
+    let code_address = image.base() + pe::next_section_rva(&file)? as usize;
+    Some(Self {
+      file,
+      image,
+      code: Vec::new(),
+      code_address,
+      targets: Vec::new(),
+      lazy_loader: None,
+    })
+  }
+
+  /// The module's image with all patches applied so far.
+  #[inline]
+  pub fn image(&self) -> &ModuleImage<Vec<u8>> {
+    &self.image
+  }
+
+  /// The address the next code added will be placed at.
+  #[inline]
+  pub fn next_code_address(&self) -> usize {
+    (self.code_address + self.code.len() + 15) & !15
+  }
+
+  /// Adds code to the module's new section. Returns the code's address. The
+  /// code will not be relocated if the module is loaded at a different address.
+  pub fn add_code(&mut self, code: &[u8]) -> usize {
+    let address = push_code(self.code_address, &mut self.code, code.len());
+    let start = address - self.code_address;
+    self.code[start..].copy_from_slice(code);
+    address
+  }
+
+  /// Replaces calls to the function at the given address in the current process
+  /// with calls to the given address in the module.
+  pub fn resolve(&mut self, target: usize, address: usize) {
+    self.targets.retain(|&(x, _)| x != target);
+    self.targets.push((target, address));
+  }
+
+  /// Loads functions without a replacement from a library the first time
+  /// they're called. The library is loaded with `LoadLibraryA`, which the
+  /// module must import from `kernel32.dll` along with `GetProcAddress`.
+  ///
+  /// `resolver` is the name of a function exported by the library with the
+  /// signature `extern "stdcall" fn(stub: usize, rva: u32) -> usize`. It's
+  /// given the stub's address and the function's address relative to the
+  /// library, and returns the function's address. It may write a `rel32` jump
+  /// to the function at `stub + 4` so later calls skip the loader. `base` is the
+  /// library's base address in the current process, so the library must be
+  /// the same build when the patched module runs.
+  pub fn add_lazy_loader(
+    &mut self,
+    library: &str,
+    resolver: &str,
+    base: usize,
+  ) -> Result<(), ExportError> {
+    let image = self.image.bytes();
+    let import = |name| {
+      pe::import_slot(image, "kernel32.dll", name)
+        .map(|slot| self.image.base() + slot)
+        .ok_or(ExportError::MissingImport(name))
+    };
+    let load_library = import("LoadLibraryA")?;
+    let get_proc_address = import("GetProcAddress")?;
+    let address = self.next_code_address();
+    let code = lazy_resolver(address, load_library, get_proc_address, library, resolver);
+    self.add_code(&code);
+    self.lazy_loader = Some((address, base));
+    Ok(())
+  }
+
+  /// Checks the patch against the module's original code and applies it.
+  pub fn apply(&mut self, patch: &Patch) -> Result<(), ExportError> {
+    patch.check(&self.image, 0).map_err(ExportError::Mismatch)?;
+    if !patch.has_whole_instructions(&self.image) {
+      return Err(ExportError::PartialInstruction);
+    }
+    pe::Headers::parse(&self.file)
+      .and_then(|h| h.file_offset(patch.range()))
+      .ok_or(ExportError::NotInFile)?;
+
+    let range = patch.range();
+    let relocs: Vec<_> = pe::relocs(self.image.bytes())
+      .ok_or(ExportError::NotInFile)?
+      .into_iter()
+      .filter(|&(_, address)| address < range.end && address + 4 > range.start)
+      .collect();
+    let target = match patch.data {
+      PatchData::Target { target, .. }
+      | PatchData::RegCall { target, .. }
+      | PatchData::Jmp { target } => Some(target),
+      PatchData::Detour { target, .. } | PatchData::Jcc { target } => {
+        if !relocs.is_empty() {
+          return Err(ExportError::RelocatedDetour);
+        }
+        Some(target)
+      }
+      PatchData::Raw { relocs, module_relocs, .. } => {
+        if !relocs.is_empty() || !module_relocs.is_empty() {
+          return Err(ExportError::RelocatedData);
+        }
+        None
+      }
+    };
+    if let Some(target) = target.map(|x| x as usize) {
+      if !self.targets.iter().any(|&(x, _)| x == target) {
+        let Some((resolver, base)) = self.lazy_loader else {
+          return Err(ExportError::Unresolved(target));
+        };
+        let address = self.next_code_address();
+        let stub = lazy_stub(address, target.wrapping_sub(base) as u32, resolver);
+        self.add_code(&stub);
+        self.targets.push((target, address));
+      }
+    }
+
+    let mut space = SectionCode {
+      address: self.code_address,
+      code: &mut self.code,
+      targets: &self.targets,
+    };
+    // Safety: Writes only to the module's image and the new section.
+    unsafe { patch.write(&mut self.image, 0, &mut space, None) }.ok_or(ExportError::Write)?;
+
+    // The relocated values were overwritten by the patch.
+    let image = self.image.bytes_mut();
+    for (pos, _) in relocs {
+      image[pos..pos + 2].fill(0);
+    }
+    Ok(())
+  }
+
+  /// Builds the patched file. Any code added is placed in a new section named
+  /// `.patch`. Returns `None` if there is no room for a new section.
+  pub fn finish(mut self) -> Option<Vec<u8>> {
+    pe::unmap_image(&mut self.file, self.image.bytes())?;
+    if !self.code.is_empty() {
+      pe::append_code_section(&mut self.file, *b".patch\0\0", &self.code)?;
+    }
+    Some(self.file)
+  }
+}
+
+/// Writes an IPS patch which converts the source file into the target file.
+/// Returns `None` if the target file is smaller than the source or is too
+/// large for the format.
+pub fn write_ips(source: &[u8], target: &[u8]) -> Option<Vec<u8>> {
+  // Offsets are stored as 24-bit values and truncation isn't supported.
+  if target.len() < source.len() || target.len() > 0x100_0000 {
+    return None;
+  }
+  let mut out = b"PATCH".to_vec();
+  let mut i = 0;
+  while i < target.len() {
+    if source.get(i) == Some(&target[i]) {
+      i += 1;
+      continue;
+    }
+    // A record at the offset which spells `EOF` would end the patch early.
+    let start = if i == 0x45_4f46 { i - 1 } else { i };
+    // Extend the record over short runs of unchanged bytes as that's smaller
+    // than starting a new record.
+    let mut end = i + 1;
+    let mut pos = end;
+    while pos < target.len() && pos - start < 0xffff && pos - end < 5 {
+      pos += 1;
+      if source.get(pos - 1) != Some(&target[pos - 1]) {
+        end = pos;
+      }
+    }
+    out.extend_from_slice(&(start as u32).to_be_bytes()[1..]);
+    out.extend_from_slice(&((end - start) as u16).to_be_bytes());
+    out.extend_from_slice(&target[start..end]);
+    i = end;
+  }
+  out.extend_from_slice(b"EOF");
+  Some(out)
+}
+
+/// Writes a variable length number as used by the BPS format.
+fn push_bps_num(out: &mut Vec<u8>, mut x: u64) {
+  loop {
+    let byte = (x & 0x7f) as u8;
+    x >>= 7;
+    if x == 0 {
+      out.push(byte | 0x80);
+      break;
+    }
+    out.push(byte);
+    x -= 1;
+  }
+}
+
+/// Writes a BPS patch which converts the source file into the target file.
+pub fn write_bps(source: &[u8], target: &[u8], metadata: &str) -> Vec<u8> {
+  const SOURCE_READ: u64 = 0;
+  const TARGET_READ: u64 = 1;
+
+  let mut out = b"BPS1".to_vec();
+  push_bps_num(&mut out, source.len() as u64);
+  push_bps_num(&mut out, target.len() as u64);
+  push_bps_num(&mut out, metadata.len() as u64);
+  out.extend_from_slice(metadata.as_bytes());
+  let mut i = 0;
+  while i < target.len() {
+    let same = source.get(i) == Some(&target[i]);
+    let end = target[i..]
+      .iter()
+      .zip(i..)
+      .position(|(x, j)| (source.get(j) == Some(x)) != same)
+      .map_or(target.len(), |len| i + len);
+    let action = if same { SOURCE_READ } else { TARGET_READ };
+    push_bps_num(&mut out, ((end - i - 1) as u64) << 2 | action);
+    if !same {
+      out.extend_from_slice(&target[i..end]);
+    }
+    i = end;
+  }
+  out.extend_from_slice(&crc32(source).to_le_bytes());
+  out.extend_from_slice(&crc32(target).to_le_bytes());
+  let crc = crc32(&out);
+  out.extend_from_slice(&crc.to_le_bytes());
+  out
+}
+
+const CRC_TABLE: [u32; 256] = {
+  let mut table = [0u32; 256];
+  let mut i = 0;
+  while i < 256 {
+    let mut x = i as u32;
+    let mut j = 0;
+    while j < 8 {
+      x = if x & 1 != 0 {
+        (x >> 1) ^ 0xedb8_8320
+      } else {
+        x >> 1
+      };
+      j += 1;
+    }
+    table[i] = x;
+    i += 1;
+  }
+  table
+};
+
+/// Computes the CRC-32 checksum used by zip and BPS.
+pub fn crc32(bytes: &[u8]) -> u32 {
+  !bytes.iter().fold(!0u32, |crc, &x| {
+    CRC_TABLE[usize::from(crc as u8 ^ x)] ^ (crc >> 8)
+  })
+}
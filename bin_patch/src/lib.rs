@@ -1,10 +1,37 @@
-use core::{ffi::c_void, mem::transmute, ptr::null_mut, slice};
-use windows_sys::Win32::{
-  Foundation::HMODULE,
-  System::Memory::{VirtualProtect, PAGE_EXECUTE_READWRITE},
+use core::{
+  fmt,
+  mem::{transmute, ManuallyDrop},
+  ops::Range,
+  ptr, slice,
 };
 
-pub use bin_patch_mac::patch_source;
+mod alloc;
+mod check;
+mod detour;
+mod export;
+mod mem;
+mod parse;
+mod pe;
+mod set;
+mod sys;
+
+use crate::{
+  alloc::{CodeSpace, ProcessCode},
+  check::{byte_kinds, ByteKind},
+  x86::Branch,
+};
+use bin_patch_syntax::source;
+
+pub use crate::{
+  check::{ForeignHook, ForeignHookKind, Found, Mismatch},
+  detour::{Reg, Registers},
+  export::{crc32, write_bps, write_ips, ExportError, PatchedFile},
+  mem::{LoadedModule, Memory, MemoryMut, ModuleImage},
+  parse::{parse_patches, Hook, ParseError},
+  set::{AppliedPatchSet, ChainedHook, PatchError, PatchSet, PatchSetError},
+};
+pub use bin_patch_mac::{asm_patch, patch_data, patch_source, patch_versions};
+pub use bin_patch_syntax::x86;
 
 struct Uint2Iter<'a> {
   iter: slice::Iter<'a, u8>,
@@ -31,141 +58,591 @@ impl Iterator for Uint2Iter<'_> {
   }
 }
 
-struct MemUnlock {
-  prev: u32,
-  address: *const c_void,
-  len: usize,
-}
-impl MemUnlock {
-  unsafe fn new(address: *const c_void, len: usize) -> Result<Self, ()> {
-    let mut prev = 0u32;
-    if VirtualProtect(address, len, PAGE_EXECUTE_READWRITE, &mut prev) != 0 {
-      Ok(Self { prev, address, len })
-    } else {
-      Err(())
-    }
-  }
-}
-impl Drop for MemUnlock {
-  fn drop(&mut self) {
-    unsafe {
-      VirtualProtect(self.address, self.len, self.prev, null_mut());
-    }
-  }
-}
-
+#[derive(Clone, Copy)]
 enum PatchData {
   Target {
     target: unsafe extern "C" fn(),
     offset: u16,
   },
-  Raw(&'static [u8]),
+  Detour {
+    target: unsafe extern "C" fn(),
+    call_after: bool,
+  },
+  /// A call through a stub which pushes the arguments from registers.
+  RegCall {
+    target: unsafe extern "C" fn(),
+    /// The register holding each argument, in order.
+    args: [Reg; 3],
+    count: u8,
+  },
+  Jmp {
+    target: unsafe extern "C" fn(),
+  },
+  Jcc {
+    target: unsafe extern "C" fn(),
+  },
+  Raw {
+    data: &'static [u8],
+    /// The position of each absolute address adjusted by the relocation
+    /// distance.
+    relocs: &'static [u16],
+    /// The position of each module-relative value converted to an address.
+    module_relocs: &'static [u16],
+    /// The position of each `rel32` holding the absolute address of a branch's
+    /// target. The address is adjusted by the relocation distance and made
+    /// relative to the end of the `rel32`.
+    branch_relocs: &'static [u16],
+  },
+}
+
+/// An error from searching a module for a patch's location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanError {
+  /// The module's code sections could not be read.
+  InvalidModule,
+  /// The expected bytes were not found.
+  NotFound,
+  /// The expected bytes were found at each of the given offsets.
+  Ambiguous(Vec<usize>),
+  /// The relocation distance doesn't fit in 32 bits.
+  RelocOverflow,
+}
+impl fmt::Display for ScanError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::InvalidModule => f.write_str("failed to read the module's code sections"),
+      Self::NotFound => f.write_str("no match found"),
+      Self::RelocOverflow => f.write_str("the relocation distance doesn't fit in 32 bits"),
+      Self::Ambiguous(offsets) => {
+        f.write_str("multiple matches found at: ")?;
+        for (i, offset) in offsets.iter().enumerate() {
+          if i != 0 {
+            f.write_str(", ")?;
+          }
+          write!(f, "{offset:#x}")?;
+        }
+        Ok(())
+      }
+    }
+  }
 }
 
 /// A code patch which can be applied to a loaded module.
+#[derive(Clone, Copy)]
 pub struct Patch {
   pub offset: usize,
   len: u16,
   hash: u32,
   control_stream: &'static [u8],
+  /// The expected bytes with masked bytes set to zero. Empty unless the
+  /// `expected-bytes` feature is enabled.
+  expected: &'static [u8],
+  /// Whether the referenced bytes are code rather than data.
+  is_code: bool,
   data: PatchData,
 }
+/// The length, hash, control stream, expected bytes and kind of the code
+/// referenced by a patch, as produced by `patch_source!`.
+type PatchSource = (u16, u32, &'static [u8], &'static [u8], bool);
+
 impl Patch {
-  /// Create a patch which replaces the referenced code with a call to a `cdecl`
-  /// function taking zero arguments.
-  pub const fn call_c<R>(
+  const fn new(
     offset: usize,
-    (len, hash, control_stream): (u16, u32, &'static [u8]),
-    target: unsafe extern "C" fn() -> R,
+    (len, hash, control_stream, expected, is_code): PatchSource,
+    data: PatchData,
   ) -> Self {
     Self {
       offset,
       len,
       hash,
       control_stream,
-      data: PatchData::Target { target: unsafe { transmute(target) }, offset: 0 },
+      expected,
+      is_code,
+      data,
     }
   }
 
+  /// Creates a patch which calls the target at the start of the referenced
+  /// code.
+  const fn call(offset: usize, src: PatchSource, target: unsafe extern "C" fn()) -> Self {
+    Self::new(offset, src, PatchData::Target { target, offset: 0 })
+  }
+
+  /// Creates a patch which calls the target through a stub which pushes the
+  /// arguments from registers.
+  const fn call_regs(
+    offset: usize,
+    src: PatchSource,
+    target: unsafe extern "C" fn(),
+    args: [Reg; 3],
+    count: u8,
+  ) -> Self {
+    Self::new(offset, src, PatchData::RegCall { target, args, count })
+  }
+
+  /// Create a patch which replaces the referenced code with a call to a `cdecl`
+  /// function taking zero arguments.
+  pub const fn call_c<R>(
+    offset: usize,
+    src: PatchSource,
+    target: unsafe extern "C" fn() -> R,
+  ) -> Self {
+    Self::call(offset, src, unsafe {
+      transmute::<unsafe extern "C" fn() -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
   /// Create a patch which replaces the referenced code with a call to a `cdecl`
   /// function taking zero arguments. Places the call at the end of the patched
   /// location.
   pub const fn call_c_at<R>(
     offset: usize,
-    (len, hash, control_stream): (u16, u32, &'static [u8]),
+    src: PatchSource,
     target: unsafe extern "C" fn() -> R,
     call_offset: u16,
   ) -> Self {
-    Self {
+    let target =
+      unsafe { transmute::<unsafe extern "C" fn() -> R, unsafe extern "C" fn()>(target) };
+    Self::new(
       offset,
-      len,
-      hash,
-      control_stream,
-      data: PatchData::Target {
-        target: unsafe { transmute(target) },
-        offset: call_offset,
-      },
-    }
+      src,
+      PatchData::Target { target, offset: call_offset },
+    )
+  }
+
+  /// Create a patch which replaces the referenced code with a call to a `cdecl`
+  /// function taking one argument from a register, for code which calls a
+  /// function taking its argument in a register (e.g. `@ esi`).
+  ///
+  /// The call goes through a stub which pushes the argument and removes it
+  /// after the call. As with any `cdecl` call, `eax`, `ecx` and `edx` aren't
+  /// preserved.
+  pub const fn call_c1<T1, R>(
+    offset: usize,
+    src: PatchSource,
+    target: unsafe extern "C" fn(T1) -> R,
+    arg: Reg,
+  ) -> Self {
+    let target =
+      unsafe { transmute::<unsafe extern "C" fn(T1) -> R, unsafe extern "C" fn()>(target) };
+    Self::call_regs(offset, src, target, [arg, arg, arg], 1)
+  }
+
+  /// Create a patch which replaces the referenced code with a call to a `cdecl`
+  /// function taking two arguments from registers. See `call_c1`.
+  pub const fn call_c2<T1, T2, R>(
+    offset: usize,
+    src: PatchSource,
+    target: unsafe extern "C" fn(T1, T2) -> R,
+    args: [Reg; 2],
+  ) -> Self {
+    let target =
+      unsafe { transmute::<unsafe extern "C" fn(T1, T2) -> R, unsafe extern "C" fn()>(target) };
+    Self::call_regs(offset, src, target, [args[0], args[1], args[1]], 2)
+  }
+
+  /// Create a patch which replaces the referenced code with a call to a `cdecl`
+  /// function taking three arguments from registers. See `call_c1`.
+  pub const fn call_c3<T1, T2, T3, R>(
+    offset: usize,
+    src: PatchSource,
+    target: unsafe extern "C" fn(T1, T2, T3) -> R,
+    args: [Reg; 3],
+  ) -> Self {
+    let target =
+      unsafe { transmute::<unsafe extern "C" fn(T1, T2, T3) -> R, unsafe extern "C" fn()>(target) };
+    Self::call_regs(offset, src, target, args, 3)
   }
 
   /// Create a patch which replaces the referenced code with a call to a
   /// `stdcall` function taking one argument.
+  #[cfg(target_arch = "x86")]
   pub const fn call_std1<T1, R>(
     offset: usize,
-    (len, hash, control_stream): (u16, u32, &'static [u8]),
+    src: PatchSource,
     target: unsafe extern "stdcall" fn(T1) -> R,
   ) -> Self {
-    Self {
-      offset,
-      len,
-      hash,
-      control_stream,
-      data: PatchData::Target { target: unsafe { transmute(target) }, offset: 0 },
-    }
+    Self::call(offset, src, unsafe {
+      transmute::<unsafe extern "stdcall" fn(T1) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// Create a patch which replaces the referenced code with a call to a
+  /// `stdcall` function taking two arguments.
+  #[cfg(target_arch = "x86")]
+  pub const fn call_std2<T1, T2, R>(
+    offset: usize,
+    src: PatchSource,
+    target: unsafe extern "stdcall" fn(T1, T2) -> R,
+  ) -> Self {
+    Self::call(offset, src, unsafe {
+      transmute::<unsafe extern "stdcall" fn(T1, T2) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// Create a patch which replaces the referenced code with a call to a
+  /// `stdcall` function taking three arguments.
+  #[cfg(target_arch = "x86")]
+  pub const fn call_std3<T1, T2, T3, R>(
+    offset: usize,
+    src: PatchSource,
+    target: unsafe extern "stdcall" fn(T1, T2, T3) -> R,
+  ) -> Self {
+    Self::call(offset, src, unsafe {
+      transmute::<unsafe extern "stdcall" fn(T1, T2, T3) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// Create a patch which replaces the referenced code with a call to a
+  /// `fastcall` function taking one argument in `ecx`.
+  #[cfg(target_arch = "x86")]
+  pub const fn call_fast1<T1, R>(
+    offset: usize,
+    src: PatchSource,
+    target: unsafe extern "fastcall" fn(T1) -> R,
+  ) -> Self {
+    Self::call(offset, src, unsafe {
+      transmute::<unsafe extern "fastcall" fn(T1) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// Create a patch which replaces the referenced code with a call to a
+  /// `fastcall` function taking two arguments in `ecx` and `edx`.
+  #[cfg(target_arch = "x86")]
+  pub const fn call_fast2<T1, T2, R>(
+    offset: usize,
+    src: PatchSource,
+    target: unsafe extern "fastcall" fn(T1, T2) -> R,
+  ) -> Self {
+    Self::call(offset, src, unsafe {
+      transmute::<unsafe extern "fastcall" fn(T1, T2) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// Create a patch which replaces the referenced code with a call to a
+  /// `fastcall` function taking two arguments in `ecx` and `edx`, and a third on
+  /// the stack.
+  #[cfg(target_arch = "x86")]
+  pub const fn call_fast3<T1, T2, T3, R>(
+    offset: usize,
+    src: PatchSource,
+    target: unsafe extern "fastcall" fn(T1, T2, T3) -> R,
+  ) -> Self {
+    Self::call(offset, src, unsafe {
+      transmute::<unsafe extern "fastcall" fn(T1, T2, T3) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// Create a patch which replaces the referenced code with a call to a
+  /// `thiscall` function taking only the `this` pointer in `ecx`.
+  #[cfg(target_arch = "x86")]
+  pub const fn call_this1<T1, R>(
+    offset: usize,
+    src: PatchSource,
+    target: unsafe extern "thiscall" fn(T1) -> R,
+  ) -> Self {
+    Self::call(offset, src, unsafe {
+      transmute::<unsafe extern "thiscall" fn(T1) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// Create a patch which replaces the referenced code with a call to a
+  /// `thiscall` function taking the `this` pointer in `ecx` and one argument on
+  /// the stack.
+  #[cfg(target_arch = "x86")]
+  pub const fn call_this2<T1, T2, R>(
+    offset: usize,
+    src: PatchSource,
+    target: unsafe extern "thiscall" fn(T1, T2) -> R,
+  ) -> Self {
+    Self::call(offset, src, unsafe {
+      transmute::<unsafe extern "thiscall" fn(T1, T2) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// Create a patch which replaces the referenced code with a jump to a `cdecl`
+  /// function taking zero arguments. The function returns directly to the
+  /// patched function's caller, so it must clean up the stack the same way the
+  /// patched function would. Typically used with an asm stub.
+  pub const fn jmp_c<R>(
+    offset: usize,
+    src: PatchSource,
+    target: unsafe extern "C" fn() -> R,
+  ) -> Self {
+    let target =
+      unsafe { transmute::<unsafe extern "C" fn() -> R, unsafe extern "C" fn()>(target) };
+    Self::new(offset, src, PatchData::Jmp { target })
+  }
+
+  /// Create a patch which changes the destination of the conditional jump at
+  /// the start of the referenced code to the target, keeping its condition. The
+  /// target is jumped to, not called, with the registers and stack as they were
+  /// at the jump.
+  ///
+  /// A near jump is rewritten in place, leaving the rest of the referenced code
+  /// unchanged. A short jump can't reach the target, so it's moved into a stub
+  /// along with the rest of the referenced code, which must then be at least
+  /// five bytes. See `detour` for the requirements on the moved code.
+  pub const fn retarget_jcc(
+    offset: usize,
+    src: PatchSource,
+    target: unsafe extern "C" fn(),
+  ) -> Self {
+    Self::new(offset, src, PatchData::Jcc { target })
+  }
+
+  /// Create a patch which moves the referenced code into a trampoline and
+  /// jumps to it. The trampoline calls the target with the saved registers
+  /// before running the moved code and jumping back.
+  ///
+  /// The referenced code must be at least five bytes, start and end on
+  /// instruction boundaries, and must not contain a branch into itself.
+  pub const fn detour(
+    offset: usize,
+    src: PatchSource,
+    target: unsafe extern "C" fn(&mut Registers),
+  ) -> Self {
+    let target =
+      unsafe { transmute::<unsafe extern "C" fn(&mut Registers), unsafe extern "C" fn()>(target) };
+    Self::new(offset, src, PatchData::Detour { target, call_after: false })
+  }
+
+  /// Create a patch which moves the referenced code into a trampoline and
+  /// jumps to it. The trampoline runs the moved code, then calls the target with
+  /// the saved registers before jumping back.
+  ///
+  /// See `detour` for the requirements on the referenced code.
+  pub const fn detour_after(
+    offset: usize,
+    src: PatchSource,
+    target: unsafe extern "C" fn(&mut Registers),
+  ) -> Self {
+    let target =
+      unsafe { transmute::<unsafe extern "C" fn(&mut Registers), unsafe extern "C" fn()>(target) };
+    Self::new(offset, src, PatchData::Detour { target, call_after: true })
   }
 
   /// Create a patch which replaces the referenced code with code that does
   /// nothing.
-  pub const fn nop(offset: usize, (len, hash, control_stream): (u16, u32, &'static [u8])) -> Self {
-    Self {
+  pub const fn nop(offset: usize, src: PatchSource) -> Self {
+    Self::new(
       offset,
-      len,
-      hash,
-      control_stream,
-      data: PatchData::Raw(&[]),
-    }
+      src,
+      PatchData::Raw {
+        data: &[],
+        relocs: &[],
+        module_relocs: &[],
+        branch_relocs: &[],
+      },
+    )
   }
 
   /// Create a patch which replaces the referenced code with the given code.
-  pub const fn raw(
+  pub const fn raw(offset: usize, src: PatchSource, data: &'static [u8]) -> Self {
+    Self::new(
+      offset,
+      src,
+      PatchData::Raw {
+        data,
+        relocs: &[],
+        module_relocs: &[],
+        branch_relocs: &[],
+      },
+    )
+  }
+
+  /// Create a patch which replaces the referenced code with code from
+  /// `patch_data!` or `asm_patch!`. Absolute addresses are adjusted by the
+  /// relocation distance, module-relative values are converted to addresses and
+  /// branches to absolute addresses are made relative when the patch is
+  /// applied.
+  pub const fn raw_reloc(
     offset: usize,
-    (len, hash, control_stream): (u16, u32, &'static [u8]),
-    data: &'static [u8],
+    src: PatchSource,
+    (data, relocs, module_relocs, branch_relocs): (
+      &'static [u8],
+      &'static [u16],
+      &'static [u16],
+      &'static [u16],
+    ),
   ) -> Self {
-    Self {
+    Self::new(
       offset,
-      len,
-      hash,
-      control_stream,
-      data: PatchData::Raw(data),
+      src,
+      PatchData::Raw { data, relocs, module_relocs, branch_relocs },
+    )
+  }
+
+  /// The range of the module the patch replaces.
+  #[inline]
+  pub fn range(&self) -> Range<usize> {
+    self.offset..self.offset + usize::from(self.len)
+  }
+
+  /// The number of bytes needed to write the patch's data.
+  fn data_len(&self) -> usize {
+    match self.data {
+      PatchData::Target { offset, .. } => usize::from(offset) + 5,
+      PatchData::Detour { .. }
+      | PatchData::RegCall { .. }
+      | PatchData::Jmp { .. }
+      | PatchData::Jcc { .. } => 5,
+      // Data which doesn't fit is placed out of line behind a `jmp`.
+      PatchData::Raw { data, .. } if data.len() > usize::from(self.len) => 5,
+      PatchData::Raw { data, .. } => data.len(),
     }
   }
 
   /// Checks if the memory at the patch location contains the expected bytes.
-  ///
-  /// # Safety
-  /// The memory in the slice `(base + patch.offset, patch.len)` must be
-  /// initialized and readable.
-  pub unsafe fn has_expected(&self, base: HMODULE, reloc_dist: isize) -> bool {
-    let address = (base as usize + self.offset) as *mut c_void;
-    let Ok(_mem) = MemUnlock::new(address, self.len.into()) else {
-      return false;
+  pub fn has_expected(&self, mem: &impl Memory, reloc_dist: isize) -> bool {
+    self.check(mem, reloc_dist).is_ok()
+  }
+
+  /// Checks if the memory at the patch location contains the expected bytes,
+  /// returning the details of any difference.
+  pub fn check(&self, mem: &impl Memory, reloc_dist: isize) -> Result<(), Mismatch> {
+    let mismatch = |reloc_dist, found| Mismatch {
+      offset: self.offset,
+      address: mem.base().wrapping_add(self.offset),
+      reloc_dist,
+      found,
+      expected: self.expected,
+      control_stream: self.control_stream,
     };
     let Ok(reloc_dist) = i32::try_from(reloc_dist) else {
-      return false;
+      return Err(mismatch(0, Found::RelocOverflow));
     };
+    match mem.read(self.offset, self.len.into(), |bytes| {
+      (!self.matches(bytes, reloc_dist)).then(|| Box::from(bytes))
+    }) {
+      Some(None) => Ok(()),
+      Some(Some(actual)) => Err(mismatch(reloc_dist, Found::Bytes(actual))),
+      None => Err(mismatch(reloc_dist, Found::Unreadable)),
+    }
+  }
 
-    let mut bytes = slice::from_raw_parts(address.cast::<u8>(), self.len.into()).iter();
+  /// Checks the patch location the same as `check`, but also accepts another
+  /// mod's hook where the patch can keep it working. Returns the other mod's
+  /// instruction if one was found.
+  ///
+  /// A detour accepts a `call` or `jmp` in the expected code which has been
+  /// retargeted. The trampoline runs the moved code, so the other mod's hook is
+  /// still called through it. This requires the expected bytes.
+  ///
+  /// Patches which call or jump to a function accept a `call` written over
+  /// whole instructions of the expected code. See `Mismatch::overwritten_hook`.
+  /// When applied through a `PatchSet` the patch jumps to a thunk which calls
+  /// the other mod's hook before the patch's target. This also requires the
+  /// expected bytes.
+  ///
+  /// A `call` or `jmp` to the patch's own target or to code generated by this
+  /// crate is left by an earlier application of the patch rather than another
+  /// mod, so the mismatch is returned instead.
+  pub fn check_chained(
+    &self,
+    mem: &impl Memory,
+    reloc_dist: isize,
+  ) -> Result<Option<ForeignHook>, Mismatch> {
+    let e = match self.check(mem, reloc_dist) {
+      Ok(()) => return Ok(None),
+      Err(e) => e,
+    };
+    let hook = match self.data {
+      PatchData::Detour { .. } => e.retargeted_hook(),
+      PatchData::Target { .. } | PatchData::RegCall { .. } | PatchData::Jmp { .. } => {
+        e.overwritten_hook().filter(|hook| hook.kind == ForeignHookKind::Call)
+      }
+      PatchData::Jcc { .. } | PatchData::Raw { .. } => None,
+    };
+    match hook {
+      Some(hook) if !self.is_own_hook(e.address, hook) => Ok(Some(hook)),
+      _ => Err(e),
+    }
+  }
+
+  /// Checks if the hook targets the patch's own target or code generated by
+  /// this crate.
+  fn is_own_hook(&self, address: usize, hook: ForeignHook) -> bool {
+    let target = match self.data {
+      PatchData::Target { target, .. }
+      | PatchData::Detour { target, .. }
+      | PatchData::RegCall { target, .. }
+      | PatchData::Jmp { target }
+      | PatchData::Jcc { target } => Some(target as usize),
+      PatchData::Raw { .. } => None,
+    };
+    // Compare where a branch to the target would reach, since a `rel32` can't
+    // encode every address on 64-bit systems.
+    let next = address.wrapping_add(hook.pos + 5);
+    let reach = |target: usize| next.wrapping_add_signed(target.wrapping_sub(next) as i32 as isize);
+    target.map(reach) == Some(hook.target) || alloc::is_generated(hook.target)
+  }
+
+  /// Searches the module's code sections for every offset containing the
+  /// expected bytes. Masked bytes match anything and relocated values are
+  /// adjusted by `reloc_dist` before comparing.
+  pub fn scan(&self, mem: &impl Memory, reloc_dist: isize) -> Result<Vec<usize>, ScanError> {
+    let ranges = pe::code_ranges(mem).ok_or(ScanError::InvalidModule)?;
+    let reloc_dist = i32::try_from(reloc_dist).map_err(|_| ScanError::RelocOverflow)?;
+    if self.len == 0 {
+      return Ok(Vec::new());
+    }
+    let len = usize::from(self.len);
+    let mut found = Vec::new();
+    for range in ranges {
+      mem
+        .read(range.start, range.len(), |bytes| {
+          found.extend(
+            bytes
+              .windows(len)
+              .enumerate()
+              .filter(|&(_, x)| self.matches(x, reloc_dist))
+              .map(|(i, _)| range.start + i),
+          )
+        })
+        .ok_or(ScanError::InvalidModule)?;
+    }
+    Ok(found)
+  }
+
+  /// Searches the module's code sections for the single offset containing the
+  /// expected bytes. See `scan` for details.
+  pub fn locate(&self, mem: &impl Memory, reloc_dist: isize) -> Result<usize, ScanError> {
+    match &*self.scan(mem, reloc_dist)? {
+      [] => Err(ScanError::NotFound),
+      &[offset] => Ok(offset),
+      offsets => Err(ScanError::Ambiguous(offsets.into())),
+    }
+  }
+
+  /// Moves the patch to a different offset. Useful with the result of `locate`.
+  pub const fn with_offset(mut self, offset: usize) -> Self {
+    self.offset = offset;
+    self
+  }
+
+  /// Checks if the bytes at the patch location are the expected bytes. The
+  /// expected bytes are compared directly when they're embedded, otherwise only
+  /// their hash is.
+  fn matches(&self, bytes: &[u8], reloc_dist: i32) -> bool {
+    if self.expected.is_empty() {
+      return self.hash_bytes(bytes, reloc_dist) == self.hash;
+    }
+    let read = |x: &[u8], i: usize| i32::from_le_bytes(x[i..i + 4].try_into().unwrap());
+    bytes.len() == self.expected.len()
+      && byte_kinds(self.control_stream, bytes.len())
+        .enumerate()
+        .all(|(i, kind)| match kind {
+          ByteKind::Exact => bytes[i] == self.expected[i],
+          ByteKind::Masked | ByteKind::RelocTail => true,
+          ByteKind::Reloc => read(bytes, i).wrapping_sub(reloc_dist) == read(self.expected, i),
+        })
+  }
+
+  /// Hashes the bytes at the patch location in the same way `patch_source`
+  /// hashes the expected bytes.
+  fn hash_bytes(&self, bytes: &[u8], reloc_dist: i32) -> u32 {
+    let mut bytes = bytes.iter();
     let mut control_stream = Uint2Iter::new(self.control_stream);
     let mut hash = 0x01000193u32;
 
@@ -175,10 +652,7 @@ impl Patch {
       if control == 2 {
         let (head, tail) = bytes.as_slice().split_at(4);
         bytes = tail.iter();
-        let buf = head
-          .as_ptr()
-          .cast::<i32>()
-          .read_unaligned()
+        let buf = i32::from_ne_bytes(head.try_into().unwrap())
           .wrapping_sub(reloc_dist)
           .to_ne_bytes();
         for x in buf {
@@ -194,47 +668,242 @@ impl Patch {
       }
     }
 
-    hash == self.hash
+    hash
+  }
+
+  /// Checks that the memory at a code patch's location contains only whole
+  /// instructions. Always succeeds for data patches.
+  pub fn has_whole_instructions(&self, mem: &impl Memory) -> bool {
+    !self.is_code || mem.read(self.offset, self.len.into(), x86::is_whole).unwrap_or(false)
   }
 
-  /// Applies the patch to the given base with the given relocation applied.
-  /// Fails if the memory cannot be read/written, or it does not contain the
-  /// expected code.
+  /// Applies the patch to the given module without checking the existing
+  /// bytes. Relocated values in raw patch data are adjusted by `reloc_dist`.
+  /// Returns `None` if the memory cannot be written.
   ///
   /// # Safety
   /// This writes to an arbitrary memory and there is no way to guarantee memory
-  /// safety.
-  pub unsafe fn apply(&self, base: HMODULE) {
-    let address = (base as usize + self.offset) as *mut c_void;
-    let _mem = MemUnlock::new(address, self.len.into()).unwrap();
+  /// safety. The same applies when the returned patch restores the original
+  /// bytes. A loaded module must never be unloaded, since code may be placed in
+  /// the padding between its functions. Any code allocated for the patch is
+  /// left in place unless it's freed with `AppliedPatch::restore_and_free`.
+  pub unsafe fn apply<M: MemoryMut>(&self, mem: M, reloc_dist: isize) -> Option<AppliedPatch<M>> {
+    self.apply_chained(mem, reloc_dist, None)
+  }
 
-    let mut slice = slice::from_raw_parts_mut(address as *mut u8, self.len.into());
+  /// Applies the patch the same as `apply`. If given, the patch calls another
+  /// mod's hook at the given address before its own target. See
+  /// `check_chained`.
+  pub(crate) unsafe fn apply_chained<M: MemoryMut>(
+    &self,
+    mut mem: M,
+    reloc_dist: isize,
+    chain: Option<usize>,
+  ) -> Option<AppliedPatch<M>> {
+    if mem.is_loaded() {
+      alloc::add_padding(&mem);
+    }
+    let mut space = ProcessCode::default();
+    match self.write(&mut mem, reloc_dist, &mut space, chain) {
+      Some(original) => Some(AppliedPatch {
+        mem,
+        offset: self.offset,
+        original,
+        code: space.chunks,
+      }),
+      None => {
+        // Nothing references the code if the patch wasn't written.
+        free_code(&space.chunks);
+        None
+      }
+    }
+  }
 
+  /// Writes the patch, placing any code it references in the given space.
+  /// Returns the original bytes.
+  unsafe fn write(
+    &self,
+    mem: &mut impl MemoryMut,
+    reloc_dist: isize,
+    space: &mut impl CodeSpace,
+    chain: Option<usize>,
+  ) -> Option<Box<[u8]>> {
+    let base = mem.base();
+    mem.write(self.offset, self.len.into(), |slice| unsafe {
+      let original = Box::<[u8]>::from(&*slice);
+      Self::write_data(
+        &self.data,
+        base,
+        self.offset,
+        reloc_dist,
+        slice,
+        space,
+        chain,
+      )?;
+      Some(original)
+    })?
+  }
+
+  unsafe fn write_data(
+    data: &PatchData,
+    base: usize,
+    offset: usize,
+    reloc_dist: isize,
+    mut slice: &mut [u8],
+    space: &mut impl CodeSpace,
+    chain: Option<usize>,
+  ) -> Option<()> {
+    let address = base.wrapping_add(offset);
+    // Calls become a jump to a thunk which calls the chained hook first.
+    let chain_call = |space: &mut _, target, call: usize| match chain {
+      Some(hook) => Some((
+        0xe9,
+        detour::build_chain_thunk(call, hook, target, Some(call + 5), space)?,
+      )),
+      None => Some((0xe8, target)),
+    };
     // Write the patch data.
-    match self.data {
+    match *data {
       PatchData::Target { target, offset: 0 } => {
+        let target = space.resolve(target as usize)?;
+        let (op, target) = chain_call(space, target, address)?;
         let (head, tail) = slice.split_at_mut(5);
-        head[0] = 0xe8;
+        head[0] = op;
         head
           .as_mut_ptr()
           .offset(1)
           .cast::<i32>()
-          .write_unaligned(((target as usize).wrapping_sub(address as usize + 5)) as i32);
+          .write_unaligned((target.wrapping_sub(address + 5)) as i32);
         slice = tail;
       }
       PatchData::Target { target, offset } => {
+        let target = space.resolve(target as usize)?;
+        let (op, target) = chain_call(space, target, address + offset as usize)?;
         let (head, rest) = slice.split_at_mut(offset as usize);
         let (call, tail) = rest.split_at_mut(5);
         head.fill(0x90);
-        call[0] = 0xe8;
-        call.as_mut_ptr().offset(1).cast::<i32>().write_unaligned(
-          ((target as usize).wrapping_sub(address as usize + offset as usize + 5)) as i32,
+        call[0] = op;
+        call
+          .as_mut_ptr()
+          .offset(1)
+          .cast::<i32>()
+          .write_unaligned((target.wrapping_sub(address + offset as usize + 5)) as i32);
+        slice = tail;
+      }
+      PatchData::Detour { target, call_after } => {
+        if slice.len() < 5 {
+          return None;
+        }
+        let target = space.resolve(target as usize)?;
+        let trampoline = detour::build_trampoline(slice, address, target, call_after, space)?;
+        let (head, tail) = slice.split_at_mut(5);
+        head[0] = 0xe9;
+        head
+          .as_mut_ptr()
+          .offset(1)
+          .cast::<i32>()
+          .write_unaligned(trampoline.wrapping_sub(address + 5) as i32);
+        slice = tail;
+      }
+      PatchData::RegCall { target, args, count } => {
+        let target = space.resolve(target as usize)?;
+        let stub =
+          detour::build_reg_call_stub(address, &args[..usize::from(count)], target, space)?;
+        let (op, target) = chain_call(space, stub, address)?;
+        let (head, tail) = slice.split_at_mut(5);
+        head[0] = op;
+        head
+          .as_mut_ptr()
+          .offset(1)
+          .cast::<i32>()
+          .write_unaligned((target.wrapping_sub(address + 5)) as i32);
+        slice = tail;
+      }
+      PatchData::Jmp { target } => {
+        let mut target = space.resolve(target as usize)?;
+        if let Some(hook) = chain {
+          target = detour::build_chain_thunk(address, hook, target, None, space)?;
+        }
+        let (head, tail) = slice.split_at_mut(5);
+        head[0] = 0xe9;
+        head
+          .as_mut_ptr()
+          .offset(1)
+          .cast::<i32>()
+          .write_unaligned((target.wrapping_sub(address + 5)) as i32);
+        slice = tail;
+      }
+      PatchData::Jcc { target } => {
+        let target = space.resolve(target as usize)?;
+        let inst = x86::decode(slice)?;
+        let Some(Branch::Jcc(cc)) = inst.branch else {
+          return None;
+        };
+        if inst.imm.size == 4 {
+          let next = address + usize::from(inst.len);
+          slice
+            .as_mut_ptr()
+            .add(inst.imm.offset.into())
+            .cast::<i32>()
+            .write_unaligned((target.wrapping_sub(next)) as i32);
+          // The rest of the referenced code still runs after the jump.
+          slice = &mut [];
+        } else {
+          if slice.len() < 5 {
+            return None;
+          }
+          let stub = detour::build_jcc_stub(slice, address, inst, cc, target, space)?;
+          slice[0] = 0xe9;
+          slice
+            .as_mut_ptr()
+            .offset(1)
+            .cast::<i32>()
+            .write_unaligned(stub.wrapping_sub(address + 5) as i32);
+          slice = &mut slice[5..];
+        }
+      }
+      PatchData::Raw { data, relocs, module_relocs, branch_relocs } if data.len() > slice.len() => {
+        // Relative branches within the data are not adjusted for the move, but
+        // branches out of it are.
+        if slice.len() < 5 {
+          return None;
+        }
+        let len = data.len() + 5;
+        let cave = space.alloc(address, len)?;
+        let mut code = Vec::with_capacity(len);
+        code.extend_from_slice(data);
+        rebase(
+          &mut code,
+          (relocs, module_relocs, branch_relocs),
+          cave,
+          base,
+          reloc_dist,
         );
+        code.push(0xe9);
+        code.extend_from_slice(
+          &((address + slice.len()).wrapping_sub(cave + len) as i32).to_le_bytes(),
+        );
+        space.write(cave, &code);
+
+        let (head, tail) = slice.split_at_mut(5);
+        head[0] = 0xe9;
+        head
+          .as_mut_ptr()
+          .offset(1)
+          .cast::<i32>()
+          .write_unaligned(cave.wrapping_sub(address + 5) as i32);
         slice = tail;
       }
-      PatchData::Raw(data) => {
+      PatchData::Raw { data, relocs, module_relocs, branch_relocs } => {
         let (head, tail) = slice.split_at_mut(data.len());
         head.copy_from_slice(data);
+        rebase(
+          head,
+          (relocs, module_relocs, branch_relocs),
+          address,
+          base,
+          reloc_dist,
+        );
         slice = tail;
       }
     }
@@ -277,5 +946,126 @@ impl Patch {
       let src = NOP_BY_SIZE.as_ptr().add(offset);
       dst.cast::<u8>().copy_from_nonoverlapping(src, len);
     }
+
+    Some(())
+  }
+}
+
+/// Adjusts the relocated values in raw patch data for the module's base
+/// address and the address the data is written to.
+fn rebase(
+  data: &mut [u8],
+  (relocs, module_relocs, branch_relocs): (&[u16], &[u16], &[u16]),
+  address: usize,
+  base: usize,
+  reloc_dist: isize,
+) {
+  // Every kind of value is adjusted by a constant amount.
+  let branch_delta =
+    |pos: u16| (reloc_dist as usize).wrapping_sub(address + usize::from(pos) + 4) as u32;
+  let deltas = relocs
+    .iter()
+    .map(|&pos| (pos, reloc_dist as u32))
+    .chain(module_relocs.iter().map(|&pos| (pos, base as u32)))
+    .chain(branch_relocs.iter().map(|&pos| (pos, branch_delta(pos))));
+  for (pos, delta) in deltas {
+    let value = &mut data[usize::from(pos)..usize::from(pos) + 4];
+    let x = u32::from_le_bytes((&*value).try_into().unwrap()).wrapping_add(delta);
+    value.copy_from_slice(&x.to_le_bytes());
+  }
+}
+
+/// A patch which has been applied to a module. The original bytes are restored
+/// when this is dropped unless `keep` is called.
+#[must_use = "dropping an `AppliedPatch` restores the original bytes"]
+pub struct AppliedPatch<M: MemoryMut> {
+  mem: M,
+  offset: usize,
+  original: Box<[u8]>,
+  /// The code allocated for the patch.
+  code: Vec<Range<usize>>,
+}
+impl<M: MemoryMut> AppliedPatch<M> {
+  /// The offset of the patch within the module.
+  #[inline]
+  pub fn offset(&self) -> usize {
+    self.offset
+  }
+
+  /// The bytes which were overwritten by the patch.
+  #[inline]
+  pub fn original(&self) -> &[u8] {
+    &self.original
+  }
+
+  /// Restores the original bytes. Returns the patch back if the memory cannot
+  /// be written.
+  pub fn restore(mut self) -> Result<M, Self> {
+    if self.write_original() {
+      Ok(self.into_parts().0)
+    } else {
+      Err(self)
+    }
+  }
+
+  /// Restores the original bytes, then frees any code allocated for the patch.
+  /// Returns the patch back if the memory cannot be written.
+  ///
+  /// # Safety
+  /// No thread may be running the patch's code (e.g. a detour's trampoline) or
+  /// be about to return into it once the original bytes are restored.
+  pub unsafe fn restore_and_free(mut self) -> Result<M, Self> {
+    if self.write_original() {
+      let (mem, _, code) = self.into_parts();
+      free_code(&code);
+      Ok(mem)
+    } else {
+      Err(self)
+    }
+  }
+
+  /// Leaves the patch in place permanently.
+  pub fn keep(self) -> M {
+    self.into_parts().0
+  }
+
+  fn write_original(&mut self) -> bool {
+    write_original(&mut self.mem, self.offset, &self.original)
+  }
+
+  fn into_parts(self) -> (M, Box<[u8]>, Vec<Range<usize>>) {
+    let this = ManuallyDrop::new(self);
+    // Safety: `this` is never used or dropped after this.
+    unsafe {
+      (
+        ptr::read(&this.mem),
+        ptr::read(&this.original),
+        ptr::read(&this.code),
+      )
+    }
   }
 }
+impl<M: MemoryMut> Drop for AppliedPatch<M> {
+  fn drop(&mut self) {
+    self.write_original();
+  }
+}
+
+/// Frees the code allocated for a patch.
+///
+/// # Safety
+/// See `AppliedPatch::restore_and_free`.
+unsafe fn free_code(code: &[Range<usize>]) {
+  for chunk in code {
+    alloc::free_exec(chunk.start, chunk.len());
+  }
+}
+
+/// Writes the bytes which were replaced by a patch back to the memory.
+fn write_original(mem: &mut impl MemoryMut, offset: usize, original: &[u8]) -> bool {
+  mem
+    .write(offset, original.len(), |slice| {
+      slice.copy_from_slice(original)
+    })
+    .is_some()
+}
@@ -0,0 +1,167 @@
+use crate::{
+  pe,
+  sys::{self, MemUnlock, ModuleHandle},
+};
+use core::{ffi::c_void, slice};
+
+/// A module's image which patches can be checked against.
+pub trait Memory {
+  /// The address the module's image starts at.
+  fn base(&self) -> usize;
+
+  /// Calls the function with the bytes in the range `offset..offset + len` of
+  /// the module's image. Returns `None` if the range can't be read.
+  fn read<R>(&self, offset: usize, len: usize, f: impl FnOnce(&[u8]) -> R) -> Option<R>;
+
+  /// Whether this is a module loaded in the current process, which code can be
+  /// placed in the padding of.
+  #[inline]
+  fn is_loaded(&self) -> bool {
+    false
+  }
+}
+
+/// A module's image which patches can be applied to.
+pub trait MemoryMut: Memory {
+  /// Calls the function with the bytes in the range `offset..offset + len` of
+  /// the module's image. Returns `None` if the range can't be written.
+  fn write<R>(&mut self, offset: usize, len: usize, f: impl FnOnce(&mut [u8]) -> R) -> Option<R>;
+}
+
+impl<M: Memory + ?Sized> Memory for &M {
+  #[inline]
+  fn base(&self) -> usize {
+    (**self).base()
+  }
+
+  #[inline]
+  fn read<R>(&self, offset: usize, len: usize, f: impl FnOnce(&[u8]) -> R) -> Option<R> {
+    (**self).read(offset, len, f)
+  }
+
+  #[inline]
+  fn is_loaded(&self) -> bool {
+    (**self).is_loaded()
+  }
+}
+impl<M: Memory + ?Sized> Memory for &mut M {
+  #[inline]
+  fn base(&self) -> usize {
+    (**self).base()
+  }
+
+  #[inline]
+  fn read<R>(&self, offset: usize, len: usize, f: impl FnOnce(&[u8]) -> R) -> Option<R> {
+    (**self).read(offset, len, f)
+  }
+
+  #[inline]
+  fn is_loaded(&self) -> bool {
+    (**self).is_loaded()
+  }
+}
+impl<M: MemoryMut + ?Sized> MemoryMut for &mut M {
+  #[inline]
+  fn write<R>(&mut self, offset: usize, len: usize, f: impl FnOnce(&mut [u8]) -> R) -> Option<R> {
+    (**self).write(offset, len, f)
+  }
+}
+
+/// A module loaded into the current process.
+#[derive(Clone, Copy)]
+pub struct LoadedModule(ModuleHandle);
+impl LoadedModule {
+  /// # Safety
+  /// The module must remain loaded for as long as this is used, and any range
+  /// accessed through it must be within the module's image.
+  #[inline]
+  pub unsafe fn new(module: ModuleHandle) -> Self {
+    Self(module)
+  }
+
+  #[inline]
+  pub fn handle(self) -> ModuleHandle {
+    self.0
+  }
+}
+impl Memory for LoadedModule {
+  #[inline]
+  fn base(&self) -> usize {
+    self.0 as usize
+  }
+
+  fn read<R>(&self, offset: usize, len: usize, f: impl FnOnce(&[u8]) -> R) -> Option<R> {
+    // The memory is read in place without changing its protection.
+    let address = self.base().wrapping_add(offset);
+    sys::is_readable(address, len)
+      .then(|| f(unsafe { slice::from_raw_parts(address as *const u8, len) }))
+  }
+
+  #[inline]
+  fn is_loaded(&self) -> bool {
+    true
+  }
+}
+impl MemoryMut for LoadedModule {
+  fn write<R>(&mut self, offset: usize, len: usize, f: impl FnOnce(&mut [u8]) -> R) -> Option<R> {
+    let address = self.base().wrapping_add(offset) as *mut c_void;
+    unsafe {
+      let _mem = MemUnlock::new(address, len)?;
+      Some(f(slice::from_raw_parts_mut(address.cast::<u8>(), len)))
+    }
+  }
+}
+
+/// A module's image held in a buffer, laid out as it would be when loaded at
+/// the given base address.
+#[derive(Clone)]
+pub struct ModuleImage<B> {
+  base: usize,
+  bytes: B,
+}
+impl<B> ModuleImage<B> {
+  #[inline]
+  pub const fn new(base: usize, bytes: B) -> Self {
+    Self { base, bytes }
+  }
+
+  #[inline]
+  pub fn bytes(&self) -> &B {
+    &self.bytes
+  }
+
+  #[inline]
+  pub fn bytes_mut(&mut self) -> &mut B {
+    &mut self.bytes
+  }
+
+  #[inline]
+  pub fn into_bytes(self) -> B {
+    self.bytes
+  }
+}
+impl ModuleImage<Vec<u8>> {
+  /// Lays out the contents of a PE file (e.g. `D2Client.dll`) as it would be
+  /// once loaded at its preferred base address. No relocations are applied.
+  pub fn from_pe_file(file: &[u8]) -> Option<Self> {
+    let (base, bytes) = pe::map_file(file)?;
+    Some(Self::new(base as usize, bytes))
+  }
+}
+impl<B: AsRef<[u8]>> Memory for ModuleImage<B> {
+  #[inline]
+  fn base(&self) -> usize {
+    self.base
+  }
+
+  fn read<R>(&self, offset: usize, len: usize, f: impl FnOnce(&[u8]) -> R) -> Option<R> {
+    let bytes = self.bytes.as_ref();
+    Some(f(bytes.get(offset..offset.checked_add(len)?)?))
+  }
+}
+impl<B: AsRef<[u8]> + AsMut<[u8]>> MemoryMut for ModuleImage<B> {
+  fn write<R>(&mut self, offset: usize, len: usize, f: impl FnOnce(&mut [u8]) -> R) -> Option<R> {
+    let bytes = self.bytes.as_mut();
+    Some(f(bytes.get_mut(offset..offset.checked_add(len)?)?))
+  }
+}
@@ -0,0 +1,434 @@
+use crate::{
+  source::{Source, SourceError},
+  Patch, PatchData, Reg, Registers,
+};
+use core::{fmt, mem::transmute};
+
+// Hooks using the x86 calling conventions can only be created on x86.
+#[cfg_attr(not(target_arch = "x86"), allow(dead_code))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum HookKind {
+  C,
+  C1,
+  C2,
+  C3,
+  Std1,
+  Std2,
+  Std3,
+  Fast1,
+  Fast2,
+  Fast3,
+  This1,
+  This2,
+  Detour,
+  Stub,
+}
+
+/// A function which can be called by patches loaded at runtime.
+#[derive(Clone, Copy)]
+pub struct Hook {
+  kind: HookKind,
+  target: unsafe extern "C" fn(),
+}
+impl Hook {
+  const fn new(kind: HookKind, target: unsafe extern "C" fn()) -> Self {
+    Self { kind, target }
+  }
+
+  /// A `cdecl` function taking zero arguments. Used by `call_c` and `call_c_at`.
+  pub const fn c<R>(target: unsafe extern "C" fn() -> R) -> Self {
+    Self::new(HookKind::C, unsafe {
+      transmute::<unsafe extern "C" fn() -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// A `cdecl` function taking one argument from a register. Used by
+  /// `call_c1`.
+  pub const fn c1<T1, R>(target: unsafe extern "C" fn(T1) -> R) -> Self {
+    Self::new(HookKind::C1, unsafe {
+      transmute::<unsafe extern "C" fn(T1) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// A `cdecl` function taking two arguments from registers. Used by
+  /// `call_c2`.
+  pub const fn c2<T1, T2, R>(target: unsafe extern "C" fn(T1, T2) -> R) -> Self {
+    Self::new(HookKind::C2, unsafe {
+      transmute::<unsafe extern "C" fn(T1, T2) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// A `cdecl` function taking three arguments from registers. Used by
+  /// `call_c3`.
+  pub const fn c3<T1, T2, T3, R>(target: unsafe extern "C" fn(T1, T2, T3) -> R) -> Self {
+    Self::new(HookKind::C3, unsafe {
+      transmute::<unsafe extern "C" fn(T1, T2, T3) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// A `stdcall` function taking one argument. Used by `call_std1`.
+  #[cfg(target_arch = "x86")]
+  pub const fn std1<T1, R>(target: unsafe extern "stdcall" fn(T1) -> R) -> Self {
+    Self::new(HookKind::Std1, unsafe {
+      transmute::<unsafe extern "stdcall" fn(T1) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// A `stdcall` function taking two arguments. Used by `call_std2`.
+  #[cfg(target_arch = "x86")]
+  pub const fn std2<T1, T2, R>(target: unsafe extern "stdcall" fn(T1, T2) -> R) -> Self {
+    Self::new(HookKind::Std2, unsafe {
+      transmute::<unsafe extern "stdcall" fn(T1, T2) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// A `stdcall` function taking three arguments. Used by `call_std3`.
+  #[cfg(target_arch = "x86")]
+  pub const fn std3<T1, T2, T3, R>(target: unsafe extern "stdcall" fn(T1, T2, T3) -> R) -> Self {
+    Self::new(HookKind::Std3, unsafe {
+      transmute::<unsafe extern "stdcall" fn(T1, T2, T3) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// A `fastcall` function taking one argument. Used by `call_fast1`.
+  #[cfg(target_arch = "x86")]
+  pub const fn fast1<T1, R>(target: unsafe extern "fastcall" fn(T1) -> R) -> Self {
+    Self::new(HookKind::Fast1, unsafe {
+      transmute::<unsafe extern "fastcall" fn(T1) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// A `fastcall` function taking two arguments. Used by `call_fast2`.
+  #[cfg(target_arch = "x86")]
+  pub const fn fast2<T1, T2, R>(target: unsafe extern "fastcall" fn(T1, T2) -> R) -> Self {
+    Self::new(HookKind::Fast2, unsafe {
+      transmute::<unsafe extern "fastcall" fn(T1, T2) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// A `fastcall` function taking three arguments. Used by `call_fast3`.
+  #[cfg(target_arch = "x86")]
+  pub const fn fast3<T1, T2, T3, R>(target: unsafe extern "fastcall" fn(T1, T2, T3) -> R) -> Self {
+    Self::new(HookKind::Fast3, unsafe {
+      transmute::<unsafe extern "fastcall" fn(T1, T2, T3) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// A `thiscall` function taking only the `this` pointer. Used by
+  /// `call_this1`.
+  #[cfg(target_arch = "x86")]
+  pub const fn this1<T1, R>(target: unsafe extern "thiscall" fn(T1) -> R) -> Self {
+    Self::new(HookKind::This1, unsafe {
+      transmute::<unsafe extern "thiscall" fn(T1) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// A `thiscall` function taking the `this` pointer and one argument. Used by
+  /// `call_this2`.
+  #[cfg(target_arch = "x86")]
+  pub const fn this2<T1, T2, R>(target: unsafe extern "thiscall" fn(T1, T2) -> R) -> Self {
+    Self::new(HookKind::This2, unsafe {
+      transmute::<unsafe extern "thiscall" fn(T1, T2) -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// Code which is jumped to rather than called, such as an asm stub. Used by
+  /// `jmp_c` and `retarget_jcc`.
+  pub const fn stub<R>(target: unsafe extern "C" fn() -> R) -> Self {
+    Self::new(HookKind::Stub, unsafe {
+      transmute::<unsafe extern "C" fn() -> R, unsafe extern "C" fn()>(target)
+    })
+  }
+
+  /// A function called by a detour. Used by `detour` and `detour_after`.
+  pub const fn detour(target: unsafe extern "C" fn(&mut Registers)) -> Self {
+    Self::new(HookKind::Detour, unsafe {
+      transmute::<unsafe extern "C" fn(&mut Registers), unsafe extern "C" fn()>(target)
+    })
+  }
+}
+
+/// An error from parsing patches at runtime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+  /// The line the error occurred on, starting from one.
+  pub line: usize,
+  /// The column the error occurred at, starting from one. Only set for errors
+  /// within a patch's source.
+  pub column: Option<usize>,
+  pub msg: String,
+}
+impl ParseError {
+  fn new(line: usize, msg: String) -> Self {
+    Self { line, column: None, msg }
+  }
+}
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.column {
+      Some(column) => write!(f, "line {}, column {column}: {}", self.line, self.msg),
+      None => write!(f, "line {}: {}", self.line, self.msg),
+    }
+  }
+}
+
+/// Parses an offset written in either hex with a `0x` prefix, or decimal.
+fn parse_offset(s: &str) -> Option<usize> {
+  match s.strip_prefix("0x") {
+    Some(s) => usize::from_str_radix(s, 16).ok(),
+    None => s.parse().ok(),
+  }
+}
+
+/// A patch's header line before its source has been read.
+struct Header<'a> {
+  line: usize,
+  offset: usize,
+  kind: &'a str,
+  args: Vec<&'a str>,
+  src: String,
+}
+impl Header<'_> {
+  fn finish(self, hooks: &mut impl FnMut(&str) -> Option<Hook>) -> Result<Patch, ParseError> {
+    let err = |msg: String| ParseError::new(self.line, msg);
+    // Data patches skip the instruction checks. The prefix is blanked out so
+    // positions in the source still match the file.
+    let mut src = self.src;
+    let start = src.len() - src.trim_start().len();
+    let is_code = !src[start..].starts_with("data");
+    if !is_code {
+      src.replace_range(start..start + 4, "    ");
+    }
+    // The source starts on the line after the header.
+    let src_err = |e: SourceError| {
+      let (line, column) = e.line_col(&src);
+      ParseError {
+        line: self.line + line,
+        column: Some(column),
+        msg: e.msg,
+      }
+    };
+    let parsed = Source::parse(&src).map_err(src_err)?;
+    parsed.check_pattern().map_err(src_err)?;
+    if parsed.bytes.is_empty() {
+      return Err(err("missing patch source".into()));
+    }
+    if is_code {
+      parsed.check_instructions().map_err(src_err)?;
+    }
+
+    let mut hook = |kind: HookKind| match self.args.first() {
+      Some(&name) => match hooks(name) {
+        Some(hook) if hook.kind == kind => Ok(hook.target),
+        Some(_) => Err(err(format!(
+          "hook `{name}` can't be used with `{}`",
+          self.kind
+        ))),
+        None => Err(err(format!("unknown hook `{name}`"))),
+      },
+      None => Err(err(format!("`{}` requires a hook", self.kind))),
+    };
+    let (data, arg_count) = match self.kind {
+      "call_c" => (
+        PatchData::Target { target: hook(HookKind::C)?, offset: 0 },
+        1,
+      ),
+      "call_c_at" => {
+        let target = hook(HookKind::C)?;
+        let offset = match self.args.get(1).map(|x| x.parse()) {
+          Some(Ok(x)) => x,
+          _ => return Err(err("`call_c_at` requires a call offset".into())),
+        };
+        (PatchData::Target { target, offset }, 2)
+      }
+      "call_c1" | "call_c2" | "call_c3" => {
+        let (kind, count) = match self.kind {
+          "call_c1" => (HookKind::C1, 1),
+          "call_c2" => (HookKind::C2, 2),
+          _ => (HookKind::C3, 3),
+        };
+        let target = hook(kind)?;
+        let regs = &self.args[1..self.args.len().min(count + 1)];
+        if regs.len() < count {
+          return Err(err(format!(
+            "`{}` requires {count} argument registers",
+            self.kind
+          )));
+        }
+        let mut args = [Reg::Eax; 3];
+        for (arg, &name) in args.iter_mut().zip(regs) {
+          *arg = Reg::from_name(name).ok_or_else(|| err(format!("unknown register `{name}`")))?;
+        }
+        (
+          PatchData::RegCall { target, args, count: count as u8 },
+          count + 1,
+        )
+      }
+      "call_std1" => (
+        PatchData::Target { target: hook(HookKind::Std1)?, offset: 0 },
+        1,
+      ),
+      "call_std2" => (
+        PatchData::Target { target: hook(HookKind::Std2)?, offset: 0 },
+        1,
+      ),
+      "call_std3" => (
+        PatchData::Target { target: hook(HookKind::Std3)?, offset: 0 },
+        1,
+      ),
+      "call_fast1" => (
+        PatchData::Target { target: hook(HookKind::Fast1)?, offset: 0 },
+        1,
+      ),
+      "call_fast2" => (
+        PatchData::Target { target: hook(HookKind::Fast2)?, offset: 0 },
+        1,
+      ),
+      "call_fast3" => (
+        PatchData::Target { target: hook(HookKind::Fast3)?, offset: 0 },
+        1,
+      ),
+      "call_this1" => (
+        PatchData::Target { target: hook(HookKind::This1)?, offset: 0 },
+        1,
+      ),
+      "call_this2" => (
+        PatchData::Target { target: hook(HookKind::This2)?, offset: 0 },
+        1,
+      ),
+      "jmp_c" => (PatchData::Jmp { target: hook(HookKind::Stub)? }, 1),
+      "retarget_jcc" => (PatchData::Jcc { target: hook(HookKind::Stub)? }, 1),
+      "detour" => (
+        PatchData::Detour { target: hook(HookKind::Detour)?, call_after: false },
+        1,
+      ),
+      "detour_after" => (
+        PatchData::Detour { target: hook(HookKind::Detour)?, call_after: true },
+        1,
+      ),
+      "nop" => (
+        PatchData::Raw {
+          data: &[],
+          relocs: &[],
+          module_relocs: &[],
+          branch_relocs: &[],
+        },
+        0,
+      ),
+      "raw" => {
+        let data = Source::parse(&self.args.join(" ")).map_err(|e| err(e.msg))?;
+        data.check_data().map_err(|e| err(e.msg))?;
+        let leak_positions = |x: Vec<usize>| -> &'static [u16] {
+          Box::leak(x.into_iter().map(|x| x as u16).collect())
+        };
+        (
+          PatchData::Raw {
+            data: Box::leak(data.bytes.into_boxed_slice()),
+            relocs: leak_positions(data.relocs),
+            module_relocs: leak_positions(data.module_relocs),
+            branch_relocs: &[],
+          },
+          self.args.len(),
+        )
+      }
+      kind => return Err(err(format!("unknown patch kind `{kind}`"))),
+    };
+    if self.args.len() > arg_count {
+      return Err(err(format!("too many arguments for `{}`", self.kind)));
+    }
+
+    Ok(Patch {
+      offset: self.offset,
+      len: parsed.bytes.len() as u16,
+      hash: parsed.hash(),
+      control_stream: Box::leak(parsed.control_stream.into_boxed_slice()),
+      expected: Box::leak(parsed.bytes.into_boxed_slice()),
+      is_code,
+      data,
+    })
+  }
+}
+
+/// Parses patches written in a text format. The data for each patch is leaked
+/// so patches should only be parsed once.
+///
+/// Each patch starts with a header line containing its offset, kind and
+/// arguments. The lines following it are indented and contain the patch's
+/// source in the same syntax as `patch_source!`. `;` starts a comment.
+///
+/// ```text
+/// ; Replace the frame limiter.
+/// 0x1a2b call_c game_loop_sleep_hook
+///   8b0d $20de8b6f
+///   ff15 $c0a18b6f
+/// 0x3c4d raw 9090
+///   data 7405
+/// ```
+///
+/// The supported kinds are `call_c <hook>`, `call_c_at <hook> <call offset>`,
+/// `call_c1 <hook> <reg>`, `call_c2 <hook> <reg> <reg>`,
+/// `call_c3 <hook> <reg> <reg> <reg>`, `call_std1`, `call_std2`, `call_std3`,
+/// `call_fast1`, `call_fast2`, `call_fast3`, `call_this1` and `call_this2`
+/// each taking a hook, `jmp_c <hook>`, `retarget_jcc <hook>`, `detour <hook>`,
+/// `detour_after <hook>`, `nop` and `raw <bytes>`. Hook names are resolved
+/// with the given function. The bytes of a `raw` patch use the same syntax as
+/// `patch_data!`.
+pub fn parse_patches(
+  text: &str,
+  mut hooks: impl FnMut(&str) -> Option<Hook>,
+) -> Result<Vec<Patch>, ParseError> {
+  let mut patches = Vec::new();
+  let mut cur: Option<Header<'_>> = None;
+  for (line, text) in text.lines().enumerate().map(|(i, x)| (i + 1, x)) {
+    let code = text.split_once(';').map_or(text, |(x, _)| x);
+    if code.trim().is_empty() {
+      // Keep the source's lines in sync with the file.
+      if let Some(cur) = &mut cur {
+        cur.src.push('\n');
+      }
+      continue;
+    }
+    if text.starts_with([' ', '\t']) {
+      // Comments are handled by the source parser.
+      match &mut cur {
+        Some(cur) => {
+          cur.src.push_str(text);
+          cur.src.push('\n');
+        }
+        None => {
+          return Err(ParseError::new(
+            line,
+            "patch source without a header".into(),
+          ))
+        }
+      }
+      continue;
+    }
+    let text = code;
+
+    if let Some(cur) = cur.take() {
+      patches.push(cur.finish(&mut hooks)?);
+    }
+    let mut words = text.split_whitespace();
+    let (Some(offset), Some(kind)) = (words.next(), words.next()) else {
+      return Err(ParseError::new(
+        line,
+        "expected a patch offset and kind".into(),
+      ));
+    };
+    let Some(offset) = parse_offset(offset) else {
+      return Err(ParseError::new(line, format!("invalid offset `{offset}`")));
+    };
+    cur = Some(Header {
+      line,
+      offset,
+      kind,
+      args: words.collect(),
+      src: String::new(),
+    });
+  }
+  if let Some(cur) = cur {
+    patches.push(cur.finish(&mut hooks)?);
+  }
+  Ok(patches)
+}
@@ -0,0 +1,334 @@
+use crate::Memory;
+use core::ops::Range;
+
+/// Reads a little-endian `u16` at the given offset.
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+  Some(u16::from_le_bytes(
+    bytes.get(offset..offset.checked_add(2)?)?.try_into().ok()?,
+  ))
+}
+
+/// Reads a little-endian `u32` at the given offset.
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+  Some(u32::from_le_bytes(
+    bytes.get(offset..offset.checked_add(4)?)?.try_into().ok()?,
+  ))
+}
+
+/// A section header from a PE image.
+#[derive(Clone, Copy)]
+pub struct Section {
+  pub virtual_size: u32,
+  pub virtual_address: u32,
+  pub raw_size: u32,
+  pub raw_offset: u32,
+  pub characteristics: u32,
+}
+impl Section {
+  const SIZE: usize = 40;
+  const CNT_CODE: u32 = 0x20;
+  const MEM_EXECUTE: u32 = 0x2000_0000;
+
+  fn read(bytes: &[u8], offset: usize) -> Option<Self> {
+    Some(Self {
+      virtual_size: read_u32(bytes, offset + 8)?,
+      virtual_address: read_u32(bytes, offset + 12)?,
+      raw_size: read_u32(bytes, offset + 16)?,
+      raw_offset: read_u32(bytes, offset + 20)?,
+      characteristics: read_u32(bytes, offset + 36)?,
+    })
+  }
+
+  /// Whether the section contains executable code.
+  pub fn is_code(&self) -> bool {
+    self.characteristics & (Self::CNT_CODE | Self::MEM_EXECUTE) != 0
+  }
+
+  /// The range the section occupies once loaded.
+  pub fn loaded_range(&self) -> Range<usize> {
+    let size = if self.virtual_size == 0 {
+      self.raw_size
+    } else {
+      self.virtual_size
+    };
+    let start = self.virtual_address as usize;
+    start..start + size as usize
+  }
+}
+
+/// The headers of a 32-bit PE image. Works with both the file layout and the
+/// loaded layout as the headers are identical in both.
+pub struct Headers<'a> {
+  bytes: &'a [u8],
+  opt_header: usize,
+  section_table: usize,
+  section_count: u16,
+}
+impl<'a> Headers<'a> {
+  /// Parses the headers at the start of the given bytes.
+  pub fn parse(bytes: &'a [u8]) -> Option<Self> {
+    if bytes.get(..2)? != b"MZ" {
+      return None;
+    }
+    let nt_header = read_u32(bytes, 0x3c)? as usize;
+    if bytes.get(nt_header..nt_header.checked_add(4)?)? != b"PE\0\0" {
+      return None;
+    }
+    let section_count = read_u16(bytes, nt_header + 6)?;
+    let opt_header_size = read_u16(bytes, nt_header + 20)?;
+    let opt_header = nt_header + 24;
+    // Only PE32 images are supported.
+    if read_u16(bytes, opt_header)? != 0x10b {
+      return None;
+    }
+    let section_table = opt_header + usize::from(opt_header_size);
+    bytes.get(section_table..section_table + usize::from(section_count) * Section::SIZE)?;
+    Some(Self { bytes, opt_header, section_table, section_count })
+  }
+
+  /// The address the image prefers to be loaded at.
+  pub fn image_base(&self) -> u32 {
+    read_u32(self.bytes, self.opt_header + 28).unwrap_or(0)
+  }
+
+  /// The size of the image once loaded.
+  pub fn image_size(&self) -> u32 {
+    read_u32(self.bytes, self.opt_header + 56).unwrap_or(0)
+  }
+
+  /// The combined size of all headers.
+  pub fn headers_size(&self) -> u32 {
+    read_u32(self.bytes, self.opt_header + 60).unwrap_or(0)
+  }
+
+  /// The alignment of each section once loaded.
+  pub fn section_alignment(&self) -> u32 {
+    read_u32(self.bytes, self.opt_header + 32).unwrap_or(0)
+  }
+
+  /// The alignment of each section's data within the file.
+  pub fn file_alignment(&self) -> u32 {
+    read_u32(self.bytes, self.opt_header + 36).unwrap_or(0)
+  }
+
+  /// Gets the range of the given data directory once loaded. Returns `None` if
+  /// the image doesn't have the directory.
+  pub fn data_dir(&self, idx: u32) -> Option<Range<usize>> {
+    if idx >= read_u32(self.bytes, self.opt_header + 92)? {
+      return None;
+    }
+    let entry = self.opt_header + 96 + idx as usize * 8;
+    let start = read_u32(self.bytes, entry)? as usize;
+    let size = read_u32(self.bytes, entry + 4)? as usize;
+    (start != 0 && size != 0).then_some(start..start + size)
+  }
+
+  /// Gets the position in the file of the given range of the loaded image.
+  /// Returns `None` if any part of the range isn't stored in the file.
+  pub fn file_offset(&self, range: Range<usize>) -> Option<usize> {
+    self.sections().find_map(|s| {
+      let start = s.virtual_address as usize;
+      let end = start + s.raw_size.min(s.loaded_range().len() as u32) as usize;
+      (start <= range.start && range.end <= end)
+        .then(|| s.raw_offset as usize + (range.start - start))
+    })
+  }
+
+  /// Iterates over the image's section headers.
+  pub fn sections(&self) -> impl 'a + Iterator<Item = Section> {
+    let bytes = self.bytes;
+    let table = self.section_table;
+    (0..usize::from(self.section_count))
+      .filter_map(move |i| Section::read(bytes, table + i * Section::SIZE))
+  }
+}
+
+/// Lays out a PE file as it would be once loaded into memory, without applying
+/// any relocations.
+pub fn map_file(file: &[u8]) -> Option<(u32, Vec<u8>)> {
+  let headers = Headers::parse(file)?;
+  let mut image = vec![0u8; headers.image_size() as usize];
+  let headers_size = (headers.headers_size() as usize).min(file.len()).min(image.len());
+  image[..headers_size].copy_from_slice(&file[..headers_size]);
+  for s in headers.sections() {
+    let src = file.get(s.raw_offset as usize..)?;
+    let dst = image.get_mut(s.virtual_address as usize..)?;
+    let len = (s.raw_size as usize).min(src.len()).min(dst.len());
+    dst[..len].copy_from_slice(&src[..len]);
+  }
+  Some((headers.image_base(), image))
+}
+
+/// Copies a loaded image's headers and section data back into the file it was
+/// mapped from. The inverse of `map_file`.
+pub fn unmap_image(file: &mut [u8], image: &[u8]) -> Option<()> {
+  let headers = Headers::parse(image)?;
+  let headers_size = (headers.headers_size() as usize).min(file.len()).min(image.len());
+  file[..headers_size].copy_from_slice(&image[..headers_size]);
+  for s in headers.sections() {
+    let src = image.get(s.virtual_address as usize..)?;
+    let dst = file.get_mut(s.raw_offset as usize..)?;
+    let len = (s.raw_size as usize).min(src.len()).min(dst.len());
+    dst[..len].copy_from_slice(&src[..len]);
+  }
+  Some(())
+}
+
+const fn align_up(x: u32, align: u32) -> u32 {
+  if align == 0 {
+    x
+  } else {
+    x.div_ceil(align) * align
+  }
+}
+
+/// The address the next section added to the image would be loaded at.
+pub fn next_section_rva(file: &[u8]) -> Option<u32> {
+  let headers = Headers::parse(file)?;
+  let end = headers
+    .sections()
+    .map(|s| s.loaded_range().end as u32)
+    .fold(headers.headers_size(), u32::max);
+  Some(align_up(end, headers.section_alignment()))
+}
+
+/// Appends a code section with the given name and contents to a PE file. The
+/// section is loaded at the address returned by `next_section_rva`. Fails if
+/// there is no room in the headers for another section.
+pub fn append_code_section(file: &mut Vec<u8>, name: [u8; 8], code: &[u8]) -> Option<()> {
+  const CHARACTERISTICS: u32 = Section::CNT_CODE | Section::MEM_EXECUTE | 0x4000_0000;
+
+  let rva = next_section_rva(file)?;
+  let headers = Headers::parse(file)?;
+  let file_alignment = headers.file_alignment();
+  let section_alignment = headers.section_alignment();
+  let opt_header = headers.opt_header;
+  let count = headers.section_count;
+  let header = headers.section_table + usize::from(count) * Section::SIZE;
+  // The new header can't overlap any section's data.
+  let data_start = headers
+    .sections()
+    .filter(|s| s.raw_size != 0)
+    .map(|s| s.raw_offset)
+    .fold(headers.headers_size(), u32::min);
+  if header + Section::SIZE > data_start as usize {
+    return None;
+  }
+
+  let raw_offset = align_up(u32::try_from(file.len()).ok()?, file_alignment);
+  let raw_size = align_up(u32::try_from(code.len()).ok()?, file_alignment);
+  let virtual_size = u32::try_from(code.len()).ok()?;
+  file.resize(raw_offset as usize, 0);
+  file.extend_from_slice(code);
+  file.resize((raw_offset + raw_size) as usize, 0);
+
+  let write_u32 = |file: &mut [u8], offset: usize, x: u32| {
+    file[offset..offset + 4].copy_from_slice(&x.to_le_bytes());
+  };
+  file[header..header + Section::SIZE].fill(0);
+  file[header..header + 8].copy_from_slice(&name);
+  write_u32(file, header + 8, virtual_size);
+  write_u32(file, header + 12, rva);
+  write_u32(file, header + 16, raw_size);
+  write_u32(file, header + 20, raw_offset);
+  write_u32(file, header + 36, CHARACTERISTICS);
+
+  let nt_header = opt_header - 24;
+  file[nt_header + 6..nt_header + 8].copy_from_slice(&(count + 1).to_le_bytes());
+  let code_size = read_u32(file, opt_header + 4)?;
+  write_u32(file, opt_header + 4, code_size + raw_size);
+  write_u32(
+    file,
+    opt_header + 56,
+    align_up(rva + virtual_size, section_alignment),
+  );
+  // The checksum is no longer valid.
+  write_u32(file, opt_header + 64, 0);
+  Some(())
+}
+
+/// The data directory containing the import descriptors.
+const IMPORT_DIR: u32 = 1;
+
+/// Finds the import address table entry for a function imported by name from
+/// the given library. Returns the entry's address relative to the image base.
+pub fn import_slot(image: &[u8], library: &str, name: &str) -> Option<usize> {
+  let read_str = |pos: usize| {
+    let bytes = image.get(pos..)?;
+    Some(&bytes[..bytes.iter().position(|&x| x == 0)?])
+  };
+  let dir = Headers::parse(image)?.data_dir(IMPORT_DIR)?;
+  // The table ends with a zeroed descriptor.
+  for desc in (dir.start..dir.end).step_by(20) {
+    let names = read_u32(image, desc)? as usize;
+    let library_name = read_u32(image, desc + 12)? as usize;
+    let slots = read_u32(image, desc + 16)? as usize;
+    if library_name == 0 && slots == 0 {
+      break;
+    }
+    if !read_str(library_name)?.eq_ignore_ascii_case(library.as_bytes()) {
+      continue;
+    }
+    // Without a lookup table the names are read from the unbound address table.
+    let names = if names == 0 { slots } else { names };
+    for i in 0.. {
+      let entry = read_u32(image, names + i * 4)?;
+      if entry == 0 {
+        break;
+      }
+      // Imports by ordinal have the high bit set. Named imports point to a hint
+      // followed by the name.
+      if entry & 0x8000_0000 == 0 && read_str(entry as usize + 2)? == name.as_bytes() {
+        return Some(slots + i * 4);
+      }
+    }
+  }
+  None
+}
+
+/// The data directory containing the base relocations.
+const RELOC_DIR: u32 = 5;
+
+/// Gets the position of every base relocation entry in a loaded image along with
+/// the address the entry relocates.
+pub fn relocs(image: &[u8]) -> Option<Vec<(usize, usize)>> {
+  let Some(dir) = Headers::parse(image)?.data_dir(RELOC_DIR) else {
+    return Some(Vec::new());
+  };
+  let mut entries = Vec::new();
+  let mut block = dir.start;
+  while block + 8 <= dir.end {
+    let page = read_u32(image, block)? as usize;
+    let size = read_u32(image, block + 4)? as usize;
+    if size < 8 {
+      break;
+    }
+    for pos in (block + 8..(block + size).min(dir.end)).step_by(2) {
+      let entry = read_u16(image, pos)?;
+      // Type zero entries are padding.
+      if entry >> 12 != 0 {
+        entries.push((pos, page + usize::from(entry & 0xfff)));
+      }
+    }
+    block += size;
+  }
+  Some(entries)
+}
+
+/// Gets the ranges of all code sections in a module's loaded image.
+pub fn code_ranges(mem: &impl Memory) -> Option<Vec<Range<usize>>> {
+  let nt_header = mem.read(0, 0x40, |x| read_u32(x, 0x3c))?? as usize;
+  let (section_count, opt_header_size) =
+    mem.read(nt_header, 24, |x| Some((read_u16(x, 6)?, read_u16(x, 20)?)))??;
+  let len =
+    nt_header + 24 + usize::from(opt_header_size) + usize::from(section_count) * Section::SIZE;
+  mem.read(0, len, |x| {
+    Some(
+      Headers::parse(x)?
+        .sections()
+        .filter(Section::is_code)
+        .map(|s| s.loaded_range())
+        .collect(),
+    )
+  })?
+}
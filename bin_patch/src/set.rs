@@ -0,0 +1,343 @@
+use crate::{
+  free_code, write_original, ForeignHook, ForeignHookKind, MemoryMut, Mismatch, Patch, PatchData,
+};
+use core::{fmt, ops::Range};
+
+/// The reason a patch in a `PatchSet` couldn't be applied.
+#[derive(Debug, Clone)]
+pub enum PatchError {
+  /// The patch location doesn't contain the expected bytes.
+  Mismatch(Mismatch),
+  /// The patch overlaps another patch in the same module at the given offset.
+  Overlap(usize),
+  /// The patch's data doesn't fit in the patch location.
+  TooLarge,
+  /// The patch location doesn't contain only whole instructions.
+  PartialInstruction,
+  /// The memory at the patch location couldn't be written.
+  Write,
+}
+impl fmt::Display for PatchError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Mismatch(m) => write!(f, "unexpected code {m}"),
+      Self::Overlap(offset) => write!(f, "overlaps the patch at {offset:#x}"),
+      Self::TooLarge => f.write_str("patch data doesn't fit"),
+      Self::PartialInstruction => f.write_str("location doesn't contain only whole instructions"),
+      Self::Write => f.write_str("failed to write memory"),
+    }
+  }
+}
+
+/// A patch which was applied over another mod's hook while keeping the hook
+/// working.
+#[derive(Debug, Clone, Copy)]
+pub struct ChainedHook {
+  /// The index of the group the patch was added with.
+  pub group: usize,
+  /// The offset of the patch within the module.
+  pub offset: usize,
+  pub hook: ForeignHook,
+}
+
+/// A patch in a `PatchSet` which couldn't be applied.
+#[derive(Debug, Clone)]
+pub struct PatchSetError {
+  /// The index of the group the patch was added with.
+  pub group: usize,
+  /// The offset of the patch within the module.
+  pub offset: usize,
+  pub error: PatchError,
+}
+impl fmt::Display for PatchSetError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "patch at {:#x}: {}", self.offset, self.error)
+  }
+}
+
+struct PatchGroup<'a, M> {
+  mem: M,
+  reloc_dist: isize,
+  patches: &'a [Patch],
+}
+
+/// A set of patches, possibly to multiple modules, which are applied as a
+/// single unit. Either every patch is applied, or none are.
+pub struct PatchSet<'a, M> {
+  groups: Vec<PatchGroup<'a, M>>,
+}
+impl<'a, M: MemoryMut> PatchSet<'a, M> {
+  pub const fn new() -> Self {
+    Self { groups: Vec::new() }
+  }
+
+  /// Adds a group of patches for the module. Returns the group's index.
+  pub fn add(&mut self, mem: M, reloc_dist: isize, patches: &'a [Patch]) -> usize {
+    self.groups.push(PatchGroup { mem, reloc_dist, patches });
+    self.groups.len() - 1
+  }
+
+  /// Checks that no patches overlap and that each patch's data fits.
+  fn check_layout(&self) -> Vec<PatchSetError> {
+    let mut errors = Vec::new();
+    let mut ranges: Vec<_> = self
+      .groups
+      .iter()
+      .enumerate()
+      .flat_map(|(group, g)| {
+        let base = g.mem.base();
+        g.patches.iter().map(move |p| {
+          let start = base.wrapping_add(p.offset);
+          (start, start.wrapping_add(p.len.into()), group, p.offset)
+        })
+      })
+      .collect();
+    ranges.sort_unstable_by_key(|&(start, ..)| start);
+    // The end and offset of the patch which extends the furthest so far.
+    let mut prev: Option<(usize, usize)> = None;
+    for &(start, end, group, offset) in &ranges {
+      match prev {
+        Some((prev_end, prev_offset)) if start < prev_end => {
+          errors.push(PatchSetError {
+            group,
+            offset,
+            error: PatchError::Overlap(prev_offset),
+          });
+        }
+        _ => {}
+      }
+      match prev {
+        Some((prev_end, _)) if prev_end >= end => {}
+        _ => prev = Some((end, offset)),
+      }
+    }
+
+    for (group, g) in self.groups.iter().enumerate() {
+      for p in g.patches {
+        if p.data_len() > usize::from(p.len) {
+          errors.push(PatchSetError {
+            group,
+            offset: p.offset,
+            error: PatchError::TooLarge,
+          });
+        }
+      }
+    }
+    errors
+  }
+
+  /// Checks every patch without applying any. Returns every problem found.
+  ///
+  /// Patches over another mod's hook are accepted where the hook can be kept
+  /// working. See `Patch::check_chained`.
+  pub fn check(&self) -> Result<(), Vec<PatchSetError>> {
+    self.check_inner().map(|_| ())
+  }
+
+  fn check_inner(&self) -> Result<Vec<ChainedHook>, Vec<PatchSetError>> {
+    let mut errors = self.check_layout();
+    let mut chained = Vec::new();
+    for (group, g) in self.groups.iter().enumerate() {
+      for p in g.patches {
+        match p.check_chained(&g.mem, g.reloc_dist) {
+          Err(e) => errors.push(PatchSetError {
+            group,
+            offset: p.offset,
+            error: PatchError::Mismatch(e),
+          }),
+          Ok(_) if !p.has_whole_instructions(&g.mem) => errors.push(PatchSetError {
+            group,
+            offset: p.offset,
+            error: PatchError::PartialInstruction,
+          }),
+          Ok(Some(hook)) => chained.push(ChainedHook { group, offset: p.offset, hook }),
+          Ok(None) => {}
+        }
+      }
+    }
+    if errors.is_empty() {
+      Ok(chained)
+    } else {
+      Err(errors)
+    }
+  }
+
+  /// Checks every patch, then applies them all. Any applied patches are
+  /// restored if a patch fails to apply.
+  ///
+  /// # Safety
+  /// See `Patch::apply`.
+  pub unsafe fn apply(self) -> Result<AppliedPatchSet<M>, Vec<PatchSetError>> {
+    let chained = self.check_inner()?;
+    self.apply_inner(chained)
+  }
+
+  /// Applies every patch without checking the expected bytes first. Overlapping
+  /// patches and patches which would split an instruction are still rejected.
+  /// Any applied patches are restored if a patch fails to apply.
+  ///
+  /// # Safety
+  /// See `Patch::apply`.
+  pub unsafe fn apply_unchecked(self) -> Result<AppliedPatchSet<M>, Vec<PatchSetError>> {
+    let mut errors = self.check_layout();
+    let mut chained = Vec::new();
+    for (group, g) in self.groups.iter().enumerate() {
+      for p in g.patches {
+        if !p.has_whole_instructions(&g.mem) {
+          errors.push(PatchSetError {
+            group,
+            offset: p.offset,
+            error: PatchError::PartialInstruction,
+          });
+        } else if let Ok(Some(hook)) = p.check_chained(&g.mem, g.reloc_dist) {
+          // Other mods' hooks are kept working regardless of the check.
+          chained.push(ChainedHook { group, offset: p.offset, hook });
+        }
+      }
+    }
+    if !errors.is_empty() {
+      return Err(errors);
+    }
+    self.apply_inner(chained)
+  }
+
+  unsafe fn apply_inner(
+    self,
+    chained: Vec<ChainedHook>,
+  ) -> Result<AppliedPatchSet<M>, Vec<PatchSetError>> {
+    // Everything written so far is restored if this is dropped early.
+    let mut applied = AppliedPatchSet {
+      groups: Vec::with_capacity(self.groups.len()),
+      chained,
+    };
+    for (group, g) in self.groups.into_iter().enumerate() {
+      applied.groups.push(AppliedGroup {
+        mem: g.mem,
+        originals: Vec::with_capacity(g.patches.len()),
+      });
+      let AppliedGroup { mem, originals } = applied.groups.last_mut().unwrap();
+      for p in g.patches {
+        // A detour's trampoline runs the other mod's hook with the moved code.
+        let chain = applied
+          .chained
+          .iter()
+          .find(|c| {
+            c.group == group && c.offset == p.offset && c.hook.kind == ForeignHookKind::Call
+          })
+          .filter(|_| !matches!(p.data, PatchData::Detour { .. }))
+          .map(|c| c.hook.target);
+        match p.apply_chained(&mut *mem, g.reloc_dist, chain) {
+          Some(applied) => {
+            let (_, original, code) = applied.into_parts();
+            originals.push(Original { offset: p.offset, bytes: original, code });
+          }
+          None => {
+            return Err(vec![PatchSetError {
+              group,
+              offset: p.offset,
+              error: PatchError::Write,
+            }]);
+          }
+        }
+      }
+    }
+    Ok(applied)
+  }
+}
+impl<M: MemoryMut> Default for PatchSet<'_, M> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// The state needed to restore a single patch.
+struct Original {
+  offset: usize,
+  bytes: Box<[u8]>,
+  /// The code allocated for the patch.
+  code: Vec<Range<usize>>,
+}
+
+struct AppliedGroup<M> {
+  mem: M,
+  originals: Vec<Original>,
+}
+
+/// A set of patches which have been applied. The original bytes are restored,
+/// in the reverse order the patches were applied, when this is dropped unless
+/// `keep` is called.
+#[must_use = "dropping an `AppliedPatchSet` restores the original bytes"]
+pub struct AppliedPatchSet<M: MemoryMut> {
+  groups: Vec<AppliedGroup<M>>,
+  chained: Vec<ChainedHook>,
+}
+impl<M: MemoryMut> AppliedPatchSet<M> {
+  /// The patches which were applied over another mod's hook.
+  #[inline]
+  pub fn chained_hooks(&self) -> &[ChainedHook] {
+    &self.chained
+  }
+
+  /// The number of applied patches.
+  pub fn len(&self) -> usize {
+    self.groups.iter().map(|g| g.originals.len()).sum()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Restores the original bytes of every patch. If a patch fails to restore
+  /// the set is returned with the remaining patches.
+  pub fn restore(mut self) -> Result<(), Self> {
+    if self.restore_inner(false) {
+      Ok(())
+    } else {
+      Err(self)
+    }
+  }
+
+  /// Restores the original bytes of every patch, then frees any code allocated
+  /// for them. If a patch fails to restore the set is returned with the
+  /// remaining patches.
+  ///
+  /// # Safety
+  /// No thread may be running any of the patches' code (e.g. a detour's
+  /// trampoline) or be about to return into it once the original bytes are
+  /// restored.
+  pub unsafe fn restore_and_free(mut self) -> Result<(), Self> {
+    if self.restore_inner(true) {
+      Ok(())
+    } else {
+      Err(self)
+    }
+  }
+
+  /// Leaves every patch in place permanently.
+  pub fn keep(mut self) {
+    self.groups.clear();
+  }
+
+  /// Restores the patches in reverse order, freeing their code if requested.
+  fn restore_inner(&mut self, free: bool) -> bool {
+    while let Some(g) = self.groups.last_mut() {
+      while let Some(original) = g.originals.last() {
+        if !write_original(&mut g.mem, original.offset, &original.bytes) {
+          return false;
+        }
+        if free {
+          // Safety: guaranteed by the caller of `restore_and_free`.
+          unsafe { free_code(&original.code) };
+        }
+        g.originals.pop();
+      }
+      self.groups.pop();
+    }
+    true
+  }
+}
+impl<M: MemoryMut> Drop for AppliedPatchSet<M> {
+  fn drop(&mut self) {
+    self.restore_inner(false);
+  }
+}
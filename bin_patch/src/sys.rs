@@ -0,0 +1,19 @@
+//! Memory management for each supported platform.
+
+#[cfg(unix)]
+mod unix;
+#[cfg(windows)]
+mod windows;
+
+#[cfg(unix)]
+pub(crate) use self::unix::*;
+#[cfg(windows)]
+pub(crate) use self::windows::*;
+
+/// A range of the process's address space with the same state.
+pub(crate) struct Region {
+  pub start: usize,
+  pub end: usize,
+  /// Whether nothing is mapped in the range.
+  pub free: bool,
+}
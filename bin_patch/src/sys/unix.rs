@@ -0,0 +1,156 @@
+use super::Region;
+use core::ffi::c_void;
+use libc::{
+  mmap, mprotect, munmap, sysconf, _SC_PAGESIZE, MAP_ANONYMOUS, MAP_FAILED, MAP_PRIVATE, PROT_EXEC,
+  PROT_READ, PROT_WRITE,
+};
+use std::fs;
+
+/// The base address of a loaded module. Has the same type as `HMODULE`.
+pub(crate) type ModuleHandle = isize;
+
+fn page_size() -> usize {
+  unsafe { sysconf(_SC_PAGESIZE) as usize }
+}
+
+/// Reads the mapped ranges of the process's address space along with their
+/// protection. Only available on systems with `/proc/self/maps`.
+fn mappings() -> Option<Vec<(usize, usize, i32)>> {
+  let maps = fs::read_to_string("/proc/self/maps").ok()?;
+  maps
+    .lines()
+    .map(|line| {
+      let mut parts = line.split_whitespace();
+      let (start, end) = parts.next()?.split_once('-')?;
+      let perms = parts.next()?.as_bytes();
+      let prot = [(b'r', PROT_READ), (b'w', PROT_WRITE), (b'x', PROT_EXEC)]
+        .into_iter()
+        .zip(perms)
+        .fold(
+          0,
+          |prot, ((c, flag), &x)| if c == x { prot | flag } else { prot },
+        );
+      Some((
+        usize::from_str_radix(start, 16).ok()?,
+        usize::from_str_radix(end, 16).ok()?,
+        prot,
+      ))
+    })
+    .collect()
+}
+
+/// Makes a range of memory writable until this is dropped.
+pub(crate) struct MemUnlock {
+  /// Each mapping in the range along with its previous protection.
+  prev: Vec<(usize, usize, i32)>,
+}
+impl MemUnlock {
+  pub unsafe fn new(address: *const c_void, len: usize) -> Option<Self> {
+    // `mprotect` requires a page aligned address and doesn't return the
+    // previous protection. The range may cover multiple mappings, each of
+    // which has its own protection restored.
+    let start = address as usize & !(page_size() - 1);
+    let end = (address as usize).checked_add(len)?;
+    let mut prev = Vec::new();
+    let mut pos = start;
+    for (s, e, prot) in mappings()? {
+      if pos >= end {
+        break;
+      }
+      if e <= pos {
+        continue;
+      }
+      if s > pos {
+        return None;
+      }
+      let e = e.min(end);
+      prev.push((pos, e - pos, prot));
+      pos = e;
+    }
+    if pos < end {
+      return None;
+    }
+    let mut unlock = Self { prev: Vec::with_capacity(prev.len()) };
+    for (address, len, prot) in prev {
+      if mprotect(
+        address as *mut c_void,
+        len,
+        PROT_READ | PROT_WRITE | PROT_EXEC,
+      ) != 0
+      {
+        // Dropping restores the ranges changed so far.
+        return None;
+      }
+      unlock.prev.push((address, len, prot));
+    }
+    Some(unlock)
+  }
+}
+impl Drop for MemUnlock {
+  fn drop(&mut self) {
+    for &(address, len, prot) in &self.prev {
+      unsafe {
+        mprotect(address as *mut c_void, len, prot);
+      }
+    }
+  }
+}
+
+/// Checks that every byte in the range is mapped and readable.
+pub(crate) fn is_readable(address: usize, len: usize) -> bool {
+  let (Some(end), Some(maps)) = (address.checked_add(len), mappings()) else {
+    return false;
+  };
+  let mut pos = address;
+  for (s, e, prot) in maps {
+    if pos >= end {
+      break;
+    }
+    if e <= pos {
+      continue;
+    }
+    if s > pos || prot & PROT_READ == 0 {
+      return false;
+    }
+    pos = e;
+  }
+  pos >= end
+}
+
+/// Gets the region of the address space containing the address.
+pub(crate) fn query(address: usize) -> Option<Region> {
+  let mut start = 0;
+  for (s, e, _) in mappings()? {
+    if address < s {
+      return Some(Region { start, end: s, free: true });
+    }
+    if address < e {
+      return Some(Region { start: s, end: e, free: false });
+    }
+    start = e;
+  }
+  Some(Region { start, end: usize::MAX, free: true })
+}
+
+/// Maps executable memory at exactly the given address.
+pub(crate) fn reserve_exec(address: usize, size: usize) -> Option<usize> {
+  unsafe {
+    let block = mmap(
+      address as *mut c_void,
+      size,
+      PROT_READ | PROT_WRITE | PROT_EXEC,
+      MAP_PRIVATE | MAP_ANONYMOUS,
+      -1,
+      0,
+    );
+    if block == MAP_FAILED {
+      None
+    } else if block as usize != address {
+      // The address is only a hint.
+      munmap(block, size);
+      None
+    } else {
+      Some(address)
+    }
+  }
+}
@@ -0,0 +1,122 @@
+use super::Region;
+use core::{
+  ffi::c_void,
+  mem::{size_of, MaybeUninit},
+};
+use windows_sys::Win32::{
+  Foundation::HMODULE,
+  System::Memory::{
+    VirtualAlloc, VirtualProtect, VirtualQuery, MEMORY_BASIC_INFORMATION, MEM_COMMIT, MEM_FREE,
+    MEM_RESERVE, PAGE_EXECUTE_READ, PAGE_EXECUTE_READWRITE, PAGE_EXECUTE_WRITECOPY, PAGE_GUARD,
+    PAGE_READONLY, PAGE_READWRITE, PAGE_WRITECOPY,
+  },
+};
+
+/// The handle to a loaded module. This is also the module's base address.
+pub(crate) type ModuleHandle = HMODULE;
+
+/// Makes a range of memory writable until this is dropped.
+pub(crate) struct MemUnlock {
+  /// Each region in the range along with its previous protection.
+  prev: Vec<(usize, usize, u32)>,
+}
+impl MemUnlock {
+  pub unsafe fn new(address: *const c_void, len: usize) -> Option<Self> {
+    // `VirtualProtect` only returns the protection of the first page. Query
+    // each region so they all have their own protection restored.
+    let end = (address as usize).checked_add(len)?;
+    let mut pos = address as usize;
+    let mut unlock = Self { prev: Vec::new() };
+    while pos < end {
+      let info = basic_info(pos)?;
+      let next = (info.BaseAddress as usize).checked_add(info.RegionSize)?.min(end);
+      let mut prev = 0u32;
+      if VirtualProtect(
+        pos as *const c_void,
+        next - pos,
+        PAGE_EXECUTE_READWRITE,
+        &mut prev,
+      ) == 0
+      {
+        // Dropping restores the ranges changed so far.
+        return None;
+      }
+      unlock.prev.push((pos, next - pos, prev));
+      pos = next;
+    }
+    Some(unlock)
+  }
+}
+impl Drop for MemUnlock {
+  fn drop(&mut self) {
+    for &(address, len, prev) in &self.prev {
+      let mut old = 0u32;
+      unsafe {
+        VirtualProtect(address as *const c_void, len, prev, &mut old);
+      }
+    }
+  }
+}
+
+fn basic_info(address: usize) -> Option<MEMORY_BASIC_INFORMATION> {
+  let mut info = MaybeUninit::<MEMORY_BASIC_INFORMATION>::uninit();
+  unsafe {
+    if VirtualQuery(
+      address as *const c_void,
+      info.as_mut_ptr(),
+      size_of::<MEMORY_BASIC_INFORMATION>(),
+    ) == 0
+    {
+      return None;
+    }
+    Some(info.assume_init())
+  }
+}
+
+/// Checks that every byte in the range is committed and readable.
+pub(crate) fn is_readable(address: usize, len: usize) -> bool {
+  const READABLE: u32 = PAGE_READONLY
+    | PAGE_READWRITE
+    | PAGE_WRITECOPY
+    | PAGE_EXECUTE_READ
+    | PAGE_EXECUTE_READWRITE
+    | PAGE_EXECUTE_WRITECOPY;
+  let Some(end) = address.checked_add(len) else {
+    return false;
+  };
+  let mut pos = address;
+  while pos < end {
+    let Some(info) = basic_info(pos) else {
+      return false;
+    };
+    if info.State != MEM_COMMIT || info.Protect & READABLE == 0 || info.Protect & PAGE_GUARD != 0 {
+      return false;
+    }
+    pos = info.BaseAddress as usize + info.RegionSize;
+  }
+  true
+}
+
+/// Gets the region of the address space containing the address.
+pub(crate) fn query(address: usize) -> Option<Region> {
+  let info = basic_info(address)?;
+  let start = info.BaseAddress as usize;
+  Some(Region {
+    start,
+    end: start.checked_add(info.RegionSize)?,
+    free: info.State == MEM_FREE,
+  })
+}
+
+/// Maps executable memory at exactly the given address.
+pub(crate) fn reserve_exec(address: usize, size: usize) -> Option<usize> {
+  let block = unsafe {
+    VirtualAlloc(
+      address as *const c_void,
+      size,
+      MEM_COMMIT | MEM_RESERVE,
+      PAGE_EXECUTE_READWRITE,
+    )
+  };
+  (!block.is_null()).then_some(block as usize)
+}
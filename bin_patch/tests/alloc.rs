@@ -0,0 +1,61 @@
+#![cfg(unix)]
+
+use bin_patch::{patch_data, patch_source, ModuleImage, Patch, PatchSet};
+use core::mem;
+
+mod common;
+use common::{pe_file, Module, CODE_RVA};
+
+/// Gets the target of the `jmp` at the given offset.
+fn jmp_target(module: &Module, offset: usize) -> usize {
+  let bytes = module.bytes();
+  assert_eq!(bytes[offset], 0xe9);
+  let rel = i32::from_le_bytes(bytes[offset + 1..offset + 5].try_into().unwrap());
+  (module.ptr as usize + offset + 5).wrapping_add_signed(rel as isize)
+}
+
+#[test]
+fn padding() {
+  const OFFSET: usize = CODE_RVA as usize;
+  const PATCH: Patch = Patch::raw_reloc(
+    OFFSET,
+    patch_source!("55 8bec 5d c3"),
+    patch_data!("b8 01000000 b9 02000000"),
+  );
+  // A function followed by padding up to the end of the code section.
+  let mut code = vec![0xcc; 0x100];
+  code[..5].copy_from_slice(&[0x55, 0x8b, 0xec, 0x5d, 0xc3]);
+  let image = ModuleImage::from_pe_file(&pe_file(&code, &[], &[])).unwrap();
+  let module = Module::new(image.bytes());
+  // The padding stays in use after the test, so the module is never unloaded.
+  let module = &*Box::leak(Box::new(module));
+  let original = module.bytes().to_vec();
+  let padding = module.ptr as usize + OFFSET + 0x10..module.ptr as usize + OFFSET + 0x100;
+
+  // The data doesn't fit, so it's placed in the padding after the function.
+  let applied = unsafe { PATCH.apply(module.get(), 0) }.unwrap();
+  let cave = jmp_target(module, OFFSET);
+  assert!(padding.contains(&cave), "{cave:#x}");
+  assert_eq!(
+    module.bytes()[cave - module.ptr as usize..][..10],
+    [0xb8, 0x01, 0x00, 0x00, 0x00, 0xb9, 0x02, 0x00, 0x00, 0x00]
+  );
+
+  // Freeing the code restores the padding, which can then be reused.
+  unsafe { applied.restore_and_free() }.ok().unwrap();
+  assert_eq!(module.bytes(), original);
+  let mut set = PatchSet::new();
+  set.add(module.get(), 0, &[PATCH]);
+  let applied = unsafe { set.apply() }.ok().unwrap();
+  assert_eq!(jmp_target(module, OFFSET), cave);
+  unsafe { applied.restore_and_free() }.ok().unwrap();
+  assert_eq!(module.bytes(), original);
+
+  // Restoring without freeing leaves the code allocated.
+  let applied = unsafe { PATCH.apply(module.get(), 0) }.unwrap();
+  assert_eq!(jmp_target(module, OFFSET), cave);
+  drop(applied);
+  let applied = unsafe { PATCH.apply(module.get(), 0) }.unwrap();
+  assert_ne!(jmp_target(module, OFFSET), cave);
+  mem::forget(applied);
+}
@@ -0,0 +1,211 @@
+#![cfg(unix)]
+
+use bin_patch::{asm_patch, parse_patches, patch_data, patch_source, Hook, Patch, Reg};
+use core::slice;
+
+mod common;
+use common::{page_size, Module};
+use libc::{PROT_EXEC, PROT_READ};
+
+unsafe extern "C" fn hook() {}
+unsafe extern "C" fn reg_hook(_: u32, _: u32) -> u32 {
+  0
+}
+
+/// Encodes the displacement of a `rel32` branch at the given offset.
+fn rel32(module: &Module, offset: usize, target: usize) -> [u8; 4] {
+  (target.wrapping_sub(module.ptr as usize + offset + 5) as u32).to_le_bytes()
+}
+
+/// Gets the target of the `rel32` branch at the given offset.
+fn branch_target(module: &Module, offset: usize) -> usize {
+  let bytes = module.bytes();
+  let rel = i32::from_le_bytes(bytes[offset + 1..offset + 5].try_into().unwrap());
+  (module.ptr as usize + offset + 5).wrapping_add_signed(rel as isize)
+}
+
+/// Applies a `nop` patch of the given size and returns the written bytes.
+fn nop_fill(len: usize) -> Vec<u8> {
+  let module = Module::new(&[0xcc; 0x100]);
+  let src = format!("0x10 nop\n  data {}", "xx".repeat(len));
+  let patches = parse_patches(&src, |_| None).unwrap();
+  let applied = unsafe { patches[0].apply(module.get(), 0) }.unwrap();
+  let bytes = module.bytes()[0x10..0x10 + len].to_vec();
+  assert!(applied.restore().is_ok());
+  assert_eq!(module.bytes(), [0xcc; 0x100]);
+  bytes
+}
+
+#[test]
+fn nop() {
+  assert_eq!(nop_fill(1), [0x90]);
+  assert_eq!(nop_fill(3), [0x0f, 0x1f, 0x00]);
+  assert_eq!(
+    nop_fill(11),
+    [0x0f, 0x1f, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0f, 0x1f, 0x00]
+  );
+  let mut expected = vec![0xeb, 0x12];
+  expected.resize(20, 0x90);
+  assert_eq!(nop_fill(20), expected);
+  let mut expected = vec![0xe9, 0x87, 0x00, 0x00, 0x00];
+  expected.resize(140, 0x90);
+  assert_eq!(nop_fill(140), expected);
+}
+
+#[test]
+fn restore_protection() {
+  // A patch crossing into a page with a different protection restores both.
+  let page = page_size();
+  let module = Module::new(&vec![0xcc; page * 2]);
+  module.protect(page, page, PROT_READ);
+  let patches = parse_patches(&format!("{:#x} nop\n  cc cc cc cc", page - 2), |_| None).unwrap();
+  let applied = unsafe { patches[0].apply(module.get(), 0) }.unwrap();
+  assert_eq!(module.bytes()[page - 2..page + 2], [0x0f, 0x1f, 0x40, 0x00]);
+  assert_eq!(module.protection(0), PROT_READ | PROT_EXEC);
+  assert_eq!(module.protection(page), PROT_READ);
+  assert!(applied.restore().is_ok());
+  assert_eq!(module.bytes()[page - 2..page + 2], [0xcc; 4]);
+  assert_eq!(module.protection(0), PROT_READ | PROT_EXEC);
+  assert_eq!(module.protection(page), PROT_READ);
+}
+
+#[test]
+fn relocated_source() {
+  const PATCH: Patch = Patch::nop(0, patch_source!("a1 $78563412"));
+  let module = Module::new(&[0xa1, 0x78, 0x66, 0x34, 0x12, 0xcc]);
+  assert!(PATCH.has_expected(&module.get(), 0x1000));
+  assert!(!PATCH.has_expected(&module.get(), 0));
+}
+
+#[test]
+fn raw_reloc() {
+  const PATCH: Patch = Patch::raw_reloc(
+    1,
+    patch_source!("b8 xxxxxxxx b9 xxxxxxxx"),
+    patch_data!("b8 $00100000 b9 @00200000"),
+  );
+  let module = Module::new(&[0xcc; 12]);
+  let applied = unsafe { PATCH.apply(module.get(), 0x1000) }.unwrap();
+  let base = (module.ptr as u32).wrapping_add(0x2000).to_le_bytes();
+  assert_eq!(
+    module.bytes(),
+    [0xcc, 0xb8, 0x00, 0x20, 0x00, 0x00, 0xb9, base[0], base[1], base[2], base[3], 0xcc]
+  );
+  drop(applied);
+  assert_eq!(module.bytes(), [0xcc; 12]);
+}
+
+#[test]
+fn raw_branch() {
+  // The branch targets `0x1000` in the module's original address space.
+  const IN_PLACE: Patch = Patch::raw_reloc(
+    0x10,
+    patch_source!("50 e8 xxxxxxxx 58"),
+    asm_patch!(
+      "
+      push ecx
+      call $00001000
+      pop ecx
+    "
+    ),
+  );
+  const OUT_OF_LINE: Patch = Patch::raw_reloc(
+    0x10,
+    patch_source!("50 e8 xxxxxxxx"),
+    asm_patch!(
+      "
+      push ecx
+      call $00001000
+      pop ecx
+    "
+    ),
+  );
+  let module = Module::new(&[0xcc; 0x100]);
+  let base = module.ptr as usize;
+  let reloc_dist = (base + 0x80) as isize - 0x1000;
+
+  let applied = unsafe { IN_PLACE.apply(module.get(), reloc_dist) }.unwrap();
+  assert_eq!(
+    module.bytes()[0x10..0x17],
+    [0x51, 0xe8, 0x6a, 0x00, 0x00, 0x00, 0x59]
+  );
+  drop(applied);
+
+  // The code is moved out of line and the branch is adjusted for the move.
+  let applied = unsafe { OUT_OF_LINE.apply(module.get(), reloc_dist) }.unwrap();
+  assert_eq!(module.bytes()[0x10], 0xe9);
+  let cave = branch_target(&module, 0x10);
+  let code = unsafe { slice::from_raw_parts(cave as *const u8, 12) };
+  assert_eq!(code[..2], [0x51, 0xe8]);
+  assert_eq!(
+    code[2..6],
+    ((base + 0x80).wrapping_sub(cave + 6) as u32).to_le_bytes()
+  );
+  assert_eq!(code[6..7], [0x59]);
+  assert_eq!(
+    code[8..12],
+    ((base + 0x16).wrapping_sub(cave + 12) as u32).to_le_bytes()
+  );
+  drop(applied);
+  assert_eq!(module.bytes(), [0xcc; 0x100]);
+}
+
+#[test]
+fn call() {
+  const PATCH: Patch = Patch::call_c(0x10, patch_source!("8b45 08 50 5d 90"), hook);
+  let module = Module::new(&[0xcc; 0x20]);
+  let applied = unsafe { PATCH.apply(module.get(), 0) }.unwrap();
+  assert_eq!(module.bytes()[0x10], 0xe8);
+  assert_eq!(
+    module.bytes()[0x11..0x15],
+    rel32(&module, 0x10, hook as *const () as usize)
+  );
+  assert_eq!(module.bytes()[0x15], 0x90);
+  drop(applied);
+
+  // The call is placed after NOPs.
+  const AT: Patch = Patch::call_c_at(0x10, patch_source!("8b45 08 50 8bec 5d 90"), hook, 3);
+  let applied = unsafe { AT.apply(module.get(), 0) }.unwrap();
+  assert_eq!(module.bytes()[0x10..0x14], [0x90, 0x90, 0x90, 0xe8]);
+  assert_eq!(
+    module.bytes()[0x14..0x18],
+    rel32(&module, 0x13, hook as *const () as usize)
+  );
+  drop(applied);
+  assert_eq!(module.bytes(), [0xcc; 0x20]);
+}
+
+#[test]
+fn call_regs() {
+  const PATCH: Patch = Patch::call_c2(
+    0x10,
+    patch_source!("e8 xxxxxxxx"),
+    reg_hook,
+    [Reg::Esi, Reg::Eax],
+  );
+  let module = Module::new(&[0xcc; 0x20]);
+  let applied = unsafe { PATCH.apply(module.get(), 0) }.unwrap();
+  assert_eq!(module.bytes()[0x10], 0xe8);
+
+  // The stub pushes the arguments in reverse, calls the hook and pops them.
+  let stub = branch_target(&module, 0x10);
+  let code = unsafe { slice::from_raw_parts(stub as *const u8, 11) };
+  let rel = (reg_hook as *const () as usize).wrapping_sub(stub + 7) as u32;
+  let mut expected = vec![0x50, 0x56, 0xe8];
+  expected.extend_from_slice(&rel.to_le_bytes());
+  expected.extend_from_slice(&[0x83, 0xc4, 0x08, 0xc3]);
+  assert_eq!(code, expected);
+  drop(applied);
+
+  // The same from a patch file.
+  let hooks = |name: &str| (name == "reg_hook").then(|| Hook::c2(reg_hook));
+  let patches = parse_patches("0x10 call_c2 reg_hook esi eax\n  e8 xxxxxxxx", hooks).unwrap();
+  let applied = unsafe { patches[0].apply(module.get(), 0) }.unwrap();
+  let stub = branch_target(&module, 0x10);
+  assert_eq!(
+    unsafe { slice::from_raw_parts(stub as *const u8, 2) },
+    [0x50, 0x56]
+  );
+  drop(applied);
+  assert_eq!(module.bytes(), [0xcc; 0x20]);
+}
@@ -0,0 +1,105 @@
+use bin_patch::{asm_patch, patch_data};
+
+type Data = (
+  &'static [u8],
+  &'static [u16],
+  &'static [u16],
+  &'static [u16],
+);
+
+#[test]
+fn encoding() {
+  const ASM: Data = asm_patch!(
+    "
+    start:
+      push dword ptr [esp + 8]
+      pop dword ptr [ebp - 4]
+      call dword ptr [$6fb7e840]
+      jmp dword ptr [eax*4 + @1234]
+      mov ax, si
+      mov dword ptr [eax + ecx*2 - 0x200], 0x12345678
+      mov eax, dword ptr [$6fb8a0c4]
+      lea eax, [ecx + edx*8 + 3]
+      add ecx, 0x1000
+      sub esp, 8
+      cmp cl, [edi]
+      je start
+      jne skip
+      call start
+    skip:
+      nop
+  "
+  );
+  const DATA: Data = patch_data!(
+    "
+    ff742408
+    8f45fc
+    ff15 $40e8b76f
+    ff2485 @34120000
+    6689f0
+    c78448 00feffff 78563412
+    8b05 $c4a0b86f
+    8d44d103
+    81c1 00100000
+    83ec08
+    3a0f
+    74c7
+    7505
+    e8 c0ffffff
+    90
+  "
+  );
+  assert_eq!(ASM, DATA);
+}
+
+#[test]
+fn branch_size() {
+  // Short branches are lengthened once the target is out of range.
+  const ASM: Data = asm_patch!(
+    "
+      jmp end
+      lea esp, [esp + 0x80]
+      lea esp, [esp + 0x80]
+      lea esp, [esp + 0x80]
+      lea esp, [esp + 0x80]
+      lea esp, [esp + 0x80]
+      lea esp, [esp + 0x80]
+      lea esp, [esp + 0x80]
+      lea esp, [esp + 0x80]
+      lea esp, [esp + 0x80]
+      lea esp, [esp + 0x80]
+      lea esp, [esp + 0x80]
+      lea esp, [esp + 0x80]
+      lea esp, [esp + 0x80]
+      lea esp, [esp + 0x80]
+      lea esp, [esp + 0x80]
+      lea esp, [esp + 0x80]
+      lea esp, [esp + 0x80]
+      lea esp, [esp + 0x80]
+      jb end
+    end:
+  "
+  );
+  assert_eq!(ASM.0[..5], [0xe9, 0x80, 0x00, 0x00, 0x00]);
+  assert_eq!(ASM.0[ASM.0.len() - 2..], [0x72, 0x00]);
+}
+
+#[test]
+fn branch_to_address() {
+  const ASM: Data = asm_patch!(
+    "
+      push eax
+      call $6fb7e840
+      jz $6fb7e850
+      jmp $6fb7e860
+  "
+  );
+  assert_eq!(
+    ASM.0,
+    [
+      0x50, 0xe8, 0x40, 0xe8, 0xb7, 0x6f, 0x0f, 0x84, 0x50, 0xe8, 0xb7, 0x6f, 0xe9, 0x60, 0xe8,
+      0xb7, 0x6f
+    ]
+  );
+  assert_eq!((ASM.1, ASM.2, ASM.3), (&[][..], &[][..], &[2, 8, 13][..]));
+}
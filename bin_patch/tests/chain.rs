@@ -0,0 +1,194 @@
+#![cfg(unix)]
+
+use bin_patch::{
+  parse_patches, AppliedPatchSet, ForeignHookKind, Hook, LoadedModule, Patch, PatchError, PatchSet,
+  PatchSetError,
+};
+use core::slice;
+
+mod common;
+use common::Module;
+
+unsafe extern "C" fn hook() {}
+unsafe extern "C" fn reg_hook(_: u32) {}
+
+/// Parses a patch at `0x10` over `mov eax, [ebp+8]; push ebp; mov ebp, esp`.
+/// Parsed patches always have their expected bytes.
+fn patch(kind: &str) -> Vec<Patch> {
+  let text = format!("0x10 {kind}\n  8b45 08 55 8bec");
+  parse_patches(&text, |name| match name {
+    "hook" => Some(Hook::c(hook)),
+    "stub" => Some(Hook::stub(hook)),
+    "reg" => Some(Hook::c1(reg_hook)),
+    _ => None,
+  })
+  .unwrap()
+}
+
+/// Encodes a `rel32` branch at the given address.
+fn branch(op: u8, address: usize, target: usize) -> Vec<u8> {
+  let mut bytes = vec![op];
+  bytes.extend_from_slice(&(target.wrapping_sub(address + 5) as u32).to_le_bytes());
+  bytes
+}
+
+/// Gets the target of the `jmp` at the given offset.
+fn jmp_target(module: &Module, offset: usize) -> usize {
+  let bytes = module.bytes();
+  assert_eq!(bytes[offset], 0xe9);
+  let rel = i32::from_le_bytes(bytes[offset + 1..offset + 5].try_into().unwrap());
+  (module.ptr as usize + offset + 5).wrapping_add_signed(rel as isize)
+}
+
+/// Reads generated code.
+fn code(address: usize, len: usize) -> &'static [u8] {
+  unsafe { slice::from_raw_parts(address as *const u8, len) }
+}
+
+/// Creates a module where another mod has written a `call` or `jmp` to `0x80`
+/// over the start of `mov eax, [ebp+8]; push ebp; mov ebp, esp` at `0x10`.
+fn load(op: u8) -> Module {
+  let module = load_original();
+  let base = module.ptr as usize;
+  let mut hook = branch(op, base + 0x10, base + 0x80);
+  hook.push(0x90);
+  module.write(0x10, &hook);
+  module
+}
+
+/// Creates a module containing `mov eax, [ebp+8]; push ebp; mov ebp, esp` at
+/// `0x10`.
+fn load_original() -> Module {
+  let mut bytes = vec![0xcc; 0x100];
+  bytes[0x10..0x16].copy_from_slice(&[0x8b, 0x45, 0x08, 0x55, 0x8b, 0xec]);
+  Module::new(&bytes)
+}
+
+fn apply(
+  module: &Module,
+  patch: &[Patch],
+  checked: bool,
+) -> Result<AppliedPatchSet<LoadedModule>, Vec<PatchSetError>> {
+  let mut set = PatchSet::new();
+  set.add(module.get(), 0, patch);
+  unsafe {
+    if checked {
+      set.apply()
+    } else {
+      set.apply_unchecked()
+    }
+  }
+}
+
+#[test]
+fn chain_call() {
+  let patch = patch("call_c hook");
+  for checked in [true, false] {
+    let module = load(0xe8);
+    let base = module.ptr as usize;
+    let original = module.bytes()[0x10..0x16].to_vec();
+    let applied = apply(&module, &patch, checked).unwrap();
+    let chained = applied.chained_hooks();
+    assert_eq!(chained.len(), 1);
+    assert_eq!((chained[0].group, chained[0].offset), (0, 0x10));
+    assert_eq!(chained[0].hook.kind, ForeignHookKind::Call);
+    assert_eq!(chained[0].hook.target, base + 0x80);
+
+    // The call is replaced by a jump to a thunk which calls both hooks.
+    let thunk = jmp_target(&module, 0x10);
+    let mut expected = branch(0xe8, thunk, base + 0x80);
+    expected.extend(branch(0xe8, thunk + 5, hook as *const () as usize));
+    expected.extend(branch(0xe9, thunk + 10, base + 0x15));
+    assert_eq!(code(thunk, expected.len()), expected);
+    assert_eq!(module.bytes()[0x15], 0x90);
+
+    drop(applied);
+    assert_eq!(module.bytes()[0x10..0x16], original);
+  }
+}
+
+#[test]
+fn chain_jmp() {
+  let patch = patch("jmp_c stub");
+  let module = load(0xe8);
+  let base = module.ptr as usize;
+  let applied = apply(&module, &patch, true).unwrap();
+  assert_eq!(applied.chained_hooks().len(), 1);
+
+  let thunk = jmp_target(&module, 0x10);
+  let mut expected = branch(0xe8, thunk, base + 0x80);
+  expected.extend(branch(0xe9, thunk + 5, hook as *const () as usize));
+  assert_eq!(code(thunk, expected.len()), expected);
+}
+
+#[test]
+fn foreign_jmp() {
+  // Code after another mod's `jmp` may never run, so it can't be chained.
+  let patch = patch("call_c hook");
+  let module = load(0xe9);
+  let original = module.bytes().to_vec();
+  let errors = apply(&module, &patch, true).err().unwrap();
+  assert!(matches!(errors[..], [ref e] if matches!(e.error, PatchError::Mismatch(_))));
+  assert_eq!(module.bytes(), original);
+
+  // Without the check the other mod's hook is overwritten.
+  let applied = apply(&module, &patch, false).unwrap();
+  assert!(applied.chained_hooks().is_empty());
+  assert_eq!(
+    module.bytes()[0x10..0x15],
+    branch(0xe8, module.ptr as usize + 0x10, hook as *const () as usize)
+  );
+}
+
+/// Checks that applying the patch fails the check without chaining a hook.
+fn assert_not_chained(module: &Module, patch: &[Patch]) {
+  let errors = apply(module, patch, true).err().unwrap();
+  assert!(matches!(errors[..], [ref e] if matches!(e.error, PatchError::Mismatch(_))));
+  let applied = apply(module, patch, false).unwrap();
+  assert!(applied.chained_hooks().is_empty());
+}
+
+#[test]
+fn partial_match() {
+  // The rest of the code must match, apart from `nop`s padding the replaced
+  // instructions.
+  let patch = patch("call_c hook");
+  let module = load(0xe8);
+  module.write(0x15, &[0xed]);
+  assert_not_chained(&module, &patch);
+}
+
+#[cfg(not(feature = "expected-bytes"))]
+#[test]
+fn without_expected_bytes() {
+  // Only the hash is available, so the rest of the code can't be compared.
+  static PATCH: [Patch; 1] = [Patch::call_c(
+    0x10,
+    bin_patch::patch_source!("8b45 08 55 8bec"),
+    hook,
+  )];
+  assert_not_chained(&load(0xe8), &PATCH);
+}
+
+#[test]
+fn reapply() {
+  // A patch applied a second time finds its own code rather than another mod's.
+  for kind in ["call_c hook", "call_c1 reg esi", "call_c_at hook 1"] {
+    let patch = patch(kind);
+    let module = load_original();
+    apply(&module, &patch, true).unwrap().keep();
+    let first = module.bytes().to_vec();
+
+    let errors = apply(&module, &patch, true).err().unwrap();
+    assert!(
+      matches!(errors[..], [ref e] if matches!(e.error, PatchError::Mismatch(_))),
+      "{kind}"
+    );
+    let applied = apply(&module, &patch, false).unwrap();
+    assert!(applied.chained_hooks().is_empty(), "{kind}");
+    applied.keep();
+    assert_eq!(module.bytes()[..0x10], first[..0x10], "{kind}");
+    assert_eq!(module.bytes()[0x16..], first[0x16..], "{kind}");
+    assert_ne!(module.bytes()[0x10], 0xe9, "{kind}");
+  }
+}
@@ -0,0 +1,160 @@
+use bin_patch::{parse_patches, ForeignHook, ForeignHookKind, Found, ModuleImage, Patch};
+
+fn patch(src: &str) -> Patch {
+  parse_patches(&format!("0x2 nop\n{src}"), |_| None).unwrap()[0]
+}
+
+fn image(bytes: &[u8]) -> ModuleImage<Vec<u8>> {
+  let mut image = vec![0xcc; 2];
+  image.extend_from_slice(bytes);
+  image.extend_from_slice(&[0xcc; 2]);
+  ModuleImage::new(0x1000, image)
+}
+
+#[test]
+fn mismatch() {
+  let p = patch("  8b45 xx\n  a1 $00104000");
+  assert!(p
+    .check(
+      &image(&[0x8b, 0x45, 0x10, 0xa1, 0x00, 0x20, 0x40, 0x00]),
+      0x1000
+    )
+    .is_ok());
+
+  let e = p
+    .check(
+      &image(&[0x8b, 0x4d, 0x10, 0xa1, 0x00, 0x20, 0x40, 0x00]),
+      0x1000,
+    )
+    .unwrap_err();
+  assert_eq!(e.offset(), 2);
+  assert_eq!(
+    e.found(),
+    &Found::Bytes([0x8b, 0x4d, 0x10, 0xa1, 0x00, 0x20, 0x40, 0x00].into())
+  );
+  assert_eq!(
+    e.expected(),
+    Some(&[0x8b, 0x45, 0x00, 0xa1, 0x00, 0x10, 0x40, 0x00][..])
+  );
+  assert_eq!(
+    e.actual_unrelocated().as_deref(),
+    Some(&[0x8b, 0x4d, 0x10, 0xa1, 0x00, 0x10, 0x40, 0x00][..])
+  );
+  assert_eq!(e.first_difference(), Some(1));
+  assert_eq!(e.foreign_hook(), None);
+  assert_eq!(
+    e.to_string(),
+    "at 0x2: expected `8b 45 xx a1 $00104000`, found `8b 4d 10 a1 $00104000`; first difference at \
+     +0x1"
+  );
+
+  // Relocated values are compared after being adjusted.
+  let e = p
+    .check(&image(&[0x8b, 0x45, 0x10, 0xa1, 0x00, 0x20, 0x40, 0x00]), 0)
+    .unwrap_err();
+  assert_eq!(e.first_difference(), Some(5));
+}
+
+#[test]
+fn mismatch_without_bytes() {
+  let p = patch("  8b45 08");
+  let e = p.with_offset(0x10).check(&image(&[]), 0).unwrap_err();
+  assert_eq!(e.found(), &Found::Unreadable);
+  assert_eq!(e.actual(), None);
+  assert_eq!(e.first_difference(), None);
+  assert_eq!(e.to_string(), "failed to read memory at 0x10");
+
+  #[cfg(target_pointer_width = "64")]
+  {
+    let e = p.check(&image(&[0x8b, 0x45, 0x08]), 0x1_0000_0000).unwrap_err();
+    assert_eq!(e.found(), &Found::RelocOverflow);
+    assert_eq!(e.actual(), None);
+    assert_eq!(
+      e.to_string(),
+      "at 0x2: the relocation distance doesn't fit in 32 bits"
+    );
+  }
+}
+
+#[test]
+fn foreign_hook() {
+  let p = patch("  55\n  8bec\n  8b45 08\n  5d");
+  // A `jmp` written over the second and third instruction.
+  let e = p
+    .check(&image(&[0x55, 0xe9, 0xf8, 0x0f, 0x00, 0x00, 0x5d]), 0)
+    .unwrap_err();
+  assert_eq!(e.first_difference(), Some(1));
+  let hook = ForeignHook { kind: ForeignHookKind::Jmp, pos: 1, target: 0x2000 };
+  assert_eq!(e.foreign_hook(), Some(hook));
+  assert_eq!(e.retargeted_hook(), None);
+  assert!(e.to_string().ends_with("; found a `jmp` to 0x2000 at +0x1"));
+
+  // A `call` at the start.
+  let e = p
+    .check(&image(&[0xe8, 0xf9, 0x0f, 0x00, 0x00, 0x90, 0x5d]), 0)
+    .unwrap_err();
+  let hook = ForeignHook {
+    kind: ForeignHookKind::Call,
+    pos: 0,
+    target: 0x2000,
+  };
+  assert_eq!(e.foreign_hook(), Some(hook));
+
+  let e = p
+    .check(&image(&[0x55, 0x8b, 0xec, 0x8b, 0x45, 0x0c, 0x5d]), 0)
+    .unwrap_err();
+  assert_eq!(e.foreign_hook(), None);
+}
+
+#[test]
+fn overwritten_hook() {
+  let p = patch("  55\n  8bec\n  8b45 08\n  5d");
+  // A `call` written over the second and third instruction.
+  let e = p
+    .check(&image(&[0x55, 0xe8, 0xf8, 0x0f, 0x00, 0x00, 0x5d]), 0)
+    .unwrap_err();
+  let hook = ForeignHook {
+    kind: ForeignHookKind::Call,
+    pos: 1,
+    target: 0x2000,
+  };
+  assert_eq!(e.overwritten_hook(), Some(hook));
+
+  // A `call` at the start with the rest of the last instruction replaced by a
+  // `nop`.
+  let e = p
+    .check(&image(&[0xe8, 0xf9, 0x0f, 0x00, 0x00, 0x90, 0x5d]), 0)
+    .unwrap_err();
+  assert_eq!(e.overwritten_hook().map(|h| h.pos), Some(0));
+
+  // Any other difference means the code isn't what's expected.
+  for bytes in [
+    [0xe8, 0xf9, 0x0f, 0x00, 0x00, 0x07, 0x5d],
+    [0xe8, 0xf9, 0x0f, 0x00, 0x00, 0x90, 0xc3],
+    [0x55, 0xe8, 0xf8, 0x0f, 0x00, 0x00, 0x90],
+  ] {
+    let e = p.check(&image(&bytes), 0).unwrap_err();
+    assert!(e.foreign_hook().is_some());
+    assert_eq!(e.overwritten_hook(), None, "{bytes:02x?}");
+  }
+}
+
+#[test]
+fn retargeted_hook() {
+  let p = patch("  50\n  e8 10000000\n  83c4 04");
+  let bytes = [0x50, 0xe8, 0xf8, 0x0f, 0x00, 0x00, 0x83, 0xc4, 0x04];
+  let e = p.check(&image(&bytes), 0).unwrap_err();
+  let hook = ForeignHook {
+    kind: ForeignHookKind::Call,
+    pos: 1,
+    target: 0x2000,
+  };
+  assert_eq!(e.retargeted_hook(), Some(hook));
+  assert_eq!(e.foreign_hook(), Some(hook));
+
+  // Any other difference means the code isn't what's expected.
+  let mut bytes = bytes;
+  bytes[8] = 0x08;
+  let e = p.check(&image(&bytes), 0).unwrap_err();
+  assert_eq!(e.retargeted_hook(), None);
+}
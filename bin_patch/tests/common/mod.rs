@@ -0,0 +1,205 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code, unused_imports)]
+
+/// The address synthetic PE files prefer to be loaded at.
+pub const IMAGE_BASE: u32 = 0x0040_0000;
+/// The address of the code section in synthetic PE files.
+pub const CODE_RVA: u32 = 0x1000;
+/// The address of the data section in synthetic PE files with at most a page
+/// of code.
+pub const DATA_RVA: u32 = 0x2000;
+const HEADERS_SIZE: u32 = 0x400;
+const FILE_ALIGN: u32 = 0x200;
+const SECTION_ALIGN: u32 = 0x1000;
+
+fn align_up(x: u32, align: u32) -> u32 {
+  x.div_ceil(align) * align
+}
+
+fn write_u16(file: &mut [u8], offset: usize, x: u16) {
+  file[offset..offset + 2].copy_from_slice(&x.to_le_bytes());
+}
+
+fn write_u32(file: &mut [u8], offset: usize, x: u32) {
+  file[offset..offset + 4].copy_from_slice(&x.to_le_bytes());
+}
+
+/// Builds a PE32 file with a code section, a data section and an uninitialized
+/// data section of `0x100` bytes. The code section is loaded at `CODE_RVA` and
+/// each following section starts on the next page. Each address in `relocs` is
+/// listed in a base relocation section.
+pub fn pe_file(code: &[u8], data: &[u8], relocs: &[u32]) -> Vec<u8> {
+  let mut reloc_data = Vec::new();
+  for &rva in relocs {
+    reloc_data.extend_from_slice(&(rva & !0xfff).to_le_bytes());
+    reloc_data.extend_from_slice(&10u32.to_le_bytes());
+    reloc_data.extend_from_slice(&(0x3000 | (rva & 0xfff) as u16).to_le_bytes());
+  }
+
+  // Name, contents, loaded size and characteristics.
+  let sections: [(&[u8; 8], &[u8], u32, u32); 4] = [
+    (b".text\0\0\0", code, code.len() as u32, 0x6000_0020),
+    (b".data\0\0\0", data, data.len() as u32, 0xc000_0040),
+    (b".bss\0\0\0\0", &[], 0x100, 0xc000_0080),
+    (
+      b".reloc\0\0",
+      &reloc_data,
+      reloc_data.len() as u32,
+      0x4200_0040,
+    ),
+  ];
+
+  let mut file = vec![0u8; HEADERS_SIZE as usize];
+  file[..2].copy_from_slice(b"MZ");
+  write_u32(&mut file, 0x3c, 0x80);
+  file[0x80..0x84].copy_from_slice(b"PE\0\0");
+  write_u16(&mut file, 0x84, 0x14c);
+  write_u16(&mut file, 0x86, sections.len() as u16);
+  write_u16(&mut file, 0x94, 0xe0);
+  write_u16(&mut file, 0x96, 0x2102);
+  let opt = 0x98;
+  write_u16(&mut file, opt, 0x10b);
+  write_u32(&mut file, opt + 4, align_up(code.len() as u32, FILE_ALIGN));
+  write_u32(&mut file, opt + 20, CODE_RVA);
+  write_u32(&mut file, opt + 28, IMAGE_BASE);
+  write_u32(&mut file, opt + 32, SECTION_ALIGN);
+  write_u32(&mut file, opt + 36, FILE_ALIGN);
+  write_u32(&mut file, opt + 60, HEADERS_SIZE);
+  write_u32(&mut file, opt + 92, 16);
+
+  let mut rva = CODE_RVA;
+  let mut header = opt + 0xe0;
+  for (name, contents, size, characteristics) in sections {
+    let raw_offset = if contents.is_empty() {
+      0
+    } else {
+      file.len() as u32
+    };
+    let raw_size = align_up(contents.len() as u32, FILE_ALIGN);
+    file.extend_from_slice(contents);
+    file.resize((raw_offset + raw_size).max(file.len() as u32) as usize, 0);
+    file[header..header + 8].copy_from_slice(name);
+    write_u32(&mut file, header + 8, size);
+    write_u32(&mut file, header + 12, rva);
+    write_u32(&mut file, header + 16, raw_size);
+    write_u32(&mut file, header + 20, raw_offset);
+    write_u32(&mut file, header + 36, characteristics);
+    if name == b".reloc\0\0" && size != 0 {
+      write_u32(&mut file, opt + 96 + 5 * 8, rva);
+      write_u32(&mut file, opt + 96 + 5 * 8 + 4, size);
+    }
+    header += 40;
+    rva += align_up(size.max(1), SECTION_ALIGN);
+  }
+  write_u32(&mut file, opt + 56, rva);
+  file
+}
+
+/// Sets the location of a data directory in a file built by `pe_file`.
+pub fn set_data_dir(file: &mut [u8], idx: usize, rva: u32, size: u32) {
+  let entry = 0x98 + 96 + idx * 8;
+  write_u32(file, entry, rva);
+  write_u32(file, entry + 4, size);
+}
+
+#[cfg(unix)]
+pub use self::unix::{page_size, Module};
+#[cfg(unix)]
+mod unix {
+  use bin_patch::LoadedModule;
+  use core::{ffi::c_void, ptr, slice};
+  use libc::{
+    mmap, mprotect, munmap, sysconf, _SC_PAGESIZE, MAP_ANONYMOUS, MAP_FAILED, MAP_PRIVATE,
+    PROT_EXEC, PROT_READ, PROT_WRITE,
+  };
+  use std::fs;
+
+  pub fn page_size() -> usize {
+    unsafe { sysconf(_SC_PAGESIZE) as usize }
+  }
+
+  /// Executable memory standing in for a loaded module.
+  pub struct Module {
+    pub ptr: *mut c_void,
+    len: usize,
+  }
+  impl Module {
+    pub fn new(bytes: &[u8]) -> Self {
+      let len = bytes.len();
+      unsafe {
+        let ptr = mmap(
+          ptr::null_mut(),
+          len,
+          PROT_READ | PROT_WRITE,
+          MAP_PRIVATE | MAP_ANONYMOUS,
+          -1,
+          0,
+        );
+        assert_ne!(ptr, MAP_FAILED);
+        ptr::copy_nonoverlapping(bytes.as_ptr(), ptr.cast(), len);
+        assert_eq!(mprotect(ptr, len, PROT_READ | PROT_EXEC), 0);
+        Self { ptr, len }
+      }
+    }
+
+    pub fn get(&self) -> LoadedModule {
+      unsafe { LoadedModule::new(self.ptr as isize) }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+      unsafe { slice::from_raw_parts(self.ptr.cast(), self.len) }
+    }
+
+    /// Overwrites part of the module, e.g. to simulate another mod's patch.
+    pub fn write(&self, offset: usize, bytes: &[u8]) {
+      unsafe {
+        assert_eq!(mprotect(self.ptr, self.len, PROT_READ | PROT_WRITE), 0);
+        ptr::copy_nonoverlapping(
+          bytes.as_ptr(),
+          self.ptr.cast::<u8>().add(offset),
+          bytes.len(),
+        );
+        assert_eq!(mprotect(self.ptr, self.len, PROT_READ | PROT_EXEC), 0);
+      }
+    }
+
+    /// Changes the protection of the pages containing the range.
+    pub fn protect(&self, offset: usize, len: usize, prot: i32) {
+      unsafe {
+        assert_eq!(
+          mprotect(self.ptr.cast::<u8>().add(offset).cast(), len, prot),
+          0
+        );
+      }
+    }
+
+    /// Reads the protection of the page containing the offset from
+    /// `/proc/self/maps`.
+    pub fn protection(&self, offset: usize) -> i32 {
+      let address = self.ptr as usize + offset;
+      let maps = fs::read_to_string("/proc/self/maps").unwrap();
+      let perms = maps
+        .lines()
+        .find_map(|line| {
+          let mut parts = line.split_whitespace();
+          let (start, end) = parts.next()?.split_once('-')?;
+          let start = usize::from_str_radix(start, 16).ok()?;
+          let end = usize::from_str_radix(end, 16).ok()?;
+          (start <= address && address < end).then(|| parts.next().unwrap().as_bytes())
+        })
+        .unwrap();
+      [(b'r', PROT_READ), (b'w', PROT_WRITE), (b'x', PROT_EXEC)]
+        .into_iter()
+        .zip(perms)
+        .filter(|&((c, _), &x)| c == x)
+        .fold(0, |prot, ((_, flag), _)| prot | flag)
+    }
+  }
+  impl Drop for Module {
+    fn drop(&mut self) {
+      unsafe {
+        munmap(self.ptr, self.len);
+      }
+    }
+  }
+}
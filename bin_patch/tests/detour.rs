@@ -0,0 +1,131 @@
+#![cfg(unix)]
+
+use bin_patch::{patch_source, Patch, Registers};
+use core::slice;
+
+mod common;
+use common::Module;
+
+unsafe extern "C" fn hook(_: &mut Registers) {}
+unsafe extern "C" fn jcc_target() {}
+
+/// Encodes a `rel32` branch at the given address.
+fn branch(op: &[u8], address: usize, target: usize) -> Vec<u8> {
+  let next = address + op.len() + 4;
+  let mut bytes = op.to_vec();
+  bytes.extend_from_slice(&(target.wrapping_sub(next) as u32).to_le_bytes());
+  bytes
+}
+
+/// Gets the target of the `jmp` at the given offset.
+fn jmp_target(module: &Module, offset: usize) -> usize {
+  let bytes = module.bytes();
+  assert_eq!(bytes[offset], 0xe9);
+  let rel = i32::from_le_bytes(bytes[offset + 1..offset + 5].try_into().unwrap());
+  (module.ptr as usize + offset + 5).wrapping_add_signed(rel as isize)
+}
+
+/// Reads generated code.
+fn code(address: usize, len: usize) -> &'static [u8] {
+  unsafe { slice::from_raw_parts(address as *const u8, len) }
+}
+
+fn load(offset: usize, code: &[u8]) -> Module {
+  let mut bytes = vec![0xcc; 0x100];
+  bytes[offset..offset + code.len()].copy_from_slice(code);
+  Module::new(&bytes)
+}
+
+#[test]
+fn detour() {
+  // test eax, eax; je +0x10; call 0x80
+  let original = [0x85, 0xc0, 0x74, 0x10, 0xe8, 0x67, 0x00, 0x00, 0x00];
+  let module = load(0x10, &original);
+  let base = module.ptr as usize;
+  const PATCH: Patch = Patch::detour(0x10, patch_source!("85c0 74 xx e8 xxxxxxxx"), hook);
+  let applied = unsafe { PATCH.apply(module.get(), 0) }.unwrap();
+  assert_eq!(module.bytes()[0x15..0x19], [0x0f, 0x1f, 0x40, 0x00]);
+
+  let trampoline = jmp_target(&module, 0x10);
+  let mut expected = vec![0x60, 0x9c, 0x54];
+  expected.extend(branch(&[0xe8], trampoline + 3, hook as *const () as usize));
+  expected.extend([0x83, 0xc4, 0x04, 0x9d, 0x61, 0x85, 0xc0]);
+  // The short `je` is widened.
+  expected.extend(branch(&[0x0f, 0x84], trampoline + 15, base + 0x24));
+  expected.extend(branch(&[0xe8], trampoline + 21, base + 0x80));
+  expected.extend(branch(&[0xe9], trampoline + 26, base + 0x19));
+  assert_eq!(code(trampoline, expected.len()), expected);
+
+  drop(applied);
+  assert_eq!(module.bytes()[0x10..0x19], original);
+}
+
+#[test]
+fn detour_after() {
+  // jne +0x10; jmp +0x20
+  let original = [0x0f, 0x85, 0x10, 0x00, 0x00, 0x00, 0xeb, 0x20];
+  let module = load(0x10, &original);
+  let base = module.ptr as usize;
+  const PATCH: Patch = Patch::detour_after(0x10, patch_source!("0f85 xxxxxxxx eb xx"), hook);
+  let applied = unsafe { PATCH.apply(module.get(), 0) }.unwrap();
+  assert_eq!(module.bytes()[0x15..0x18], [0x0f, 0x1f, 0x00]);
+
+  let trampoline = jmp_target(&module, 0x10);
+  let mut expected = branch(&[0x0f, 0x85], trampoline, base + 0x26);
+  expected.extend(branch(&[0xe9], trampoline + 6, base + 0x38));
+  expected.extend([0x60, 0x9c, 0x54]);
+  expected.extend(branch(&[0xe8], trampoline + 14, hook as *const () as usize));
+  expected.extend([0x83, 0xc4, 0x04, 0x9d, 0x61]);
+  expected.extend(branch(&[0xe9], trampoline + 24, base + 0x18));
+  assert_eq!(code(trampoline, expected.len()), expected);
+
+  drop(applied);
+  assert_eq!(module.bytes()[0x10..0x18], original);
+}
+
+#[test]
+fn unmovable() {
+  // A branch into the moved code.
+  const INTO_SELF: Patch = Patch::detour(0x10, patch_source!("74 xx 90 90 90"), hook);
+  let module = load(0x10, &[0x74, 0x01, 0x90, 0x90, 0x90]);
+  assert!(unsafe { INTO_SELF.apply(module.get(), 0) }.is_none());
+  assert_eq!(module.bytes()[0x10..0x15], [0x74, 0x01, 0x90, 0x90, 0x90]);
+
+  // `loop` has no `rel32` form.
+  const LOOP: Patch = Patch::detour(0x10, patch_source!("e2 xx 90 90 90"), hook);
+  let module = load(0x10, &[0xe2, 0x10, 0x90, 0x90, 0x90]);
+  assert!(unsafe { LOOP.apply(module.get(), 0) }.is_none());
+
+  const SHORT: Patch = Patch::detour(0x10, patch_source!("90 90 90 90"), hook);
+  let module = load(0x10, &[0x90; 4]);
+  assert!(unsafe { SHORT.apply(module.get(), 0) }.is_none());
+  assert_eq!(module.bytes()[0x10..0x14], [0x90; 4]);
+}
+
+#[test]
+fn retarget_jcc() {
+  // The near form is rewritten in place, keeping the following code.
+  const NEAR: Patch = Patch::retarget_jcc(0x10, patch_source!("0f84 xxxxxxxx 85c0"), jcc_target);
+  let module = load(0x10, &[0x0f, 0x84, 0x10, 0x00, 0x00, 0x00, 0x85, 0xc0]);
+  let base = module.ptr as usize;
+  let applied = unsafe { NEAR.apply(module.get(), 0) }.unwrap();
+  assert_eq!(
+    module.bytes()[0x10..0x16],
+    branch(&[0x0f, 0x84], base + 0x10, jcc_target as *const () as usize)
+  );
+  assert_eq!(module.bytes()[0x16..0x18], [0x85, 0xc0]);
+  drop(applied);
+
+  // The short form is moved into a stub along with the following code.
+  const SHORT: Patch = Patch::retarget_jcc(0x10, patch_source!("74 xx 85c0 90"), jcc_target);
+  let module = load(0x10, &[0x74, 0x10, 0x85, 0xc0, 0x90]);
+  let base = module.ptr as usize;
+  let applied = unsafe { SHORT.apply(module.get(), 0) }.unwrap();
+  let stub = jmp_target(&module, 0x10);
+  let mut expected = branch(&[0x0f, 0x84], stub, jcc_target as *const () as usize);
+  expected.extend([0x85, 0xc0, 0x90]);
+  expected.extend(branch(&[0xe9], stub + 9, base + 0x15));
+  assert_eq!(code(stub, expected.len()), expected);
+  drop(applied);
+  assert_eq!(module.bytes()[0x10..0x15], [0x74, 0x10, 0x85, 0xc0, 0x90]);
+}
@@ -0,0 +1,158 @@
+use bin_patch::{
+  crc32, patch_source, write_bps, write_ips, ExportError, ModuleImage, Patch, PatchedFile,
+};
+
+mod common;
+use common::{pe_file, set_data_dir, CODE_RVA, DATA_RVA};
+
+#[test]
+fn crc() {
+  assert_eq!(crc32(b""), 0);
+  assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+  assert_eq!(
+    crc32(b"The quick brown fox jumps over the lazy dog"),
+    0x414f_a339
+  );
+}
+
+#[test]
+fn ips() {
+  let source = [0u8; 16];
+  let mut target = source;
+  target[2] = 1;
+  target[4] = 2;
+  target[12] = 3;
+  assert_eq!(
+    write_ips(&source, &target).unwrap(),
+    b"PATCH\0\0\x02\0\x03\x01\0\x02\0\0\x0c\0\x01\x03EOF"
+  );
+
+  // Bytes past the end of the source are always written.
+  assert_eq!(
+    write_ips(&source[..14], &target).unwrap(),
+    b"PATCH\0\0\x02\0\x03\x01\0\x02\0\0\x0c\0\x04\x03\0\0\0EOF"
+  );
+  assert_eq!(write_ips(&target, &source[..15]), None);
+  assert_eq!(write_ips(&source, &source).unwrap(), b"PATCHEOF");
+
+  // A record can't start at the offset which spells `EOF`.
+  let source = vec![0u8; 0x45_4f48];
+  let mut target = source.clone();
+  target[0x45_4f46] = 1;
+  assert_eq!(
+    write_ips(&source, &target).unwrap(),
+    b"PATCH\x45\x4f\x45\0\x02\0\x01EOF"
+  );
+}
+
+#[test]
+fn bps() {
+  let patch = write_bps(b"abcd", b"abXde", "m");
+  let mut expected = b"BPS1\x84\x85\x81m".to_vec();
+  // Two bytes from the source, then two from the patch and one from the source.
+  expected.extend_from_slice(&[0x84, 0x81, b'X', 0x80, 0x81, b'e']);
+  expected.extend_from_slice(&crc32(b"abcd").to_le_bytes());
+  expected.extend_from_slice(&crc32(b"abXde").to_le_bytes());
+  expected.extend_from_slice(&crc32(&expected).to_le_bytes());
+  assert_eq!(patch, expected);
+
+  // Numbers use seven bits per byte with the last byte marked.
+  let patch = write_bps(&[], &[], &"m".repeat(0x80));
+  assert_eq!(patch[..8], *b"BPS1\x80\x80\x00\x80");
+}
+
+unsafe extern "C" fn hook() {}
+
+/// Builds the data section of a module which imports `GetProcAddress` and
+/// `LoadLibraryA` from `KERNEL32.dll`. Their address table entries are at
+/// `DATA_RVA + 0x60` and `DATA_RVA + 0x68`.
+fn imports() -> Vec<u8> {
+  let mut data = vec![0u8; 0xc0];
+  let mut write = |offset: usize, bytes: &[u8]| {
+    data[offset..offset + bytes.len()].copy_from_slice(bytes);
+  };
+  // The descriptor.
+  write(0x00, &(DATA_RVA + 0x40).to_le_bytes());
+  write(0x0c, &(DATA_RVA + 0x80).to_le_bytes());
+  write(0x10, &(DATA_RVA + 0x60).to_le_bytes());
+  // The lookup and address tables. The second entry is imported by ordinal.
+  for table in [0x40, 0x60] {
+    write(table, &(DATA_RVA + 0xa0).to_le_bytes());
+    write(table + 4, &0x8000_0010u32.to_le_bytes());
+    write(table + 8, &(DATA_RVA + 0xb0).to_le_bytes());
+  }
+  write(0x80, b"KERNEL32.dll\0");
+  write(0xa2, b"GetProcAddress\0");
+  write(0xb2, b"LoadLibraryA\0");
+  data
+}
+
+/// Reads a `rel32` branch target at the given position in the image.
+fn branch_target(image: &[u8], pos: usize) -> usize {
+  let rel = i32::from_le_bytes(image[pos + 1..pos + 5].try_into().unwrap());
+  (pos + 5).wrapping_add_signed(rel as isize)
+}
+
+#[test]
+fn lazy_loader() {
+  const PATCHES: [Patch; 2] = [
+    Patch::call_c(CODE_RVA as usize, patch_source!("90 90 90 90 90"), hook),
+    Patch::call_c(CODE_RVA as usize + 5, patch_source!("90 90 90 90 90"), hook),
+  ];
+  let code = [
+    0x90, 0x90, 0x90, 0x90, 0x90, 0x90, 0x90, 0x90, 0x90, 0x90, 0xc3,
+  ];
+  let target = hook as *const () as usize;
+
+  let mut patched = PatchedFile::new(pe_file(&code, &[], &[])).unwrap();
+  assert!(matches!(
+    patched.apply(&PATCHES[0]),
+    Err(ExportError::Unresolved(x)) if x == target
+  ));
+  assert!(matches!(
+    patched.add_lazy_loader("hooks.dll", "resolve", 0),
+    Err(ExportError::MissingImport("LoadLibraryA"))
+  ));
+
+  let mut file = pe_file(&code, &imports(), &[]);
+  set_data_dir(&mut file, 1, DATA_RVA, 0x28);
+  let mut patched = PatchedFile::new(file).unwrap();
+  patched
+    .add_lazy_loader("hooks.dll", "resolve", target.wrapping_sub(0x1234))
+    .unwrap();
+  for p in &PATCHES {
+    patched.apply(p).unwrap();
+  }
+  let image = ModuleImage::from_pe_file(&patched.finish().unwrap()).unwrap();
+  let image = image.bytes();
+
+  // Both calls use the same stub.
+  let code = CODE_RVA as usize;
+  assert_eq!(image[code], 0xe8);
+  let stub = branch_target(image, code);
+  assert_eq!(branch_target(image, code + 5), stub);
+  assert_eq!(stub % 16, 0);
+  assert_eq!(
+    image[stub..stub + 14],
+    [0x90, 0x90, 0x90, 0xe9, 0, 0, 0, 0, 0x68, 0x34, 0x12, 0, 0, 0xe8]
+  );
+
+  let resolver = branch_target(image, stub + 13);
+  assert_eq!(
+    image[resolver..resolver + 10],
+    [0x60, 0x9c, 0xe8, 0, 0, 0, 0, 0x5b, 0x8d, 0x83]
+  );
+  // Everything is addressed relative to the `pop ebx`.
+  let relative = |pos: usize| {
+    let rel = i32::from_le_bytes(image[pos..pos + 4].try_into().unwrap());
+    (resolver + 7).wrapping_add_signed(rel as isize)
+  };
+  assert_eq!(relative(resolver + 10), resolver + 69);
+  assert_eq!(relative(resolver + 17), DATA_RVA as usize + 0x68);
+  assert_eq!(relative(resolver + 27), resolver + 79);
+  assert_eq!(relative(resolver + 35), DATA_RVA as usize + 0x60);
+  assert_eq!(
+    image[resolver + 69..resolver + 87],
+    *b"hooks.dll\0resolve\0"
+  );
+}
@@ -0,0 +1,134 @@
+use bin_patch::{parse_patches, Hook, ParseError, Registers};
+
+unsafe extern "C" fn hook_fn() {}
+unsafe extern "C" fn detour_fn(_: &mut Registers) {}
+unsafe extern "C" fn reg_fn(_: u32) {}
+
+fn hooks(name: &str) -> Option<Hook> {
+  match name {
+    "hook" => Some(Hook::c(hook_fn)),
+    "stub" => Some(Hook::stub(hook_fn)),
+    "detour" => Some(Hook::detour(detour_fn)),
+    "reg" => Some(Hook::c1(reg_fn)),
+    _ => None,
+  }
+}
+
+fn parse_error(text: &str) -> ParseError {
+  match parse_patches(text, hooks) {
+    Ok(_) => panic!("parsed `{text}`"),
+    Err(e) => e,
+  }
+}
+
+/// Parses the patches and gets the error's line, column and message.
+fn error(text: &str) -> (usize, Option<usize>, String) {
+  let ParseError { line, column, msg } = parse_error(text);
+  (line, column, msg)
+}
+
+#[test]
+fn parse() {
+  let text = "\
+; A comment before the first patch.
+
+0x1a2b call_c hook ; Trailing comment.
+  8b0d $20de8b6f
+  ; A comment within the source.
+
+  ff15 $c0a18b6f
+16 call_c_at hook 2
+  55 e8 xxxxxxxx
+0x40 jmp_c stub
+  eb 10 90 90 90
+0x50 retarget_jcc stub
+  74 10
+0x60 detour detour
+  55 8bec 83ec08
+0x70 detour_after detour
+  55 8bec 83ec08
+0x80 nop
+  data 74 05
+0x90 raw b8 $00100000
+  b8 01000000
+0xa0 call_c1 reg esi
+  e8 xxxxxxxx
+";
+  let patches = parse_patches(text, hooks).unwrap();
+  let ranges: Vec<_> = patches.iter().map(|p| p.range()).collect();
+  assert_eq!(
+    ranges,
+    [
+      0x1a2b..0x1a37,
+      16..22,
+      0x40..0x45,
+      0x50..0x52,
+      0x60..0x66,
+      0x70..0x76,
+      0x80..0x82,
+      0x90..0x95,
+      0xa0..0xa5,
+    ]
+  );
+  assert_eq!(parse_patches("", hooks).unwrap().len(), 0);
+}
+
+#[test]
+fn errors() {
+  #[rustfmt::skip]
+  let cases: &[(&str, usize, Option<usize>, &str)] = &[
+    ("  90", 1, None, "patch source without a header"),
+    ("\n0x10\n  90", 2, None, "expected a patch offset and kind"),
+    ("0x1g nop\n  90", 1, None, "invalid offset `0x1g`"),
+    ("0 nop", 1, None, "missing patch source"),
+    ("0 nop\n\n0 nop\n  90", 1, None, "missing patch source"),
+    ("0 jump hook\n  90", 1, None, "unknown patch kind `jump`"),
+    ("0 call_c\n  90", 1, None, "`call_c` requires a hook"),
+    ("0 call_c missing\n  90", 1, None, "unknown hook `missing`"),
+    ("0 call_c stub\n  90", 1, None, "hook `stub` can't be used with `call_c`"),
+    ("0 call_c_at hook\n  90", 1, None, "`call_c_at` requires a call offset"),
+    ("0 nop 90\n  90", 1, None, "too many arguments for `nop`"),
+    ("0 raw 9\n  90", 1, None, "incomplete byte value"),
+    ("0 call_c1 hook esi\n  90", 1, None, "hook `hook` can't be used with `call_c1`"),
+    ("0 call_c1 reg\n  90", 1, None, "`call_c1` requires 1 argument registers"),
+    ("0 call_c1 reg esp\n  90", 1, None, "unknown register `esp`"),
+    ("0 call_c1 reg esi eax\n  90", 1, None, "too many arguments for `call_c1`"),
+  ];
+  for &(text, line, column, msg) in cases {
+    let e = error(text);
+    assert_eq!((e.0, e.1), (line, column), "`{text}`: {}", e.2);
+    assert!(e.2.contains(msg), "`{text}`: {}", e.2);
+  }
+}
+
+#[test]
+fn source_error_position() {
+  // Errors within the source are reported at their position in the text.
+  let (line, column, _) = error("0 nop\n  90\n  ; Comment\n\n    9z");
+  assert_eq!((line, column), (5, Some(5)));
+  let (line, column, _) = error("0 nop\n  90\n1 nop\n  90 zz");
+  assert_eq!((line, column), (4, Some(6)));
+  // Comments and labels are part of the source.
+  let (line, column, msg) = error("0 nop\n  a: 90 ; b: 90\n  a: 90");
+  assert_eq!((line, column), (3, Some(3)));
+  assert!(msg.contains("duplicate label `a`"), "{msg}");
+  // The `data` prefix doesn't shift the column.
+  let (line, column, _) = error("0 nop\n  data 9");
+  assert_eq!((line, column), (2, Some(8)));
+  // Code must contain whole instructions.
+  let (line, column, _) = error("0 nop\n  b8 0100");
+  assert_eq!((line, column.is_some()), (2, true));
+}
+
+#[test]
+fn display() {
+  assert_eq!(
+    parse_error("0 jump\n  90").to_string(),
+    "line 1: unknown patch kind `jump`"
+  );
+  let e = parse_error("0 nop\n  9z");
+  assert_eq!(
+    e.to_string(),
+    format!("line 2, column {}: {}", e.column.unwrap(), e.msg)
+  );
+}
@@ -0,0 +1,73 @@
+use bin_patch::{patch_source, Memory, MemoryMut, ModuleImage, Patch};
+
+mod common;
+use common::{pe_file, CODE_RVA, IMAGE_BASE};
+
+const CODE: [u8; 9] = [0x55, 0x8b, 0xec, 0x8b, 0x45, 0x08, 0x5d, 0xc3, 0xcc];
+
+#[test]
+fn map_file() {
+  let data = [1, 2, 3, 4];
+  let file = pe_file(&CODE, &data, &[]);
+  let image = ModuleImage::from_pe_file(&file).unwrap();
+  assert_eq!(image.base(), IMAGE_BASE as usize);
+
+  let bytes = image.bytes();
+  assert_eq!(bytes.len(), 0x5000);
+  assert_eq!(bytes[..0x400], file[..0x400]);
+  assert!(bytes[0x400..0x1000].iter().all(|&x| x == 0));
+  assert_eq!(bytes[0x1000..0x1009], CODE);
+  // The rest of the section's file alignment padding is loaded as zeros.
+  assert!(bytes[0x1009..0x2000].iter().all(|&x| x == 0));
+  assert_eq!(bytes[0x2000..0x2004], data);
+  // Uninitialized data has no contents in the file.
+  assert!(bytes[0x3000..0x5000].iter().all(|&x| x == 0));
+}
+
+#[test]
+fn map_invalid_file() {
+  let file = pe_file(&CODE, &[], &[]);
+  assert!(ModuleImage::from_pe_file(&file).is_some());
+
+  let mut bad = file.clone();
+  bad[0] = b'X';
+  assert!(ModuleImage::from_pe_file(&bad).is_none());
+  let mut bad = file.clone();
+  bad[0x80] = b'X';
+  assert!(ModuleImage::from_pe_file(&bad).is_none());
+  // PE32+ images aren't supported.
+  let mut bad = file.clone();
+  bad[0x98] = 0x0b;
+  bad[0x99] = 0x02;
+  assert!(ModuleImage::from_pe_file(&bad).is_none());
+  // The section table is cut off.
+  assert!(ModuleImage::from_pe_file(&file[..0x100]).is_none());
+  assert!(ModuleImage::from_pe_file(&[]).is_none());
+}
+
+#[test]
+fn module_image() {
+  let mut image = ModuleImage::new(0x1000, vec![0u8; 0x10]);
+  assert_eq!(image.base(), 0x1000);
+  assert_eq!(image.write(4, 2, |x| x.copy_from_slice(&[1, 2])), Some(()));
+  assert_eq!(image.read(3, 4, |x| x.to_vec()), Some(vec![0, 1, 2, 0]));
+  assert_eq!(image.read(0x10, 0, |x| x.len()), Some(0));
+  assert_eq!(image.read(0xf, 2, |x| x.len()), None);
+  assert_eq!(image.write(usize::MAX, 2, |x| x.len()), None);
+  assert_eq!(image.into_bytes()[4..6], [1, 2]);
+}
+
+#[test]
+fn check_mapped_file() {
+  const PATCH: Patch = Patch::nop(CODE_RVA as usize + 3, patch_source!("8b45 08"));
+  const OTHER: Patch = Patch::nop(CODE_RVA as usize + 3, patch_source!("8b45 0c"));
+  let mut image = ModuleImage::from_pe_file(&pe_file(&CODE, &[], &[])).unwrap();
+  assert!(PATCH.check(&image, 0).is_ok());
+  assert!(OTHER.check(&image, 0).is_err());
+  assert_eq!(PATCH.locate(&image, 0), Ok(CODE_RVA as usize + 3));
+
+  let applied = unsafe { PATCH.apply(&mut image, 0) }.unwrap();
+  assert_eq!(applied.original(), [0x8b, 0x45, 0x08]);
+  assert!(applied.restore().is_ok());
+  assert!(PATCH.check(&image, 0).is_ok());
+}
@@ -0,0 +1,128 @@
+use bin_patch::{patch_source, Memory, MemoryMut, Patch, PatchSet};
+use core::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// A module image shared with the test which can be made read-only.
+#[derive(Clone)]
+struct Image {
+  base: usize,
+  bytes: Rc<RefCell<Vec<u8>>>,
+  writable: Rc<Cell<bool>>,
+}
+impl Image {
+  fn new(base: usize, bytes: &[u8]) -> Self {
+    Self {
+      base,
+      bytes: Rc::new(RefCell::new(bytes.into())),
+      writable: Rc::new(Cell::new(true)),
+    }
+  }
+
+  fn bytes(&self) -> Vec<u8> {
+    self.bytes.borrow().clone()
+  }
+}
+impl Memory for Image {
+  fn base(&self) -> usize {
+    self.base
+  }
+
+  fn read<R>(&self, offset: usize, len: usize, f: impl FnOnce(&[u8]) -> R) -> Option<R> {
+    Some(f(self.bytes.borrow().get(offset..offset + len)?))
+  }
+}
+impl MemoryMut for Image {
+  fn write<R>(&mut self, offset: usize, len: usize, f: impl FnOnce(&mut [u8]) -> R) -> Option<R> {
+    if !self.writable.get() {
+      return None;
+    }
+    Some(f(self.bytes.borrow_mut().get_mut(offset..offset + len)?))
+  }
+}
+
+const PATCH: Patch = Patch::nop(2, patch_source!("cc cc cc"));
+const PATCHED: [u8; 8] = [0xcc, 0xcc, 0x0f, 0x1f, 0x00, 0xcc, 0xcc, 0xcc];
+
+#[test]
+fn restore() {
+  let image = Image::new(0x1000, &[0xcc; 8]);
+  let applied = unsafe { PATCH.apply(image.clone(), 0) }.unwrap();
+  assert_eq!(applied.offset(), 2);
+  assert_eq!(applied.original(), [0xcc; 3]);
+  assert_eq!(image.bytes(), PATCHED);
+  assert!(applied.restore().is_ok());
+  assert_eq!(image.bytes(), [0xcc; 8]);
+}
+
+#[test]
+fn restore_failed() {
+  let image = Image::new(0x1000, &[0xcc; 8]);
+  let applied = unsafe { PATCH.apply(image.clone(), 0) }.unwrap();
+  image.writable.set(false);
+  let applied = applied.restore().err().unwrap();
+  assert_eq!(image.bytes(), PATCHED);
+  image.writable.set(true);
+  assert!(applied.restore().is_ok());
+  assert_eq!(image.bytes(), [0xcc; 8]);
+}
+
+#[test]
+fn drop_restores() {
+  let image = Image::new(0x1000, &[0xcc; 8]);
+  let applied = unsafe { PATCH.apply(image.clone(), 0) }.unwrap();
+  assert_eq!(image.bytes(), PATCHED);
+  drop(applied);
+  assert_eq!(image.bytes(), [0xcc; 8]);
+
+  let applied = unsafe { PATCH.apply(image.clone(), 0) }.unwrap();
+  applied.keep();
+  assert_eq!(image.bytes(), PATCHED);
+}
+
+#[test]
+fn restore_set() {
+  const PATCHES: [Patch; 2] = [
+    Patch::nop(0, patch_source!("cc")),
+    Patch::nop(4, patch_source!("cc cc")),
+  ];
+  let (a, b) = (
+    Image::new(0x1000, &[0xcc; 8]),
+    Image::new(0x2000, &[0xcc; 8]),
+  );
+  let patched = [0x90, 0xcc, 0xcc, 0xcc, 0x66, 0x90, 0xcc, 0xcc];
+  let apply = || {
+    let mut set = PatchSet::new();
+    assert_eq!(set.add(a.clone(), 0, &PATCHES), 0);
+    assert_eq!(set.add(b.clone(), 0, &PATCHES[1..]), 1);
+    unsafe { set.apply() }.unwrap()
+  };
+
+  let applied = apply();
+  assert_eq!(applied.len(), 3);
+  assert!(!applied.is_empty());
+  assert!(applied.chained_hooks().is_empty());
+  assert_eq!(a.bytes(), patched);
+  assert_eq!(b.bytes()[4..6], [0x66, 0x90]);
+  assert!(applied.restore().is_ok());
+  assert_eq!(a.bytes(), [0xcc; 8]);
+  assert_eq!(b.bytes(), [0xcc; 8]);
+
+  drop(apply());
+  assert_eq!(a.bytes(), [0xcc; 8]);
+  assert_eq!(b.bytes(), [0xcc; 8]);
+
+  // Patches are restored in reverse order, so only the second module is
+  // restored before the failure.
+  let applied = apply();
+  a.writable.set(false);
+  let applied = applied.restore().err().unwrap();
+  assert_eq!(applied.len(), 2);
+  assert_eq!(a.bytes(), patched);
+  assert_eq!(b.bytes(), [0xcc; 8]);
+  a.writable.set(true);
+  drop(applied);
+  assert_eq!(a.bytes(), [0xcc; 8]);
+
+  apply().keep();
+  assert_eq!(a.bytes(), patched);
+}
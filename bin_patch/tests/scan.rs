@@ -0,0 +1,67 @@
+use bin_patch::{parse_patches, patch_source, ModuleImage, Patch, ScanError};
+
+mod common;
+use common::{pe_file, CODE_RVA};
+
+const CODE: usize = CODE_RVA as usize;
+
+fn load(code: &[u8], data: &[u8]) -> ModuleImage<Vec<u8>> {
+  ModuleImage::from_pe_file(&pe_file(code, data, &[])).unwrap()
+}
+
+#[test]
+fn locate() {
+  const PATCH: Patch = Patch::nop(0, patch_source!("8b45 xx 5d"));
+  let image = load(&[0xcc, 0x8b, 0x45, 0x08, 0x5d, 0xc3], &[]);
+  assert_eq!(PATCH.scan(&image, 0), Ok(vec![CODE + 1]));
+  assert_eq!(PATCH.locate(&image, 0), Ok(CODE + 1));
+  assert!(PATCH.with_offset(CODE + 1).check(&image, 0).is_ok());
+
+  let image = load(&[0x8b, 0x45, 0x08, 0x5d, 0x8b, 0x45, 0x0c, 0x5d], &[]);
+  assert_eq!(PATCH.scan(&image, 0), Ok(vec![CODE, CODE + 4]));
+  assert_eq!(
+    PATCH.locate(&image, 0),
+    Err(ScanError::Ambiguous(vec![CODE, CODE + 4]))
+  );
+  assert_eq!(
+    ScanError::Ambiguous(vec![CODE, CODE + 4]).to_string(),
+    "multiple matches found at: 0x1000, 0x1004"
+  );
+
+  let image = load(&[0x8b, 0x45, 0x08, 0xc3], &[]);
+  assert_eq!(PATCH.locate(&image, 0), Err(ScanError::NotFound));
+}
+
+#[test]
+fn only_code_sections() {
+  const PATCH: Patch = Patch::nop(0, patch_source!("8b45 08 5d"));
+  let image = load(&[0xc3], &[0x8b, 0x45, 0x08, 0x5d]);
+  assert_eq!(PATCH.scan(&image, 0), Ok(vec![]));
+  let image = ModuleImage::new(0, vec![0x8b, 0x45, 0x08, 0x5d]);
+  assert_eq!(PATCH.scan(&image, 0), Err(ScanError::InvalidModule));
+}
+
+#[test]
+fn relocated() {
+  const PATCH: Patch = Patch::nop(0, patch_source!("a1 $00104000"));
+  let image = load(&[0xcc, 0xa1, 0x00, 0x20, 0x40, 0x00], &[]);
+  assert_eq!(PATCH.scan(&image, 0x1000), Ok(vec![CODE + 1]));
+  assert_eq!(PATCH.scan(&image, 0), Ok(vec![]));
+  #[cfg(target_pointer_width = "64")]
+  assert_eq!(
+    PATCH.scan(&image, 0x1_0000_0000),
+    Err(ScanError::RelocOverflow)
+  );
+}
+
+#[test]
+fn compares_expected_bytes() {
+  // Both have the same hash.
+  let patches = parse_patches("0 nop\n  data 27f7a403", |_| None).unwrap();
+  let image = load(&[0xcc, 0x0b, 0x00, 0x00, 0x04, 0xcc], &[]);
+  assert_eq!(patches[0].scan(&image, 0), Ok(vec![]));
+  assert!(patches[0].with_offset(CODE + 1).check(&image, 0).is_err());
+
+  let image = load(&[0xcc, 0x27, 0xf7, 0xa4, 0x03, 0xcc], &[]);
+  assert_eq!(patches[0].scan(&image, 0), Ok(vec![CODE + 1]));
+}
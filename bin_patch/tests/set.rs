@@ -0,0 +1,145 @@
+use bin_patch::{
+  parse_patches, patch_source, ModuleImage, Patch, PatchError, PatchSet, PatchSetError,
+};
+
+extern "C" fn hook() {}
+
+fn image(base: usize) -> ModuleImage<Vec<u8>> {
+  ModuleImage::new(base, vec![0x90; 0x10])
+}
+
+/// Gets the group, offset and error name of each error.
+fn errors(errors: Vec<PatchSetError>) -> Vec<(usize, usize, String)> {
+  errors
+    .into_iter()
+    .map(|e| {
+      let error = match e.error {
+        PatchError::Mismatch(_) => "mismatch".into(),
+        PatchError::Overlap(offset) => format!("overlap {offset:#x}"),
+        PatchError::TooLarge => "too large".into(),
+        PatchError::PartialInstruction => "partial instruction".into(),
+        PatchError::Write => "write".into(),
+      };
+      (e.group, e.offset, error)
+    })
+    .collect()
+}
+
+#[test]
+fn overlap() {
+  const PATCHES: [Patch; 3] = [
+    Patch::nop(0, patch_source!("90 90 90 90")),
+    Patch::nop(4, patch_source!("90 90")),
+    Patch::nop(2, patch_source!("90 90 90 90 90 90")),
+  ];
+  let (mut a, mut b) = (image(0x1000), image(0x1000));
+  let mut set = PatchSet::new();
+  set.add(&mut a, 0, &PATCHES);
+  let expected = vec![
+    (0, 2, "overlap 0x0".to_owned()),
+    (0, 4, "overlap 0x2".to_owned()),
+  ];
+  assert_eq!(errors(set.check().unwrap_err()), expected);
+  assert_eq!(errors(unsafe { set.apply() }.err().unwrap()), expected);
+
+  // Patches in different modules are compared by address.
+  let mut set = PatchSet::new();
+  set.add(&mut a, 0, &PATCHES[..1]);
+  set.add(&mut b, 0, &PATCHES[1..2]);
+  assert!(set.check().is_ok());
+  let mut set = PatchSet::new();
+  set.add(&mut a, 0, &PATCHES[..1]);
+  set.add(&mut b, 0, &PATCHES[2..]);
+  assert_eq!(
+    errors(unsafe { set.apply_unchecked() }.err().unwrap()),
+    [(1, 2, "overlap 0x0".to_owned())]
+  );
+  assert_eq!(a.bytes(), &[0x90; 0x10]);
+  assert_eq!(b.bytes(), &[0x90; 0x10]);
+}
+
+#[test]
+fn too_large() {
+  const PATCHES: [Patch; 2] = [
+    Patch::call_c(0, patch_source!("90 90 90 90"), hook),
+    Patch::call_c(8, patch_source!("90 90 90 90 90"), hook),
+  ];
+  let mut a = image(0x1000);
+  let mut set = PatchSet::new();
+  set.add(&mut a, 0, &PATCHES);
+  assert_eq!(
+    errors(set.check().unwrap_err()),
+    [(0, 0, "too large".to_owned())]
+  );
+  let mut set = PatchSet::new();
+  set.add(&mut a, 0, &PATCHES);
+  assert_eq!(
+    errors(unsafe { set.apply_unchecked() }.err().unwrap()),
+    [(0, 0, "too large".to_owned())]
+  );
+  assert_eq!(a.bytes(), &[0x90; 0x10]);
+}
+
+#[test]
+fn mismatch() {
+  const PATCHES: [Patch; 3] = [
+    Patch::nop(0, patch_source!("90 90")),
+    Patch::nop(4, patch_source!("cc cc")),
+    Patch::nop(0x20, patch_source!("90 90")),
+  ];
+  let mut a = image(0x1000);
+  let mut set = PatchSet::new();
+  set.add(&mut a, 0, &PATCHES);
+  let expected = vec![
+    (0, 4, "mismatch".to_owned()),
+    (0, 0x20, "mismatch".to_owned()),
+  ];
+  assert_eq!(errors(set.check().unwrap_err()), expected);
+  assert_eq!(errors(unsafe { set.apply() }.err().unwrap()), expected);
+  assert_eq!(a.bytes(), &[0x90; 0x10]);
+}
+
+#[test]
+fn partial_instruction() {
+  const PATCHES: [Patch; 1] = [Patch::nop(0, patch_source!("90 90"))];
+  // mov eax, 0
+  let mut a = ModuleImage::new(0x1000, vec![0xb8, 0x00, 0x00, 0x00, 0x00, 0x90]);
+  let mut set = PatchSet::new();
+  set.add(&mut a, 0, &PATCHES);
+  assert_eq!(
+    errors(unsafe { set.apply_unchecked() }.err().unwrap()),
+    [(0, 0, "partial instruction".to_owned())]
+  );
+  assert_eq!(a.bytes()[0], 0xb8);
+}
+
+#[test]
+fn rollback() {
+  // Data patches skip the instruction check, so nothing is read before the
+  // write to the out of bounds patch fails.
+  let patches = parse_patches(
+    "0 nop\n  data 9090\n8 nop\n  data 9090\n0x20 nop\n  data 9090",
+    |_| None,
+  )
+  .unwrap();
+  let (mut a, mut b, mut c) = (image(0x1000), image(0x2000), image(0x3000));
+  let mut set = PatchSet::new();
+  set.add(&mut a, 0, &patches[..2]);
+  set.add(&mut b, 0, &patches[..1]);
+  set.add(&mut c, 0, &patches[2..]);
+  assert_eq!(
+    errors(unsafe { set.apply_unchecked() }.err().unwrap()),
+    [(2, 0x20, "write".to_owned())]
+  );
+  // Every patch applied before the failure is restored.
+  assert_eq!(a.bytes(), &[0x90; 0x10]);
+  assert_eq!(b.bytes(), &[0x90; 0x10]);
+
+  let mut set = PatchSet::new();
+  set.add(&mut a, 0, &patches[..2]);
+  let applied = unsafe { set.apply_unchecked() }.unwrap();
+  assert_eq!(applied.len(), 2);
+  applied.keep();
+  assert_eq!(a.bytes()[..2], [0x66, 0x90]);
+  assert_eq!(a.bytes()[8..10], [0x66, 0x90]);
+}
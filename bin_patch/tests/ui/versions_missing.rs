@@ -0,0 +1,13 @@
+use bin_patch::patch_versions;
+
+unsafe extern "C" fn hook() {}
+
+patch_versions! {
+  versions: [v101, v102, v103];
+
+  HOOK = call_c(hook) {
+    v101 => 0x10, "ff15 $1cea1210";
+  }
+}
+
+fn main() {}
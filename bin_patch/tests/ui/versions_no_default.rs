@@ -0,0 +1,14 @@
+use bin_patch::patch_versions;
+
+unsafe extern "C" fn hook() {}
+
+patch_versions! {
+  versions: [v101, v102];
+
+  HOOK = call_c(hook) {
+    v101 => 0x10, "ff15 $1cea1210";
+    v102 => 0x20;
+  }
+}
+
+fn main() {}
@@ -0,0 +1,713 @@
+//! A small assembler for 32-bit x86 code used by `asm_patch!`.
+//!
+//! Each line holds a single instruction in Intel syntax, optionally preceded
+//! by labels. `;` starts a comment which runs to the end of the line.
+//!
+//! Operands can be registers, numbers, memory references such as
+//! `dword ptr [esi + eax*4 + 0x10]` and labels. `$` before a hex address marks
+//! it as relocated with the module, and `@` marks a hex value as relative to
+//! the module's base address. Direct branches can target labels or `$`
+//! addresses. Branches to an address always use a `rel32` which is filled in
+//! once the code's final location is known.
+
+use crate::source::SourceError;
+use std::collections::HashMap;
+
+fn error(pos: usize, msg: impl Into<String>) -> SourceError {
+  SourceError { pos, msg: msg.into() }
+}
+
+/// Assembled code along with the positions of its 32-bit values which need to
+/// be adjusted.
+#[derive(Default)]
+pub struct Code {
+  pub bytes: Vec<u8>,
+  pub relocs: Vec<usize>,
+  pub module_relocs: Vec<usize>,
+  /// The position of each `rel32` which currently holds its branch's absolute
+  /// target.
+  pub branch_relocs: Vec<usize>,
+}
+impl Code {
+  fn push(&mut self, x: u8) {
+    self.bytes.push(x);
+  }
+
+  fn extend(&mut self, other: &Code) {
+    let base = self.bytes.len();
+    self.bytes.extend_from_slice(&other.bytes);
+    self.relocs.extend(other.relocs.iter().map(|x| x + base));
+    self
+      .module_relocs
+      .extend(other.module_relocs.iter().map(|x| x + base));
+    self
+      .branch_relocs
+      .extend(other.branch_relocs.iter().map(|x| x + base));
+  }
+
+  /// Writes the `rel32` of a branch to a relocated address.
+  fn branch(&mut self, target: &Imm) -> Result<(), SourceError> {
+    if target.reloc != Reloc::Game {
+      return Err(error(
+        target.pos,
+        "direct branches must target a label or a `$` address",
+      ));
+    }
+    self.branch_relocs.push(self.bytes.len());
+    self.bytes.extend_from_slice(&(target.value as u32).to_le_bytes());
+    Ok(())
+  }
+
+  /// Writes an immediate or displacement of the given size.
+  fn imm(&mut self, imm: &Imm, size: u8) -> Result<(), SourceError> {
+    match imm.reloc {
+      Reloc::None => {}
+      _ if size != 4 => return Err(error(imm.pos, "relocated values must be 32 bits")),
+      Reloc::Game => self.relocs.push(self.bytes.len()),
+      Reloc::Module => self.module_relocs.push(self.bytes.len()),
+    }
+    let bits = u32::from(size) * 8;
+    if imm.value < -(1i64 << (bits - 1)) || imm.value >= 1i64 << bits {
+      return Err(error(imm.pos, format!("value doesn't fit in {bits} bits")));
+    }
+    self
+      .bytes
+      .extend_from_slice(&imm.value.to_le_bytes()[..usize::from(size)]);
+    Ok(())
+  }
+
+  /// Writes the ModRM byte along with any SIB byte and displacement.
+  fn modrm(&mut self, reg: u8, rm: &Operand) -> Result<(), SourceError> {
+    let m = match rm {
+      Operand::Reg(r) => {
+        self.push(0xc0 | reg << 3 | r.num);
+        return Ok(());
+      }
+      Operand::Mem(m) => m,
+      _ => unreachable!(),
+    };
+    let Some(base) = m.base.or(m.index.map(|_| 5)) else {
+      self.push(reg << 3 | 5);
+      return self.imm(&m.disp, 4);
+    };
+    let fixed = m.disp.reloc == Reloc::None;
+    let mode = if m.base.is_none() {
+      // Index only uses a 32-bit displacement with no base.
+      0
+    } else if fixed && m.disp.value == 0 && base != 5 {
+      0
+    } else if fixed && i8::try_from(m.disp.value).is_ok() {
+      1
+    } else {
+      2
+    };
+    if m.index.is_some() || base == 4 {
+      let (index, scale) = m.index.unwrap_or((4, 0));
+      self.push(mode << 6 | reg << 3 | 4);
+      self.push(scale << 6 | index << 3 | base);
+    } else {
+      self.push(mode << 6 | reg << 3 | base);
+    }
+    match mode {
+      1 => self.imm(&m.disp, 1),
+      2 => self.imm(&m.disp, 4),
+      _ if m.base.is_none() => self.imm(&m.disp, 4),
+      _ => Ok(()),
+    }
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Reloc {
+  None,
+  Game,
+  Module,
+}
+
+struct Imm {
+  value: i64,
+  reloc: Reloc,
+  pos: usize,
+}
+
+#[derive(Clone, Copy)]
+struct Reg {
+  num: u8,
+  /// The size in bytes.
+  size: u8,
+}
+
+struct Mem {
+  /// The size in bytes, if given.
+  size: Option<u8>,
+  base: Option<u8>,
+  /// The index register and the scale's exponent.
+  index: Option<(u8, u8)>,
+  disp: Imm,
+}
+
+enum Operand {
+  Reg(Reg),
+  Mem(Mem),
+  Imm(Imm),
+  Label(String),
+}
+
+const REGS: [[&str; 8]; 3] = [
+  ["al", "cl", "dl", "bl", "ah", "ch", "dh", "bh"],
+  ["ax", "cx", "dx", "bx", "sp", "bp", "si", "di"],
+  ["eax", "ecx", "edx", "ebx", "esp", "ebp", "esi", "edi"],
+];
+
+fn parse_reg(s: &str) -> Option<Reg> {
+  let s = s.to_ascii_lowercase();
+  REGS.iter().enumerate().find_map(|(i, names)| {
+    let num = names.iter().position(|&x| x == s)?;
+    Some(Reg { num: num as u8, size: 1 << i })
+  })
+}
+
+/// The condition code of each conditional jump.
+const CONDITIONS: [(&str, u8); 30] = [
+  ("jo", 0x0),
+  ("jno", 0x1),
+  ("jb", 0x2),
+  ("jc", 0x2),
+  ("jnae", 0x2),
+  ("jae", 0x3),
+  ("jnb", 0x3),
+  ("jnc", 0x3),
+  ("je", 0x4),
+  ("jz", 0x4),
+  ("jne", 0x5),
+  ("jnz", 0x5),
+  ("jbe", 0x6),
+  ("jna", 0x6),
+  ("ja", 0x7),
+  ("jnbe", 0x7),
+  ("js", 0x8),
+  ("jns", 0x9),
+  ("jp", 0xa),
+  ("jpe", 0xa),
+  ("jnp", 0xb),
+  ("jpo", 0xb),
+  ("jl", 0xc),
+  ("jnge", 0xc),
+  ("jge", 0xd),
+  ("jnl", 0xd),
+  ("jle", 0xe),
+  ("jng", 0xe),
+  ("jg", 0xf),
+  ("jnle", 0xf),
+];
+
+fn condition(name: &str) -> Option<u8> {
+  CONDITIONS.iter().find(|&&(x, _)| x == name).map(|&(_, cc)| cc)
+}
+
+fn is_ident(s: &str) -> bool {
+  s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+    && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parses a number or a relocated address.
+fn parse_imm(s: &str, pos: usize) -> Result<Imm, SourceError> {
+  let (reloc, digits) = if let Some(x) = s.strip_prefix('$') {
+    (Reloc::Game, x)
+  } else if let Some(x) = s.strip_prefix('@') {
+    (Reloc::Module, x)
+  } else {
+    (Reloc::None, s)
+  };
+  let value = if reloc == Reloc::None {
+    let (neg, x) = match digits.strip_prefix('-') {
+      Some(x) => (true, x),
+      None => (false, digits),
+    };
+    let value = match x.strip_prefix("0x") {
+      Some(x) => i64::from_str_radix(x, 16),
+      None => x.parse(),
+    };
+    value
+      .ok()
+      .filter(|_| !x.starts_with('+'))
+      .map(|x| if neg { -x } else { x })
+  } else {
+    let x = digits.strip_prefix("0x").unwrap_or(digits);
+    u32::from_str_radix(x, 16)
+      .ok()
+      .filter(|_| !x.starts_with('+'))
+      .map(i64::from)
+  };
+  match value {
+    Some(value) => Ok(Imm { value, reloc, pos }),
+    None => Err(error(pos, format!("invalid value `{s}`"))),
+  }
+}
+
+/// Parses the contents of a memory reference.
+fn parse_mem(s: &str, pos: usize, size: Option<u8>) -> Result<Mem, SourceError> {
+  let mut mem = Mem {
+    size,
+    base: None,
+    index: None,
+    disp: Imm { value: 0, reloc: Reloc::None, pos },
+  };
+  let mut neg = false;
+  let mut rest = s;
+  let mut term_pos = pos;
+  loop {
+    let end = rest.find(['+', '-']).unwrap_or(rest.len());
+    let term = rest[..end].trim();
+    let tpos = term_pos + rest[..end].find(|c: char| !c.is_whitespace()).unwrap_or(0);
+    if term.is_empty() {
+      return Err(error(tpos, "expected address term"));
+    }
+
+    let scaled = term.split_once('*').map(|(a, b)| (a.trim(), b.trim()));
+    let reg = match scaled {
+      Some((a, b)) => match (parse_reg(a), parse_reg(b)) {
+        (Some(r), None) => Some((r, b)),
+        (None, Some(r)) => Some((r, a)),
+        _ => return Err(error(tpos, "expected a register and a scale")),
+      },
+      None => parse_reg(term).map(|r| (r, "1")),
+    };
+    if let Some((reg, scale)) = reg {
+      let scale = match scale {
+        "1" => 0,
+        "2" => 1,
+        "4" => 2,
+        "8" => 3,
+        _ => return Err(error(tpos, "scale must be 1, 2, 4 or 8")),
+      };
+      if reg.size != 4 || neg {
+        return Err(error(tpos, "invalid address register"));
+      }
+      if scale == 0 && mem.base.is_none() {
+        mem.base = Some(reg.num);
+      } else if mem.index.is_none() {
+        mem.index = Some((reg.num, scale));
+      } else {
+        return Err(error(tpos, "too many address registers"));
+      }
+    } else {
+      let imm = parse_imm(term, tpos)?;
+      if imm.reloc != Reloc::None && (neg || mem.disp.reloc != Reloc::None) {
+        return Err(error(tpos, "only one relocated value can be added"));
+      }
+      mem.disp.value += if neg { -imm.value } else { imm.value };
+      if imm.reloc != Reloc::None {
+        mem.disp.reloc = imm.reloc;
+        mem.disp.pos = tpos;
+      }
+    }
+
+    let Some(&sign) = rest.as_bytes().get(end) else {
+      break;
+    };
+    neg = sign == b'-';
+    rest = &rest[end + 1..];
+    term_pos += end + 1;
+  }
+
+  // `esp` can only be a base.
+  match (mem.base, mem.index) {
+    (Some(b), Some((4, 0))) if b != 4 => {
+      mem.base = Some(4);
+      mem.index = Some((b, 0));
+    }
+    (_, Some((4, _))) => return Err(error(pos, "`esp` can't be an index")),
+    _ => {}
+  }
+  if !(-(1i64 << 31)..1i64 << 32).contains(&mem.disp.value) {
+    return Err(error(pos, "displacement doesn't fit in 32 bits"));
+  }
+  mem.disp.value = i64::from(mem.disp.value as i32);
+  Ok(mem)
+}
+
+fn parse_operand(s: &str, pos: usize) -> Result<Operand, SourceError> {
+  let lower = s.to_ascii_lowercase();
+  let (size, rest) = [("byte", 1), ("word", 2), ("dword", 4)]
+    .iter()
+    .find_map(|&(name, size)| {
+      let rest = lower.strip_prefix(name)?.trim_start().strip_prefix("ptr")?;
+      Some((Some(size), s.len() - rest.len()))
+    })
+    .unwrap_or((None, 0));
+  let text = s[rest..].trim_start();
+  let pos = pos + (s.len() - text.len());
+
+  if let Some(inner) = text.strip_prefix('[') {
+    let Some(inner) = inner.strip_suffix(']') else {
+      return Err(error(pos, "expected `]`"));
+    };
+    return parse_mem(inner, pos + 1, size).map(Operand::Mem);
+  }
+  if size.is_some() {
+    return Err(error(pos, "expected memory operand"));
+  }
+  if let Some(reg) = parse_reg(text) {
+    Ok(Operand::Reg(reg))
+  } else if is_ident(text) {
+    Ok(Operand::Label(text.into()))
+  } else {
+    parse_imm(text, pos).map(Operand::Imm)
+  }
+}
+
+#[derive(Clone, Copy)]
+enum BranchKind {
+  Call,
+  Jmp,
+  Jcc(u8),
+}
+
+enum Item {
+  Code(Code),
+  Branch {
+    kind: BranchKind,
+    label: usize,
+    pos: usize,
+    /// Whether a `rel32` is needed to reach the target.
+    long: bool,
+  },
+}
+impl Item {
+  fn len(&self) -> usize {
+    match *self {
+      Self::Code(ref c) => c.bytes.len(),
+      Self::Branch { kind: BranchKind::Call, .. } => 5,
+      Self::Branch { long: false, .. } => 2,
+      Self::Branch { kind: BranchKind::Jmp, .. } => 5,
+      Self::Branch { kind: BranchKind::Jcc(_), .. } => 6,
+    }
+  }
+}
+
+/// Gets the operand size of a register or memory operand.
+fn operand_size(op: &Operand) -> Option<u8> {
+  match op {
+    Operand::Reg(r) => Some(r.size),
+    Operand::Mem(m) => m.size,
+    _ => None,
+  }
+}
+
+/// Encodes `mov` and the arithmetic instructions. `op` is the opcode of the
+/// `r/m8, r8` form, and `ext` is the ModRM extension used with an immediate.
+fn encode_binary(
+  code: &mut Code,
+  mov: bool,
+  op: u8,
+  ext: u8,
+  dst: &Operand,
+  src: &Operand,
+  pos: usize,
+) -> Result<(), SourceError> {
+  let size = match (operand_size(dst), operand_size(src)) {
+    (Some(a), Some(b)) if a != b => return Err(error(pos, "operand sizes don't match")),
+    (Some(x), _) | (None, Some(x)) => x,
+    (None, None) => return Err(error(pos, "operand size is unknown; use `ptr`")),
+  };
+  if size == 2 {
+    code.push(0x66);
+  }
+  let wide = u8::from(size != 1);
+  match (dst, src) {
+    (Operand::Reg(_) | Operand::Mem(_), Operand::Reg(r)) => {
+      code.push(op | wide);
+      code.modrm(r.num, dst)
+    }
+    (Operand::Reg(r), Operand::Mem(_)) => {
+      code.push(op | 2 | wide);
+      code.modrm(r.num, src)
+    }
+    (Operand::Reg(r), Operand::Imm(imm)) if mov => {
+      code.push(if size == 1 { 0xb0 } else { 0xb8 } | r.num);
+      code.imm(imm, size)
+    }
+    (Operand::Reg(_) | Operand::Mem(_), Operand::Imm(imm)) => {
+      if mov {
+        code.push(0xc6 | wide);
+        code.modrm(0, dst)?;
+        code.imm(imm, size)
+      } else if size != 1 && imm.reloc == Reloc::None && i8::try_from(imm.value).is_ok() {
+        code.push(0x83);
+        code.modrm(ext, dst)?;
+        code.imm(imm, 1)
+      } else {
+        code.push(0x80 | wide);
+        code.modrm(ext, dst)?;
+        code.imm(imm, size)
+      }
+    }
+    _ => Err(error(pos, "invalid operands")),
+  }
+}
+
+/// Encodes an instruction which doesn't refer to a label.
+fn encode(name: &str, ops: &[Operand], pos: usize) -> Result<Code, SourceError> {
+  let mut code = Code::default();
+  let is_dword = |op: &Operand| matches!(operand_size(op), None | Some(4));
+  match (name, ops) {
+    ("nop", []) => code.push(0x90),
+    ("push", [Operand::Reg(r)]) if r.size == 4 => code.push(0x50 | r.num),
+    ("push", [Operand::Imm(imm)]) => {
+      if imm.reloc == Reloc::None && i8::try_from(imm.value).is_ok() {
+        code.push(0x6a);
+        code.imm(imm, 1)?;
+      } else {
+        code.push(0x68);
+        code.imm(imm, 4)?;
+      }
+    }
+    ("push", [op @ Operand::Mem(_)]) if is_dword(op) => {
+      code.push(0xff);
+      code.modrm(6, op)?;
+    }
+    ("pop", [Operand::Reg(r)]) if r.size == 4 => code.push(0x58 | r.num),
+    ("pop", [op @ Operand::Mem(_)]) if is_dword(op) => {
+      code.push(0x8f);
+      code.modrm(0, op)?;
+    }
+    ("call" | "jmp", [op @ (Operand::Reg(_) | Operand::Mem(_))]) if is_dword(op) => {
+      code.push(0xff);
+      code.modrm(if name == "call" { 2 } else { 4 }, op)?;
+    }
+    ("call" | "jmp", [Operand::Imm(target)]) => {
+      code.push(if name == "call" { 0xe8 } else { 0xe9 });
+      code.branch(target)?;
+    }
+    ("lea", [Operand::Reg(r), op @ Operand::Mem(_)]) if r.size == 4 => {
+      code.push(0x8d);
+      code.modrm(r.num, op)?;
+    }
+    ("mov", [dst, src]) => encode_binary(&mut code, true, 0x88, 0, dst, src, pos)?,
+    ("add", [dst, src]) => encode_binary(&mut code, false, 0x00, 0, dst, src, pos)?,
+    ("sub", [dst, src]) => encode_binary(&mut code, false, 0x28, 5, dst, src, pos)?,
+    ("cmp", [dst, src]) => encode_binary(&mut code, false, 0x38, 7, dst, src, pos)?,
+    ("nop" | "push" | "pop" | "call" | "jmp" | "lea" | "mov" | "add" | "sub" | "cmp", _) => {
+      return Err(error(pos, format!("invalid operands for `{name}`")))
+    }
+    (_, [Operand::Imm(target)]) if condition(name).is_some() => {
+      code.push(0x0f);
+      code.push(0x80 | condition(name).unwrap());
+      code.branch(target)?;
+    }
+    _ if condition(name).is_some() => {
+      return Err(error(
+        pos,
+        format!("`{name}` must target a label or a `$` address"),
+      ))
+    }
+    _ => return Err(error(pos, format!("unknown instruction `{name}`"))),
+  }
+  Ok(code)
+}
+
+/// Gets the length of the label's name if the text starts with a label.
+fn label_len(text: &str) -> Option<usize> {
+  let len = text.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))?;
+  (len != 0 && is_ident(&text[..len]) && text[len..].starts_with(':')).then_some(len)
+}
+
+pub fn assemble(src: &str) -> Result<Code, SourceError> {
+  let mut items = Vec::new();
+  // Each label's name and the index of the item it precedes.
+  let mut labels: HashMap<&str, usize> = HashMap::new();
+  // Each branch target's name and position.
+  let mut targets = Vec::new();
+
+  let mut line_pos = 0;
+  for line in src.split('\n') {
+    let start = line_pos;
+    line_pos += line.len() + 1;
+    let mut line = line.split(';').next().unwrap();
+    let mut pos = start;
+
+    loop {
+      let trimmed = line.trim_start();
+      pos += line.len() - trimmed.len();
+      line = trimmed;
+      let Some(len) = label_len(line) else {
+        break;
+      };
+      if labels.insert(&line[..len], items.len()).is_some() {
+        return Err(error(pos, format!("duplicate label `{}`", &line[..len])));
+      }
+      pos += len + 1;
+      line = &line[len + 1..];
+    }
+    let line = line.trim_end();
+    if line.is_empty() {
+      continue;
+    }
+
+    let name_len = line.find(char::is_whitespace).unwrap_or(line.len());
+    let name = line[..name_len].to_ascii_lowercase();
+    let mut ops = Vec::new();
+    let args = &line[name_len..];
+    if !args.trim().is_empty() {
+      let mut arg_pos = pos + name_len;
+      for arg in args.split(',') {
+        let text = arg.trim();
+        let text_pos = arg_pos + (arg.len() - arg.trim_start().len());
+        if text.is_empty() {
+          return Err(error(text_pos, "expected operand"));
+        }
+        ops.push(parse_operand(text, text_pos)?);
+        arg_pos += arg.len() + 1;
+      }
+    }
+
+    let branch = match &*name {
+      "call" => Some(BranchKind::Call),
+      "jmp" => Some(BranchKind::Jmp),
+      _ => condition(&name).map(BranchKind::Jcc),
+    };
+    match (branch, &*ops) {
+      (Some(kind), [Operand::Label(label)]) => {
+        items.push(Item::Branch { kind, label: targets.len(), pos, long: false });
+        targets.push((label.clone(), pos));
+      }
+      (_, [Operand::Label(label), ..] | [_, Operand::Label(label)]) => {
+        return Err(error(pos, format!("unknown register `{label}`")));
+      }
+      _ => items.push(Item::Code(encode(&name, &ops, pos)?)),
+    }
+  }
+
+  let targets = targets
+    .into_iter()
+    .map(|(label, pos)| match labels.get(&*label) {
+      Some(&x) => Ok(x),
+      None => Err(error(pos, format!("unknown label `{label}`"))),
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+
+  // Start with short branches and lengthen them until every target is in
+  // range. Branches only ever get longer so this always finishes.
+  let addresses = loop {
+    let mut addresses = Vec::with_capacity(items.len() + 1);
+    let mut address = 0;
+    for item in &items {
+      addresses.push(address);
+      address += item.len();
+    }
+    addresses.push(address);
+
+    let mut changed = false;
+    for (i, item) in items.iter_mut().enumerate() {
+      if let Item::Branch { label, long: long @ false, .. } = item {
+        let disp = addresses[targets[*label]] as isize - (addresses[i] + 2) as isize;
+        if i8::try_from(disp).is_err() {
+          *long = true;
+          changed = true;
+        }
+      }
+    }
+    if !changed {
+      break addresses;
+    }
+  };
+
+  let mut code = Code::default();
+  for (i, item) in items.iter().enumerate() {
+    match *item {
+      Item::Code(ref c) => code.extend(c),
+      Item::Branch { kind, label, pos, long } => {
+        let disp = addresses[targets[label]] as i64 - addresses[i + 1] as i64;
+        let imm = Imm { value: disp, reloc: Reloc::None, pos };
+        match (kind, long) {
+          (BranchKind::Call, _) => code.push(0xe8),
+          (BranchKind::Jmp, false) => code.push(0xeb),
+          (BranchKind::Jmp, true) => code.push(0xe9),
+          (BranchKind::Jcc(cc), false) => code.push(0x70 | cc),
+          (BranchKind::Jcc(cc), true) => {
+            code.push(0x0f);
+            code.push(0x80 | cc);
+          }
+        }
+        code.imm(&imm, if item.len() == 2 { 1 } else { 4 })?;
+      }
+    }
+  }
+  if code.bytes.len() > usize::from(u16::MAX) {
+    return Err(error(0, "assembled code is too large"));
+  }
+  Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::assemble;
+
+  /// Assembles the code and gets the error's line, column and message.
+  fn error(src: &str) -> (usize, usize, String) {
+    match assemble(src) {
+      Ok(_) => panic!("assembled `{src}`"),
+      Err(e) => {
+        let (line, col) = e.line_col(src);
+        (line, col, e.msg)
+      }
+    }
+  }
+
+  #[test]
+  fn errors() {
+    #[rustfmt::skip]
+    let cases: &[(&str, usize, usize, &str)] = &[
+      ("mov al, $6fb8a0c4", 1, 9, "relocated values must be 32 bits"),
+      ("mov al, 0x100", 1, 9, "value doesn't fit in 8 bits"),
+      ("push 0x1g", 1, 6, "invalid value `0x1g`"),
+      ("push dword ptr [eax + ]", 1, 22, "expected address term"),
+      ("push dword ptr [eax*ecx]", 1, 17, "expected a register and a scale"),
+      ("push dword ptr [eax*3]", 1, 17, "scale must be 1, 2, 4 or 8"),
+      ("push dword ptr [ax]", 1, 17, "invalid address register"),
+      ("push dword ptr [1 - eax]", 1, 21, "invalid address register"),
+      ("push dword ptr [eax + ecx + edx]", 1, 29, "too many address registers"),
+      ("push dword ptr [$6fb8a0c4 + @10]", 1, 29, "only one relocated value can be added"),
+      ("push dword ptr [esp*2]", 1, 17, "`esp` can't be an index"),
+      ("push dword ptr [0x100000000]", 1, 17, "displacement doesn't fit in 32 bits"),
+      ("push dword ptr [eax", 1, 16, "expected `]`"),
+      ("push dword ptr eax", 1, 16, "expected memory operand"),
+      ("mov eax, cl", 1, 1, "operand sizes don't match"),
+      ("mov [eax], 1", 1, 1, "operand size is unknown; use `ptr`"),
+      ("mov 1, eax", 1, 1, "invalid operands"),
+      ("push al", 1, 1, "invalid operands for `push`"),
+      ("\n  nop\n  jz eax", 3, 3, "`jz` must target a label or a `$` address"),
+      ("call 0x1000", 1, 6, "direct branches must target a label or a `$` address"),
+      ("jne @1000", 1, 5, "direct branches must target a label or a `$` address"),
+      ("movzx eax, cl", 1, 1, "unknown instruction `movzx`"),
+      ("a: nop\n  a: nop", 2, 3, "duplicate label `a`"),
+      ("mov eax, ", 1, 9, "expected operand"),
+      ("mov eax, foo", 1, 1, "unknown register `foo`"),
+      ("  jmp end", 1, 3, "unknown label `end`"),
+    ];
+    for &(src, line, col, msg) in cases {
+      assert_eq!(error(src), (line, col, msg.into()), "`{src}`");
+    }
+  }
+
+  #[test]
+  fn too_large() {
+    let src = "push dword ptr [eax + 0x1000]\n".repeat(0x3000);
+    assert_eq!(error(&src).2, "assembled code is too large");
+  }
+
+  #[test]
+  fn branch_to_address() {
+    let code = assemble("call $6fb7e840\n jmp $6fb7e840\n je $6fb7e840\n jmp end\n end:").unwrap();
+    assert_eq!(
+      code.bytes,
+      [
+        0xe8, 0x40, 0xe8, 0xb7, 0x6f, 0xe9, 0x40, 0xe8, 0xb7, 0x6f, 0x0f, 0x84, 0x40, 0xe8, 0xb7,
+        0x6f, 0xeb, 0x00,
+      ]
+    );
+    assert_eq!(code.branch_relocs, [1, 6, 12]);
+    assert!(code.relocs.is_empty());
+  }
+}
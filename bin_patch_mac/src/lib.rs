@@ -1,126 +1,260 @@
 extern crate proc_macro;
 use proc_macro::{
   Delimiter::{Bracket, Parenthesis},
-  Group, Literal, Punct,
+  Group, Ident, Literal, Punct,
   Spacing::Alone,
-  TokenStream, TokenTree as TT,
+  Span, TokenStream, TokenTree as TT,
 };
 
-struct Uint2Collector {
-  collected: Vec<u8>,
-  next: u8,
-  pos: u8,
+use bin_patch_syntax::source;
+
+mod asm;
+mod versions;
+
+/// Creates a `compile_error!` invocation with the given message.
+fn error(span: Span, msg: &str) -> TokenStream {
+  let mut lit = Literal::string(msg);
+  lit.set_span(span);
+  let mut bang = Punct::new('!', Alone);
+  bang.set_span(span);
+  let mut group = Group::new(Parenthesis, TokenStream::from_iter([TT::Literal(lit)]));
+  group.set_span(span);
+  TokenStream::from_iter([
+    TT::Ident(Ident::new("compile_error", span)),
+    TT::Punct(bang),
+    TT::Group(group),
+  ])
 }
-impl Uint2Collector {
-  fn new() -> Self {
-    Self { collected: Vec::with_capacity(8), next: 0, pos: 0 }
-  }
 
-  fn push(&mut self, x: u8) {
-    assert!(x < 3);
-    self.next |= x << self.pos;
-    self.pos = if self.pos == 6 {
-      self.collected.push(self.next);
-      self.next = 0;
-      0
-    } else {
-      self.pos + 2
-    };
+/// Gets the contents of a string literal token along with its span.
+fn parse_str(token: Option<TT>) -> Result<(String, Span), TokenStream> {
+  let Some(TT::Literal(lit)) = token else {
+    let span = token.map_or_else(Span::call_site, |x| x.span());
+    return Err(error(span, "expected string literal"));
+  };
+  let text = lit.to_string();
+  match text.strip_prefix('"').and_then(|x| x.strip_suffix('"')) {
+    Some(x) => Ok((x.into(), lit.span())),
+    None => Err(error(lit.span(), "expected string literal")),
   }
+}
 
-  fn into_iter(mut self) -> impl Iterator<Item = u8> {
-    if self.next != 0 {
-      self.collected.push(self.next);
-    }
-    let idx = self.collected.iter().rposition(|x| *x != 0).map_or(0, |x| x + 1);
-    self.collected.into_iter().take(idx)
+/// Creates a `compile_error!` for an error within a string literal. The
+/// location is included in the message since the span can only cover the
+/// whole literal.
+fn source_error(src: &str, span: Span, e: source::SourceError) -> TokenStream {
+  let (line, col) = e.line_col(src);
+  // Skip the literal's opening quote.
+  let col = if line == 1 { span.column() + col } else { col };
+  let line = span.line() + line - 1;
+  error(span, &format!("{} (at {line}:{col})", e.msg))
+}
+
+/// Creates a `u16` slice expression.
+fn u16_slice(values: &[usize]) -> [TT; 2] {
+  [
+    TT::Punct(Punct::new('&', Alone)),
+    TT::Group(Group::new(
+      Bracket,
+      TokenStream::from_iter(values.iter().flat_map(|&x| {
+        [
+          TT::Literal(Literal::u16_suffixed(x as u16)),
+          TT::Punct(Punct::new(',', Alone)),
+        ]
+      })),
+    )),
+  ]
+}
+
+/// Creates the `(data, relocs, module_relocs, branch_relocs)` tuple taken by
+/// `Patch::raw_reloc`.
+fn data_tuple(
+  bytes: &[u8],
+  relocs: &[usize],
+  module_relocs: &[usize],
+  branch_relocs: &[usize],
+) -> TokenStream {
+  let mut tokens = vec![
+    TT::Literal(Literal::byte_string(bytes)),
+    TT::Punct(Punct::new(',', Alone)),
+  ];
+  for positions in [relocs, module_relocs, branch_relocs] {
+    tokens.extend(u16_slice(positions));
+    tokens.push(TT::Punct(Punct::new(',', Alone)));
   }
+  tokens.pop();
+  TokenStream::from_iter([TT::Group(Group::new(
+    Parenthesis,
+    TokenStream::from_iter(tokens),
+  ))])
 }
 
 #[proc_macro]
 pub fn patch_source(i: TokenStream) -> TokenStream {
   let mut i = i.into_iter();
-  let Some(TT::Literal(lit)) = i.next() else {
-    panic!("expected string literal");
-  };
-  let lit = lit.to_string();
-  let Some(lit) = lit.strip_prefix('"') else {
-    panic!("expected string literal");
+  let mut next = i.next();
+  // Data patches skip the instruction checks.
+  let is_code = match &next {
+    Some(TT::Ident(x)) if x.to_string() == "data" => {
+      next = i.next();
+      false
+    }
+    _ => true,
   };
-  let Some(mut lit) = lit.strip_suffix('"') else {
-    panic!("expected string literal");
+  let (lit, span) = match parse_str(next) {
+    Ok(x) => x,
+    Err(e) => return e,
   };
 
-  let mut bytes = Vec::with_capacity(256);
-  let mut control_stream = Uint2Collector::new();
-  let mut cur_reloc = None;
-
-  while let Some(&c) = lit.as_bytes().first() {
-    match c {
-      b' ' | b'\t' | b'\n' | b'\r' => {
-        if let Some(cur_reloc) = cur_reloc.take() {
-          assert!(cur_reloc + 4 == bytes.len(), "incorrect relocation size");
-        }
-        lit = &lit[1..];
-      }
-      b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F' => {
-        if let Some(b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F') = lit.as_bytes().get(1) {
-          if cur_reloc.is_none() {
-            control_stream.push(0);
-          }
-          bytes.push(u8::from_str_radix(&lit[..2], 16).unwrap());
-          lit = &lit[2..];
-        } else {
-          panic!("incomplete byte value");
-        }
-      }
-      b'x' if cur_reloc.is_none() => {
-        if let Some(b'x') = lit.as_bytes().get(1) {
-          control_stream.push(1);
-          bytes.push(0);
-          lit = &lit[2..];
-        } else {
-          panic!("incomplete byte mask");
-        }
-      }
-      b'x' => panic!("masked byte in relocation"),
-      b'$' => match cur_reloc {
-        Some(_) => panic!("already reading relocation"),
-        None => {
-          control_stream.push(2);
-          cur_reloc = Some(bytes.len());
-          lit = &lit[1..];
-        }
-      },
-      _ => panic!("unexpected character `{}`", lit.chars().next().unwrap()),
+  let src = match source::Source::parse(&lit).and_then(|src| {
+    src.check_pattern()?;
+    if is_code {
+      src.check_instructions()?;
     }
-  }
-
-  if let Some(cur_reloc) = cur_reloc {
-    assert!(cur_reloc + 4 == bytes.len(), "incorrect relocation size");
-  }
-
-  let hash = bytes.iter().fold(0x01000193u32, |hash, x| {
-    (hash ^ *x as u32).wrapping_mul(0x01000193u32)
-  });
+    Ok(src)
+  }) {
+    Ok(src) => src,
+    Err(e) => return source_error(&lit, span, e),
+  };
 
   TokenStream::from_iter([TT::Group(Group::new(
     Parenthesis,
     TokenStream::from_iter([
-      TT::Literal(Literal::u16_suffixed(bytes.len() as u16)),
+      TT::Literal(Literal::u16_suffixed(src.bytes.len() as u16)),
       TT::Punct(Punct::new(',', Alone)),
-      TT::Literal(Literal::u32_suffixed(hash)),
+      TT::Literal(Literal::u32_suffixed(src.hash())),
       TT::Punct(Punct::new(',', Alone)),
       TT::Punct(Punct::new('&', Alone)),
       TT::Group(Group::new(
         Bracket,
-        TokenStream::from_iter(control_stream.into_iter().flat_map(|x| {
+        TokenStream::from_iter(src.control_stream.iter().flat_map(|x| {
           [
-            TT::Literal(Literal::u8_suffixed(x)),
+            TT::Literal(Literal::u8_suffixed(*x)),
             TT::Punct(Punct::new(',', Alone)),
           ]
         })),
       )),
+      TT::Punct(Punct::new(',', Alone)),
+      TT::Literal(Literal::byte_string(if cfg!(feature = "expected-bytes") {
+        &src.bytes
+      } else {
+        &[]
+      })),
+      TT::Punct(Punct::new(',', Alone)),
+      TT::Ident(Ident::new(
+        if is_code { "true" } else { "false" },
+        Span::call_site(),
+      )),
     ]),
   ))])
 }
+
+/// Creates the data for `Patch::raw_reloc`. Uses the same syntax as
+/// `patch_source!`, except bytes can't be masked and `@` marks a value relative
+/// to the module's base address.
+#[proc_macro]
+pub fn patch_data(i: TokenStream) -> TokenStream {
+  let (lit, span) = match parse_str(i.into_iter().next()) {
+    Ok(x) => x,
+    Err(e) => return e,
+  };
+  let src = match source::Source::parse(&lit).and_then(|src| src.check_data().map(|()| src)) {
+    Ok(src) => src,
+    Err(e) => return source_error(&lit, span, e),
+  };
+
+  data_tuple(&src.bytes, &src.relocs, &src.module_relocs, &[])
+}
+
+/// Assembles 32-bit x86 code into the data for `Patch::raw_reloc`. Supports
+/// `mov`, `push`, `pop`, `call`, `jmp`, `cmp`, the conditional jumps, `lea`,
+/// `add`, `sub` and `nop` written in Intel syntax, one instruction per line.
+///
+/// `$` before a hex address marks it as relocated with the module and `@`
+/// marks a value relative to the module's base address. Direct branches can
+/// target either a label within the code or a `$` address in the module.
+///
+/// ```
+/// use bin_patch::{asm_patch, patch_source, Patch};
+///
+/// const PATCH: Patch = Patch::raw_reloc(
+///   0x1234,
+///   patch_source!("a1 $c4a0b86f 83f802 7507 50 ff15 $40e8b76f e9 xxxxxxxx"),
+///   asm_patch!("
+///       mov eax, dword ptr [$6fb8a0c4]
+///       cmp eax, 2
+///       jne skip
+///       push eax
+///       call dword ptr [$6fb7e840]
+///     skip:
+///       jmp $6fb8a0d0
+///   "),
+/// );
+/// assert_eq!(PATCH.range(), 0x1234..0x124a);
+/// ```
+#[proc_macro]
+pub fn asm_patch(i: TokenStream) -> TokenStream {
+  let (lit, span) = match parse_str(i.into_iter().next()) {
+    Ok(x) => x,
+    Err(e) => return e,
+  };
+  match asm::assemble(&lit) {
+    Ok(code) => data_tuple(
+      &code.bytes,
+      &code.relocs,
+      &code.module_relocs,
+      &code.branch_relocs,
+    ),
+    Err(e) => source_error(&lit, span, e),
+  }
+}
+
+/// Declares patches which exist in multiple versions of a module. Each patch is
+/// declared once with its constructor, the arguments following the patch
+/// source, and a table of the offset and source in each version. Every listed
+/// version must have an entry unless there is a default entry.
+///
+/// A default entry, written as `_ => offset, "source";`, is used for every
+/// version without an entry. An entry written as `version => offset;` uses the
+/// default entry's source at a different offset.
+///
+/// Expands to a module for each version containing a `Patch` constant for each
+/// declared patch. The modules import everything from the enclosing module.
+///
+/// ```
+/// use bin_patch::patch_versions;
+///
+/// unsafe extern "C" fn draw_game_paused() {}
+///
+/// patch_versions! {
+///   versions: [v101, v102, v103];
+///
+///   /// Draw paused game framerate
+///   DRAW_GAME_PAUSED = call_c(draw_game_paused) {
+///     v101 => 0xf957, "ff15 $1cea1210";
+///     v102 => 0xf4e5, "ff15 $5ce91210";
+///     v103 => 0xf535, "ff15 $bce81210";
+///   }
+///
+///   /// Skip a call
+///   SKIP_CALL = nop() {
+///     _ => 0xd3fb, "e860030000";
+///     v102 => 0xd12b;
+///   }
+/// }
+///
+/// fn main() {
+///   assert_eq!(v102::DRAW_GAME_PAUSED.range(), 0xf4e5..0xf4eb);
+///   assert_eq!(v101::SKIP_CALL.range(), 0xd3fb..0xd400);
+///   assert_eq!(v102::SKIP_CALL.range(), 0xd12b..0xd130);
+///   assert_eq!(v103::SKIP_CALL.range(), 0xd3fb..0xd400);
+/// }
+/// ```
+#[proc_macro]
+pub fn patch_versions(i: TokenStream) -> TokenStream {
+  versions::expand(i).unwrap_or_else(|mut e| {
+    // Expanded in item position.
+    e.extend([TT::Punct(Punct::new(';', Alone))]);
+    e
+  })
+}
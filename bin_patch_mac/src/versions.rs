@@ -0,0 +1,274 @@
+//! Parsing and expansion for `patch_versions!`.
+
+use crate::error;
+use core::iter::Peekable;
+use proc_macro::{
+  token_stream::IntoIter, Delimiter, Group, Ident, Punct, Spacing::Alone, Span, TokenStream,
+  TokenTree as TT,
+};
+
+type Tokens = Peekable<IntoIter>;
+
+/// The location of a patch in a single version.
+struct Entry {
+  /// The version, or `_` for the default entry.
+  version: TT,
+  offset: TT,
+  /// The `data` marker passed on to `patch_source!` and the source. Missing if
+  /// the entry uses the default source.
+  src: Option<(Option<TT>, TT)>,
+}
+impl Entry {
+  fn is_default(&self) -> bool {
+    matches!(&self.version, TT::Ident(x) if x.to_string() == "_")
+  }
+}
+
+/// A patch declared for every version.
+struct Decl {
+  attrs: Vec<TT>,
+  name: Ident,
+  ctor: Ident,
+  args: TokenStream,
+  entries: Vec<Entry>,
+  default: Option<Entry>,
+}
+
+fn span_of(token: Option<&TT>) -> Span {
+  token.map_or_else(Span::call_site, |x| x.span())
+}
+
+fn expect_punct(tokens: &mut Tokens, c: char) -> Result<(), TokenStream> {
+  match tokens.next() {
+    Some(TT::Punct(x)) if x.as_char() == c => Ok(()),
+    x => Err(error(span_of(x.as_ref()), &format!("expected `{c}`"))),
+  }
+}
+
+fn expect_ident(tokens: &mut Tokens) -> Result<Ident, TokenStream> {
+  match tokens.next() {
+    Some(TT::Ident(x)) => Ok(x),
+    x => Err(error(span_of(x.as_ref()), "expected identifier")),
+  }
+}
+
+fn expect_group(tokens: &mut Tokens, delim: Delimiter) -> Result<Group, TokenStream> {
+  match tokens.next() {
+    Some(TT::Group(x)) if x.delimiter() == delim => Ok(x),
+    x => {
+      let expected = match delim {
+        Delimiter::Parenthesis => "`(`",
+        Delimiter::Bracket => "`[`",
+        _ => "`{`",
+      };
+      Err(error(span_of(x.as_ref()), &format!("expected {expected}")))
+    }
+  }
+}
+
+/// Consumes the punctuation if it's the next token.
+fn eat_punct(tokens: &mut Tokens, c: char) -> bool {
+  let found = matches!(tokens.peek(), Some(TT::Punct(x)) if x.as_char() == c);
+  if found {
+    tokens.next();
+  }
+  found
+}
+
+fn parse_versions(tokens: &mut Tokens) -> Result<Vec<Ident>, TokenStream> {
+  let kw = expect_ident(tokens)?;
+  if kw.to_string() != "versions" {
+    return Err(error(kw.span(), "expected `versions`"));
+  }
+  expect_punct(tokens, ':')?;
+  let mut list = expect_group(tokens, Delimiter::Bracket)?
+    .stream()
+    .into_iter()
+    .peekable();
+  expect_punct(tokens, ';')?;
+
+  let mut versions: Vec<Ident> = Vec::new();
+  while list.peek().is_some() {
+    let version = expect_ident(&mut list)?;
+    if versions.iter().any(|x| x.to_string() == version.to_string()) {
+      return Err(error(
+        version.span(),
+        &format!("duplicate version `{version}`"),
+      ));
+    }
+    versions.push(version);
+    if list.peek().is_some() {
+      expect_punct(&mut list, ',')?;
+    }
+  }
+  Ok(versions)
+}
+
+fn parse_entry(tokens: &mut Tokens) -> Result<Entry, TokenStream> {
+  let version = match tokens.next() {
+    Some(x @ TT::Ident(_)) => x,
+    x => return Err(error(span_of(x.as_ref()), "expected version or `_`")),
+  };
+  expect_punct(tokens, '=')?;
+  expect_punct(tokens, '>')?;
+  let offset = match tokens.next() {
+    Some(x @ TT::Literal(_)) => x,
+    x => return Err(error(span_of(x.as_ref()), "expected patch offset")),
+  };
+  // The source can be left out to use the default entry's.
+  let src = if tokens.peek().is_none() || eat_punct(tokens, ';') {
+    None
+  } else {
+    expect_punct(tokens, ',')?;
+    let data = match tokens.peek() {
+      Some(TT::Ident(x)) if x.to_string() == "data" => tokens.next(),
+      _ => None,
+    };
+    let src = match tokens.next() {
+      Some(x @ TT::Literal(_)) => x,
+      x => return Err(error(span_of(x.as_ref()), "expected string literal")),
+    };
+    if tokens.peek().is_some() {
+      expect_punct(tokens, ';')?;
+    }
+    Some((data, src))
+  };
+  Ok(Entry { version, offset, src })
+}
+
+fn parse_decl(tokens: &mut Tokens, versions: &[Ident]) -> Result<Decl, TokenStream> {
+  let mut attrs = Vec::new();
+  while let Some(TT::Punct(x)) = tokens.peek() {
+    if x.as_char() != '#' {
+      break;
+    }
+    attrs.push(tokens.next().unwrap());
+    attrs.push(TT::Group(expect_group(tokens, Delimiter::Bracket)?));
+  }
+  let name = expect_ident(tokens)?;
+  expect_punct(tokens, '=')?;
+  let ctor = expect_ident(tokens)?;
+  let args = expect_group(tokens, Delimiter::Parenthesis)?.stream();
+  let mut body = expect_group(tokens, Delimiter::Brace)?
+    .stream()
+    .into_iter()
+    .peekable();
+  eat_punct(tokens, ';');
+
+  let mut entries: Vec<Entry> = Vec::new();
+  let mut default: Option<Entry> = None;
+  while body.peek().is_some() {
+    let entry = parse_entry(&mut body)?;
+    let version = entry.version.to_string();
+    if entry.is_default() {
+      if default.is_some() {
+        return Err(error(entry.version.span(), "duplicate default entry"));
+      }
+      if entry.src.is_none() {
+        return Err(error(
+          entry.version.span(),
+          "the default entry requires a patch source",
+        ));
+      }
+      default = Some(entry);
+      continue;
+    }
+    if !versions.iter().any(|x| x.to_string() == version) {
+      return Err(error(
+        entry.version.span(),
+        &format!("unknown version `{version}`"),
+      ));
+    }
+    if entries.iter().any(|x| x.version.to_string() == version) {
+      return Err(error(
+        entry.version.span(),
+        &format!("duplicate entry for `{version}`"),
+      ));
+    }
+    entries.push(entry);
+  }
+  if default.is_none() {
+    if let Some(entry) = entries.iter().find(|x| x.src.is_none()) {
+      return Err(error(
+        entry.version.span(),
+        &format!(
+          "`{}` has no patch source and there is no default entry",
+          entry.version
+        ),
+      ));
+    }
+    let missing: Vec<_> = versions
+      .iter()
+      .map(|x| x.to_string())
+      .filter(|x| !entries.iter().any(|e| e.version.to_string() == *x))
+      .collect();
+    if !missing.is_empty() {
+      return Err(error(
+        name.span(),
+        &format!("`{name}` has no entry for: {}", missing.join(", ")),
+      ));
+    }
+  }
+  Ok(Decl { attrs, name, ctor, args, entries, default })
+}
+
+/// Builds the `Patch` constant for a single version.
+fn expand_const(decl: &Decl, version: &Ident) -> TokenStream {
+  let default = decl.default.as_ref();
+  let entry = decl
+    .entries
+    .iter()
+    .find(|x| x.version.to_string() == version.to_string())
+    .or(default)
+    .unwrap();
+  let (data, lit) = entry.src.as_ref().or(default.and_then(|x| x.src.as_ref())).unwrap();
+  let mut src = TokenStream::new();
+  src.extend(data.clone());
+  src.extend([lit.clone()]);
+
+  let mut args = TokenStream::from_iter([entry.offset.clone(), TT::Punct(Punct::new(',', Alone))]);
+  args.extend("::bin_patch::patch_source!".parse::<TokenStream>().unwrap());
+  args.extend([TT::Group(Group::new(Delimiter::Parenthesis, src))]);
+  if !decl.args.is_empty() {
+    args.extend([TT::Punct(Punct::new(',', Alone))]);
+    args.extend(decl.args.clone());
+  }
+
+  let mut out = TokenStream::from_iter(decl.attrs.iter().cloned());
+  out.extend("pub const".parse::<TokenStream>().unwrap());
+  out.extend([TT::Ident(decl.name.clone())]);
+  out.extend(
+    ": ::bin_patch::Patch = ::bin_patch::Patch::"
+      .parse::<TokenStream>()
+      .unwrap(),
+  );
+  out.extend([
+    TT::Ident(decl.ctor.clone()),
+    TT::Group(Group::new(Delimiter::Parenthesis, args)),
+    TT::Punct(Punct::new(';', Alone)),
+  ]);
+  out
+}
+
+pub fn expand(input: TokenStream) -> Result<TokenStream, TokenStream> {
+  let mut tokens = input.into_iter().peekable();
+  let versions = parse_versions(&mut tokens)?;
+  let mut decls = Vec::new();
+  while tokens.peek().is_some() {
+    decls.push(parse_decl(&mut tokens, &versions)?);
+  }
+
+  let mut out = TokenStream::new();
+  for version in &versions {
+    let mut body: TokenStream = "#[allow(unused_imports)] use super::*;".parse().unwrap();
+    for decl in &decls {
+      body.extend(expand_const(decl, version));
+    }
+    out.extend("pub mod".parse::<TokenStream>().unwrap());
+    out.extend([
+      TT::Ident(version.clone()),
+      TT::Group(Group::new(Delimiter::Brace, body)),
+    ]);
+  }
+  Ok(out)
+}
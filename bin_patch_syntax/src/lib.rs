@@ -0,0 +1,5 @@
+//! The instruction decoder and patch source syntax shared by `bin_patch` and
+//! its macros.
+
+pub mod source;
+pub mod x86;
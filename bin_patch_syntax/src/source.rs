@@ -0,0 +1,305 @@
+//! The patch source syntax used by both `patch_source!` and patches loaded at
+//! runtime.
+//!
+//! A source is a sequence of hex bytes separated by any amount of whitespace.
+//! `xx` marks a byte which isn't checked, and `$` before four bytes marks a
+//! value which is adjusted by the module's relocation distance. In patch data
+//! `@` before four bytes marks a value relative to the module's base address.
+//!
+//! `;` starts a comment which runs to the end of the line, and a name followed
+//! by `:` labels the following bytes. Labels are only used to annotate the
+//! source and must be unique.
+
+use crate::x86;
+
+struct Uint2Collector {
+  collected: Vec<u8>,
+  next: u8,
+  pos: u8,
+}
+impl Uint2Collector {
+  fn new() -> Self {
+    Self { collected: Vec::with_capacity(8), next: 0, pos: 0 }
+  }
+
+  fn push(&mut self, x: u8) {
+    assert!(x < 3);
+    self.next |= x << self.pos;
+    self.pos = if self.pos == 6 {
+      self.collected.push(self.next);
+      self.next = 0;
+      0
+    } else {
+      self.pos + 2
+    };
+  }
+
+  fn finish(mut self) -> Vec<u8> {
+    if self.next != 0 {
+      self.collected.push(self.next);
+    }
+    let idx = self.collected.iter().rposition(|x| *x != 0).map_or(0, |x| x + 1);
+    self.collected.truncate(idx);
+    self.collected
+  }
+}
+
+/// An error in a patch source.
+#[derive(Debug)]
+pub struct SourceError {
+  /// The byte offset in the source text the error occurred at.
+  pub pos: usize,
+  pub msg: String,
+}
+impl SourceError {
+  fn new(pos: usize, msg: impl Into<String>) -> Self {
+    Self { pos, msg: msg.into() }
+  }
+
+  /// Gets the line and column, starting from one, the error occurred at.
+  pub fn line_col(&self, src: &str) -> (usize, usize) {
+    let before = &src[..self.pos.min(src.len())];
+    let line_start = before.rfind('\n').map_or(0, |x| x + 1);
+    (
+      before.matches('\n').count() + 1,
+      before[line_start..].chars().count() + 1,
+    )
+  }
+}
+
+/// Gets the length of the label's name if the text starts with a label.
+fn label_len(text: &[u8]) -> Option<usize> {
+  let len = text.iter().position(|&c| !(c.is_ascii_alphanumeric() || c == b'_'))?;
+  (len != 0 && !text[0].is_ascii_digit() && text[len] == b':').then_some(len)
+}
+
+/// A parsed patch source.
+pub struct Source {
+  /// The expected bytes with masked bytes set to zero.
+  pub bytes: Vec<u8>,
+  /// Whether each byte is masked.
+  pub masked: Vec<bool>,
+  /// The position of each relocated value.
+  pub relocs: Vec<usize>,
+  /// The position of each module-relative value.
+  pub module_relocs: Vec<usize>,
+  /// How each byte is checked, packed as two bit values.
+  pub control_stream: Vec<u8>,
+  /// The offset in the source text of each byte.
+  text_pos: Vec<usize>,
+}
+impl Source {
+  pub fn parse(src: &str) -> Result<Self, SourceError> {
+    let text = src.as_bytes();
+    let mut bytes = Vec::with_capacity(256);
+    let mut masked = Vec::with_capacity(256);
+    let mut relocs = Vec::new();
+    let mut module_relocs = Vec::new();
+    let mut labels = Vec::new();
+    let mut control_stream = Uint2Collector::new();
+    let mut text_pos = Vec::with_capacity(256);
+    // The start of the current relocated value in both the bytes and the text.
+    let mut cur_reloc: Option<(usize, usize)> = None;
+    let end_reloc = |cur_reloc: &mut Option<(usize, usize)>, len: usize| match cur_reloc.take() {
+      Some((start, pos)) if start + 4 != len => {
+        Err(SourceError::new(pos, "incorrect relocation size"))
+      }
+      _ => Ok(()),
+    };
+
+    let mut pos = 0;
+    while let Some(&c) = text.get(pos) {
+      match c {
+        b' ' | b'\t' | b'\n' | b'\r' => {
+          end_reloc(&mut cur_reloc, bytes.len())?;
+          pos += 1;
+        }
+        b';' => {
+          end_reloc(&mut cur_reloc, bytes.len())?;
+          pos = text[pos..]
+            .iter()
+            .position(|&c| c == b'\n')
+            .map_or(text.len(), |x| pos + x);
+        }
+        // Hex digits can also start a label, so check for one first.
+        _ if cur_reloc.is_none() && label_len(&text[pos..]).is_some() => {
+          let len = label_len(&text[pos..]).unwrap();
+          let name = &src[pos..pos + len];
+          if labels.contains(&name) {
+            return Err(SourceError::new(pos, format!("duplicate label `{name}`")));
+          }
+          labels.push(name);
+          pos += len + 1;
+        }
+        b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F' => {
+          if let Some(b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F') = text.get(pos + 1) {
+            if cur_reloc.is_none() {
+              control_stream.push(0);
+            }
+            bytes.push(u8::from_str_radix(&src[pos..pos + 2], 16).unwrap());
+            masked.push(false);
+            text_pos.push(pos);
+            pos += 2;
+          } else {
+            return Err(SourceError::new(pos, "incomplete byte value"));
+          }
+        }
+        b'x' if cur_reloc.is_none() => {
+          if let Some(b'x') = text.get(pos + 1) {
+            control_stream.push(1);
+            bytes.push(0);
+            masked.push(true);
+            text_pos.push(pos);
+            pos += 2;
+          } else {
+            return Err(SourceError::new(pos, "incomplete byte mask"));
+          }
+        }
+        b'x' => return Err(SourceError::new(pos, "masked byte in relocation")),
+        b'$' | b'@' => match cur_reloc {
+          Some(_) => return Err(SourceError::new(pos, "already reading relocation")),
+          None => {
+            control_stream.push(2);
+            cur_reloc = Some((bytes.len(), pos));
+            if c == b'$' {
+              relocs.push(bytes.len());
+            } else {
+              module_relocs.push(bytes.len());
+            }
+            pos += 1;
+          }
+        },
+        _ => {
+          return Err(SourceError::new(
+            pos,
+            format!(
+              "unexpected character `{}`",
+              src[pos..].chars().next().unwrap()
+            ),
+          ))
+        }
+      }
+    }
+
+    end_reloc(&mut cur_reloc, bytes.len())?;
+    if bytes.len() > usize::from(u16::MAX) {
+      return Err(SourceError::new(0, "patch source is too long"));
+    }
+
+    Ok(Self {
+      bytes,
+      masked,
+      relocs,
+      module_relocs,
+      control_stream: control_stream.finish(),
+      text_pos,
+    })
+  }
+
+  /// Creates an error at the given byte.
+  fn error_at(&self, byte: usize, msg: impl Into<String>) -> SourceError {
+    SourceError::new(self.text_pos.get(byte).copied().unwrap_or(0), msg)
+  }
+
+  /// Checks that the source can be used to find a patch's location.
+  pub fn check_pattern(&self) -> Result<(), SourceError> {
+    match self.module_relocs.first() {
+      Some(&x) => Err(self.error_at(x, "module-relative values can only be used in patch data")),
+      None => Ok(()),
+    }
+  }
+
+  /// Checks that the source can be used as patch data.
+  pub fn check_data(&self) -> Result<(), SourceError> {
+    match self.masked.iter().position(|&x| x) {
+      Some(x) => Err(self.error_at(x, "masked bytes can't be used in patch data")),
+      None => Ok(()),
+    }
+  }
+
+  /// Hashes the expected bytes. Masked bytes are hashed as zero.
+  pub fn hash(&self) -> u32 {
+    self.bytes.iter().fold(0x01000193u32, |hash, x| {
+      (hash ^ *x as u32).wrapping_mul(0x01000193u32)
+    })
+  }
+
+  /// Checks that the bytes are made up of whole instructions and that each
+  /// relocation is a 32-bit displacement or immediate. An instruction starting
+  /// with a masked byte can be any length, but can't contain a relocation.
+  pub fn check_instructions(&self) -> Result<(), SourceError> {
+    let len = self.bytes.len();
+    let masked = &self.masked;
+    let masked_inst_ends = |pos: usize| {
+      let limit = self
+        .relocs
+        .iter()
+        .copied()
+        .find(|&x| x > pos)
+        .unwrap_or(len)
+        .min(pos + 15);
+      pos + 1..=limit
+    };
+
+    // Whether the bytes starting at each position can be split into whole
+    // instructions.
+    let mut valid = vec![false; len + 1];
+    valid[len] = true;
+    for pos in (0..len).rev() {
+      valid[pos] = if masked[pos] {
+        masked_inst_ends(pos).any(|end| valid[end])
+      } else {
+        self.decode_at(pos).is_ok_and(|end| valid[end])
+      };
+    }
+    if valid[0] {
+      return Ok(());
+    }
+
+    // Find the first error to report.
+    let mut pos = 0;
+    while pos < len {
+      pos = if masked[pos] {
+        masked_inst_ends(pos)
+          .find(|&end| valid[end])
+          .unwrap_or_else(|| (pos..len).find(|&x| !masked[x]).unwrap_or(len))
+      } else {
+        self.decode_at(pos)?
+      };
+    }
+    Err(self.error_at(
+      len.saturating_sub(1),
+      "pattern doesn't end on an instruction boundary",
+    ))
+  }
+
+  /// Decodes the instruction at the given position. The instruction must not
+  /// start with a masked byte.
+  fn decode_at(&self, pos: usize) -> Result<usize, SourceError> {
+    let Some(inst) = x86::decode(&self.bytes[pos..]) else {
+      return Err(self.error_at(pos, "invalid or incomplete instruction"));
+    };
+    let end = pos + usize::from(inst.len);
+    let operands = [inst.disp, inst.imm].into_iter().filter(|x| x.size != 0);
+
+    // Masked bytes are decoded as zero, so they can't be anywhere which changes
+    // the instruction's length.
+    let fixed_end = operands
+      .clone()
+      .map(|x| pos + usize::from(x.offset))
+      .min()
+      .unwrap_or(end);
+    if let Some(i) = (pos..fixed_end).find(|&i| self.masked[i]) {
+      return Err(self.error_at(i, "masked byte is part of an instruction's opcode"));
+    }
+    for &reloc in self.relocs.iter().filter(|&&x| pos <= x && x < end) {
+      if !operands
+        .clone()
+        .any(|x| x.size == 4 && pos + usize::from(x.offset) == reloc)
+      {
+        return Err(self.error_at(reloc, "relocation is not a 32-bit operand"));
+      }
+    }
+    Ok(end)
+  }
+}
@@ -0,0 +1,205 @@
+//! A length decoder for 32-bit x86 instructions.
+
+/// The position of an operand within an instruction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Operand {
+  pub offset: u8,
+  pub size: u8,
+}
+
+/// The kind of a relative branch.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Branch {
+  Call,
+  Jmp,
+  /// A conditional jump with the given condition code.
+  Jcc(u8),
+  /// `loop`, `loope`, `loopne` and `jecxz`.
+  Loop,
+}
+
+/// The layout of a decoded instruction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Instruction {
+  /// The instruction's total size in bytes.
+  pub len: u8,
+  /// The memory operand's displacement. Has a size of zero if there isn't one.
+  pub disp: Operand,
+  /// The immediate operand. Has a size of zero if there isn't one.
+  pub imm: Operand,
+  /// Set if the immediate operand is the target of a relative branch.
+  pub branch: Option<Branch>,
+}
+
+const NONE: u8 = 0;
+const IB: u8 = 1;
+const IW: u8 = 2;
+/// A word or dword depending on the operand size.
+const IZ: u8 = 3;
+/// A word followed by a byte. Only used by `enter`.
+const IWB: u8 = 4;
+/// A word or dword depending on the address size.
+const MOFFS: u8 = 5;
+/// A far pointer.
+const PTR: u8 = 6;
+const REL8: u8 = 7;
+/// A relative word or dword depending on the operand size.
+const RELZ: u8 = 8;
+const MODRM: u8 = 0x10;
+const INVALID: u8 = 0xff;
+
+const fn one_byte(op: u8) -> u8 {
+  match op {
+    0x0f => INVALID,
+    0x00..=0x3f => match op & 7 {
+      0..=3 => MODRM,
+      4 => IB,
+      5 => IZ,
+      _ => NONE,
+    },
+    0x40..=0x61 | 0x6c..=0x6f => NONE,
+    0x62 | 0x63 | 0x84..=0x8f | 0xc4 | 0xc5 | 0xd0..=0xd3 | 0xd8..=0xdf | 0xfe | 0xff => MODRM,
+    0x68 | 0xa9 | 0xb8..=0xbf => IZ,
+    0x69 | 0x81 | 0xc7 | 0xf7 => MODRM | IZ,
+    0x6a | 0xa8 | 0xb0..=0xb7 | 0xcd | 0xd4 | 0xd5 | 0xe4..=0xe7 => IB,
+    0x6b | 0x80 | 0x82 | 0x83 | 0xc0 | 0xc1 | 0xc6 | 0xf6 => MODRM | IB,
+    0x70..=0x7f | 0xe0..=0xe3 | 0xeb => REL8,
+    0x9a | 0xea => PTR,
+    0xa0..=0xa3 => MOFFS,
+    0xc2 | 0xca => IW,
+    0xc8 => IWB,
+    0xe8 | 0xe9 => RELZ,
+    _ => NONE,
+  }
+}
+
+const fn two_byte(op: u8) -> u8 {
+  match op {
+    0x04 | 0x0a | 0x0c | 0x24..=0x27 | 0x36 | 0x39 | 0x3b..=0x3f | 0xa6 | 0xa7 => INVALID,
+    0x0f | 0x3a | 0x70..=0x73 | 0xa4 | 0xac | 0xba | 0xc2 | 0xc4..=0xc6 => MODRM | IB,
+    0x05..=0x0b | 0x0e | 0x30..=0x37 | 0x77 | 0xa0..=0xa2 | 0xa8..=0xaa | 0xc8..=0xcf => NONE,
+    0x80..=0x8f => RELZ,
+    _ => MODRM,
+  }
+}
+
+/// Decodes the length and layout of the instruction at the start of the given
+/// bytes. Returns `None` if the instruction is invalid or incomplete.
+pub fn decode(bytes: &[u8]) -> Option<Instruction> {
+  let mut i = 0usize;
+  let mut op_size16 = false;
+  let mut addr_size16 = false;
+  let op = loop {
+    match *bytes.get(i)? {
+      0x66 => op_size16 = true,
+      0x67 => addr_size16 = true,
+      0xf0 | 0xf2 | 0xf3 | 0x26 | 0x2e | 0x36 | 0x3e | 0x64 | 0x65 => {}
+      x => break x,
+    }
+    i += 1;
+  };
+  i += 1;
+
+  let (kind, branch) = if op == 0x0f {
+    let op = *bytes.get(i)?;
+    i += 1;
+    if op == 0x38 || op == 0x3a {
+      // Three byte opcode.
+      bytes.get(i)?;
+      i += 1;
+    }
+    let branch = match op {
+      0x80..=0x8f => Some(Branch::Jcc(op & 0xf)),
+      _ => None,
+    };
+    (two_byte(op), branch)
+  } else {
+    let branch = match op {
+      0x70..=0x7f => Some(Branch::Jcc(op & 0xf)),
+      0xe0..=0xe3 => Some(Branch::Loop),
+      0xe8 => Some(Branch::Call),
+      0xe9 | 0xeb => Some(Branch::Jmp),
+      _ => None,
+    };
+    (one_byte(op), branch)
+  };
+  if kind == INVALID {
+    return None;
+  }
+
+  let mut imm_kind = kind & 0xf;
+  let mut disp = Operand::default();
+  if kind & MODRM != 0 {
+    let modrm = *bytes.get(i)?;
+    i += 1;
+    // Only `test` in group 3 has an immediate.
+    if (op == 0xf6 || op == 0xf7) && modrm & 0x30 != 0 {
+      imm_kind = NONE;
+    }
+    let md = modrm >> 6;
+    let rm = modrm & 7;
+    let disp_size = match md {
+      3 => 0,
+      _ if addr_size16 => match (md, rm) {
+        (0, 6) => 2,
+        (0, _) => 0,
+        (1, _) => 1,
+        _ => 2,
+      },
+      _ => {
+        let base = if rm == 4 {
+          let sib = *bytes.get(i)?;
+          i += 1;
+          sib & 7
+        } else {
+          rm
+        };
+        match md {
+          0 if base == 5 => 4,
+          0 => 0,
+          1 => 1,
+          _ => 4,
+        }
+      }
+    };
+    disp = Operand { offset: i as u8, size: disp_size };
+    i += usize::from(disp_size);
+  }
+
+  let z = if op_size16 { 2 } else { 4 };
+  let imm_size = match imm_kind {
+    IB | REL8 => 1,
+    IW => 2,
+    IZ | RELZ => z,
+    IWB => 3,
+    MOFFS if addr_size16 => 2,
+    MOFFS => 4,
+    PTR => z + 2,
+    _ => 0,
+  };
+  let len = i + imm_size;
+  if len > 15 || len > bytes.len() {
+    return None;
+  }
+  Some(Instruction {
+    len: len as u8,
+    disp,
+    imm: Operand { offset: i as u8, size: imm_size as u8 },
+    branch: if matches!(imm_kind, REL8 | RELZ) {
+      branch
+    } else {
+      None
+    },
+  })
+}
+
+/// Checks whether the bytes consist of only whole instructions.
+pub fn is_whole(mut bytes: &[u8]) -> bool {
+  while !bytes.is_empty() {
+    match decode(bytes) {
+      Some(inst) => bytes = &bytes[usize::from(inst.len)..],
+      None => return false,
+    }
+  }
+  true
+}
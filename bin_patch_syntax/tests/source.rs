@@ -0,0 +1,81 @@
+use bin_patch_syntax::source::{Source, SourceError};
+
+fn parse(src: &str) -> Source {
+  Source::parse(src).unwrap_or_else(|e| panic!("failed to parse `{src}`: {}", e.msg))
+}
+
+fn parse_error(src: &str) -> SourceError {
+  match Source::parse(src) {
+    Ok(_) => panic!("parsed `{src}`"),
+    Err(e) => e,
+  }
+}
+
+#[test]
+fn comments() {
+  let src = parse(
+    "; A comment on its own line.
+    8b45 08 ; A trailing comment with hex digits: ff $ xx
+    ;55
+    8bec;",
+  );
+  assert_eq!(src.bytes, [0x8b, 0x45, 0x08, 0x8b, 0xec]);
+  assert_eq!(parse("; Only a comment").bytes, []);
+  // A comment ends a relocated value.
+  assert_eq!(parse("a1 $78563412; Comment").relocs, [1]);
+  assert_eq!(
+    parse_error("a1 $7856; Comment").msg,
+    "incorrect relocation size"
+  );
+}
+
+#[test]
+fn labels() {
+  let src = parse(
+    "start: 55 8bec
+    body:8b45 08
+    a1: $78563412
+    _end:",
+  );
+  assert_eq!(
+    src.bytes,
+    [0x55, 0x8b, 0xec, 0x8b, 0x45, 0x08, 0x78, 0x56, 0x34, 0x12]
+  );
+  assert_eq!(src.relocs, [6]);
+  // Hex digits without a `:` are still bytes.
+  assert_eq!(parse("ab cd").bytes, [0xab, 0xcd]);
+  // Labels can't start with a digit.
+  assert_eq!(parse_error("1a: 90").msg, "unexpected character `:`");
+}
+
+#[test]
+fn duplicate_labels() {
+  let e = parse_error("a: 90 b: 90\n  a: 90");
+  assert_eq!(e.msg, "duplicate label `a`");
+  assert_eq!(e.pos, 14);
+  // Labels are case sensitive.
+  assert!(Source::parse("a: 90 A: 90").is_ok());
+}
+
+#[test]
+fn line_col() {
+  let src = "55\n  8b zz";
+  let e = parse_error(src);
+  assert_eq!(e.msg, "unexpected character `z`");
+  assert_eq!(e.line_col(src), (2, 6));
+
+  let src = "; Comment\r\n\tstart: 9";
+  assert_eq!(parse_error(src).line_col(src), (2, 9));
+
+  // Columns count characters rather than bytes.
+  let src = "90 ; Ünïcödé\n é";
+  assert_eq!(parse_error(src).line_col(src), (2, 2));
+
+  // Errors found after parsing point at the offending byte.
+  let src = "55\n  8b 45 xx";
+  let e = parse(src).check_data().unwrap_err();
+  assert_eq!(e.line_col(src), (2, 9));
+  let src = "55\n  8b 45";
+  let e = parse(src).check_instructions().unwrap_err();
+  assert_eq!(e.line_col(src), (2, 3));
+}
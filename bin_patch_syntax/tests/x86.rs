@@ -0,0 +1,118 @@
+use bin_patch_syntax::x86::{decode, is_whole, Branch, Operand};
+
+fn hex(s: &str) -> Vec<u8> {
+  s.split_whitespace()
+    .map(|x| u8::from_str_radix(x, 16).unwrap())
+    .collect()
+}
+
+/// Gets the offset and size of an operand, or zeros if there isn't one.
+fn operand(x: Operand) -> (u8, u8) {
+  if x.size == 0 {
+    (0, 0)
+  } else {
+    (x.offset, x.size)
+  }
+}
+
+/// The bytes, length, displacement, immediate and branch kind.
+type Case = (&'static str, u8, (u8, u8), (u8, u8), Option<Branch>);
+
+#[test]
+fn decode_valid() {
+  #[rustfmt::skip]
+  let cases: &[Case] = &[
+    ("90", 1, (0, 0), (0, 0), None),
+    ("55", 1, (0, 0), (0, 0), None),
+    ("8b ec", 2, (0, 0), (0, 0), None),
+    ("8b 45 08", 3, (2, 1), (0, 0), None),
+    ("8b 80 78 56 34 12", 6, (2, 4), (0, 0), None),
+    ("8b 05 78 56 34 12", 6, (2, 4), (0, 0), None),
+    ("8b 04 24", 3, (0, 0), (0, 0), None),
+    ("8b 44 24 04", 4, (3, 1), (0, 0), None),
+    ("8b 04 25 78 56 34 12", 7, (3, 4), (0, 0), None),
+    ("67 8b 06 34 12", 5, (3, 2), (0, 0), None),
+    ("67 8b 46 08", 4, (3, 1), (0, 0), None),
+    ("67 8b 07", 3, (0, 0), (0, 0), None),
+    ("b8 01 00 00 00", 5, (0, 0), (1, 4), None),
+    ("66 b8 01 00", 4, (0, 0), (2, 2), None),
+    ("83 c4 04", 3, (0, 0), (2, 1), None),
+    ("c7 45 f8 01 00 00 00", 7, (2, 1), (3, 4), None),
+    ("66 c7 45 f8 01 00", 6, (3, 1), (4, 2), None),
+    ("f6 c1 01", 3, (0, 0), (2, 1), None),
+    ("f6 d9", 2, (0, 0), (0, 0), None),
+    ("f7 c1 01 00 00 00", 6, (0, 0), (2, 4), None),
+    ("f7 d8", 2, (0, 0), (0, 0), None),
+    ("a1 78 56 34 12", 5, (0, 0), (1, 4), None),
+    ("67 a1 78 56", 4, (0, 0), (2, 2), None),
+    ("64 a1 00 00 00 00", 6, (0, 0), (2, 4), None),
+    ("c2 08 00", 3, (0, 0), (1, 2), None),
+    ("c8 10 00 00", 4, (0, 0), (1, 3), None),
+    ("9a 78 56 34 12 08 00", 7, (0, 0), (1, 6), None),
+    ("f3 a5", 2, (0, 0), (0, 0), None),
+    ("0f a2", 2, (0, 0), (0, 0), None),
+    ("0f b6 45 08", 4, (3, 1), (0, 0), None),
+    ("0f 1f 44 00 00", 5, (4, 1), (0, 0), None),
+    ("0f 38 00 c1", 4, (0, 0), (0, 0), None),
+    ("0f 3a 0f c1 08", 5, (0, 0), (4, 1), None),
+    ("e8 00 00 00 00", 5, (0, 0), (1, 4), Some(Branch::Call)),
+    ("66 e8 00 00", 4, (0, 0), (2, 2), Some(Branch::Call)),
+    ("e9 00 00 00 00", 5, (0, 0), (1, 4), Some(Branch::Jmp)),
+    ("eb 10", 2, (0, 0), (1, 1), Some(Branch::Jmp)),
+    ("74 10", 2, (0, 0), (1, 1), Some(Branch::Jcc(4))),
+    ("0f 85 10 00 00 00", 6, (0, 0), (2, 4), Some(Branch::Jcc(5))),
+    ("e2 fe", 2, (0, 0), (1, 1), Some(Branch::Loop)),
+    ("e3 10", 2, (0, 0), (1, 1), Some(Branch::Loop)),
+    // Indirect branches have no relative target.
+    ("ff 15 78 56 34 12", 6, (2, 4), (0, 0), None),
+    ("ff e0", 2, (0, 0), (0, 0), None),
+  ];
+  for &(bytes, len, disp, imm, branch) in cases {
+    let mut bytes = hex(bytes);
+    // Trailing bytes aren't part of the instruction.
+    bytes.extend_from_slice(&[0xcc; 4]);
+    let inst = decode(&bytes).unwrap_or_else(|| panic!("failed to decode `{bytes:02x?}`"));
+    assert_eq!(
+      (inst.len, operand(inst.disp), operand(inst.imm), inst.branch),
+      (len, disp, imm, branch),
+      "`{bytes:02x?}`"
+    );
+  }
+}
+
+#[test]
+fn decode_invalid() {
+  let cases = [
+    "",
+    "66",
+    "0f",
+    "0f 38",
+    "0f 04",
+    "0f 0a",
+    "8b",
+    "8b 45",
+    "8b 04",
+    "8b 80 78 56 34",
+    "e8 00 00 00",
+    "66 c7 45 f8 01",
+    "74",
+  ];
+  for bytes in cases {
+    assert_eq!(decode(&hex(bytes)), None, "`{bytes}`");
+  }
+
+  // Instructions are limited to 15 bytes.
+  let mut bytes = vec![0x66; 14];
+  bytes.push(0x90);
+  assert_eq!(decode(&bytes).map(|x| x.len), Some(15));
+  bytes.insert(0, 0x66);
+  assert_eq!(decode(&bytes), None);
+}
+
+#[test]
+fn whole() {
+  assert!(is_whole(&[]));
+  assert!(is_whole(&hex("55 8b ec 83 ec 08")));
+  assert!(!is_whole(&hex("55 8b ec 83 ec")));
+  assert!(!is_whole(&hex("0f 0a")));
+}
@@ -4,6 +4,7 @@ use crate::{
   GAME_FPS,
 };
 use core::sync::atomic::{AtomicBool, Ordering::Relaxed};
+use parking_lot::Mutex;
 use std::{env, fs};
 
 pub(crate) struct Config {
@@ -12,6 +13,7 @@ pub(crate) struct Config {
   pub features: AtomicFeatures,
   pub reapply_patches: AtomicBool,
   pub integrity_checks: AtomicBool,
+  pub patch_file: Mutex<Option<String>>,
 }
 impl Config {
   pub const fn new() -> Self {
@@ -21,6 +23,7 @@ impl Config {
       features: AtomicFeatures::ALL,
       reapply_patches: AtomicBool::new(true),
       integrity_checks: AtomicBool::new(true),
+      patch_file: Mutex::new(None),
     }
   }
 
@@ -76,6 +79,9 @@ impl Config {
               Ok(v) => self.integrity_checks.store(v, Relaxed),
               Err(_) => log!("Error parsing d2fps.ini: line `{i}`: unknown value `{v}`"),
             },
+            "patch-file" => {
+              *self.patch_file.lock() = (!v.is_empty()).then(|| v.into());
+            }
 
             k => {
               log!("Error parsing d2fps.ini: line `{i}`: unknown key `{k}`");
@@ -123,12 +129,14 @@ impl Config {
       \n  bg-fps: {}\
       \n  features: {}\
       \n  reapply-patches: {}\
-      \n  integrity-checks: {}",
+      \n  integrity-checks: {}\
+      \n  patch-file: {}",
       self.fps.load_relaxed(),
       self.bg_fps.load_relaxed(),
       self.features.load_relaxed(),
       self.reapply_patches.load(Relaxed),
       self.integrity_checks.load(Relaxed),
+      self.patch_file.lock().as_deref().unwrap_or(""),
     );
   }
 }
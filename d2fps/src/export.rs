@@ -0,0 +1,218 @@
+//! Exports the patches for the game as static IPS or BPS patch files for
+//! setups which can't inject `d2fps.dll` into the game.
+//!
+//! The hook functions are part of `d2fps.dll` and can't be embedded in the
+//! game's files. Instead each hook is replaced by a stub which loads the
+//! library the first time it's called. The library must stay next to the game
+//! and must be the same build which exported the patches.
+
+use crate::{
+  features::{FeatureId, FeaturePatches, Features},
+  hooks::game_patches,
+  util::{message_box_error, message_box_info, module_file_name},
+  DLL_MODULE, INSTANCE,
+};
+use bin_patch::{write_bps, write_ips, ExportError, PatchedFile};
+use core::{
+  ffi::{c_char, CStr},
+  fmt,
+  sync::atomic::Ordering::Relaxed,
+};
+use d2interface as d2;
+use std::{fs, path::Path};
+use windows_sys::Win32::{
+  Foundation::{HMODULE, HWND},
+  System::Memory::{VirtualProtect, PAGE_EXECUTE_READWRITE},
+};
+use xxhash_rust::xxh3::xxh3_64;
+
+/// The name of the export the stubs call to find a hook.
+const RESOLVER: &str = "ResolveHook";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PatchFormat {
+  Ips,
+  Bps,
+}
+impl PatchFormat {
+  pub fn from_name(name: &str) -> Option<Self> {
+    if name.eq_ignore_ascii_case("ips") {
+      Some(Self::Ips)
+    } else if name.eq_ignore_ascii_case("bps") {
+      Some(Self::Bps)
+    } else {
+      None
+    }
+  }
+
+  pub const fn extension(self) -> &'static str {
+    match self {
+      Self::Ips => "ips",
+      Self::Bps => "bps",
+    }
+  }
+}
+
+pub enum ExportModuleError {
+  /// The module's file couldn't be parsed.
+  InvalidModule,
+  /// The stubs which load the hooks couldn't be added.
+  Loader(ExportError),
+  Patch {
+    feature: FeatureId,
+    offset: usize,
+    error: ExportError,
+  },
+  /// There isn't enough room in the module's headers for the hook section.
+  NoSectionSpace,
+  /// The patched file can't be represented by the patch format.
+  UnsupportedFormat,
+}
+impl fmt::Display for ExportModuleError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::InvalidModule => f.write_str("failed to parse the module's file"),
+      Self::Loader(error) => write!(f, "failed to add the hook loader: {error}"),
+      Self::Patch { feature, offset, error } => {
+        write!(
+          f,
+          "failed to export `{feature}` patch at {offset:#x}: {error}"
+        )
+      }
+      Self::NoSectionSpace => f.write_str("no room to add the hook section"),
+      Self::UnsupportedFormat => {
+        f.write_str("the patched file can't be stored in the patch format")
+      }
+    }
+  }
+}
+
+/// Builds a patch file for a single module containing the patches of every
+/// selected feature. Hooks are loaded from the library with the given file
+/// name and base address.
+pub fn export_module_patches(
+  patches: &FeaturePatches,
+  features: Features,
+  module: d2::Module,
+  file: &[u8],
+  format: PatchFormat,
+  (library, library_base): (&str, usize),
+) -> Result<Vec<u8>, ExportModuleError> {
+  let mut patched = PatchedFile::new(file.to_vec()).ok_or(ExportModuleError::InvalidModule)?;
+  patched
+    .add_lazy_loader(library, RESOLVER, library_base)
+    .map_err(ExportModuleError::Loader)?;
+  for (feature, mod_patches) in patches.iter() {
+    if !features.intersects(feature.as_flag()) || !features.contains(feature.prereqs()) {
+      continue;
+    }
+    for p in mod_patches
+      .iter()
+      .filter(|p| p.module == module)
+      .flat_map(|p| p.patches)
+    {
+      patched.apply(p).map_err(|error| ExportModuleError::Patch {
+        feature,
+        offset: p.offset,
+        error,
+      })?;
+    }
+  }
+  let target = patched.finish().ok_or(ExportModuleError::NoSectionSpace)?;
+  match format {
+    PatchFormat::Ips => write_ips(file, &target).ok_or(ExportModuleError::UnsupportedFormat),
+    PatchFormat::Bps => Ok(write_bps(
+      file,
+      &target,
+      &format!("d2fps {} {module}", env!("CARGO_PKG_VERSION")),
+    )),
+  }
+}
+
+/// Writes a patch file to the directory for each module changed by the
+/// features enabled in `d2fps.ini`. Returns the written files.
+fn export_patches(format: PatchFormat, dir: &Path) -> Result<Vec<String>, String> {
+  INSTANCE.config.load_config();
+  let features = INSTANCE.config.features.load_relaxed();
+  let patches = game_patches(|| fs::read("game.exe").ok().map(|x| xxh3_64(&x)))?.patches;
+  let library_module = DLL_MODULE.load(Relaxed);
+  let library_path =
+    module_file_name(library_module).ok_or("failed to get the library's file name")?;
+  let library = Path::new(&library_path)
+    .file_name()
+    .and_then(|x| x.to_str())
+    .ok_or("failed to get the library's file name")?;
+
+  let mut modules = Vec::new();
+  for (feature, mod_patches) in patches.iter() {
+    if features.intersects(feature.as_flag()) {
+      for p in mod_patches {
+        if !modules.contains(&p.module) {
+          modules.push(p.module);
+        }
+      }
+    }
+  }
+
+  let mut written = Vec::with_capacity(modules.len());
+  for module in modules {
+    let file = fs::read(module.as_str()).map_err(|e| format!("failed to read `{module}`: {e}"))?;
+    let patch = export_module_patches(
+      patches,
+      features,
+      module,
+      &file,
+      format,
+      (library, library_module as usize),
+    )
+    .map_err(|e| format!("{module}: {e}"))?;
+    let path = dir.join(format!("{module}.{}", format.extension()));
+    fs::write(&path, patch).map_err(|e| format!("failed to write `{}`: {e}", path.display()))?;
+    written.push(path.display().to_string());
+  }
+  Ok(written)
+}
+
+/// Exports the patches for the game in the current directory. Run as
+/// `rundll32 d2fps.dll,ExportPatches <ips|bps> [output directory]`.
+#[no_mangle]
+pub unsafe extern "system" fn ExportPatches(_: HWND, _: HMODULE, cmd_line: *const c_char, _: i32) {
+  let args = if cmd_line.is_null() {
+    String::new()
+  } else {
+    CStr::from_ptr(cmd_line).to_string_lossy().into_owned()
+  };
+  let (format, dir) = args.trim().split_once(' ').unwrap_or((args.trim(), ""));
+  let Some(format) = PatchFormat::from_name(format) else {
+    message_box_error("Usage: rundll32 d2fps.dll,ExportPatches <ips|bps> [output directory]");
+    return;
+  };
+  let dir = match dir.trim() {
+    "" => ".",
+    dir => dir,
+  };
+  match export_patches(format, Path::new(dir)) {
+    Ok(files) if files.is_empty() => message_box_info("No patches to export"),
+    Ok(files) => message_box_info(&format!("Exported patches to:\n{}", files.join("\n"))),
+    Err(e) => message_box_error(&format!("Failed to export patches: {e}")),
+  }
+}
+
+/// Called by the stubs in the exported patches the first time a hook is used.
+/// Attaches to the game without applying any patches, then points the stub at
+/// the hook. Returns the hook's address.
+#[no_mangle]
+unsafe extern "stdcall" fn ResolveHook(stub: usize, rva: u32) -> usize {
+  INSTANCE.static_patches.store(true, Relaxed);
+  crate::Init();
+  let target = (DLL_MODULE.load(Relaxed) as usize).wrapping_add(rva as usize);
+  // The stub's jump is aligned, so other threads running the stub will see
+  // either the old or the new target.
+  let jmp = (stub + 4) as *mut u32;
+  let mut prev = 0;
+  if VirtualProtect(jmp.cast(), 4, PAGE_EXECUTE_READWRITE, &mut prev) != 0 {
+    jmp.write_volatile(target.wrapping_sub(stub + 8) as u32);
+    VirtualProtect(jmp.cast(), 4, prev, &mut prev);
+  }
+  target
+}
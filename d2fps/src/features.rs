@@ -30,6 +30,19 @@ impl FeatureId {
     }
   }
 
+  /// Gets a feature by the name used in the config file.
+  pub fn from_config_name(name: &str) -> Option<Self> {
+    Some(match name {
+      "menu-fps" => Self::MenuFps,
+      "game-fps" => Self::GameFps,
+      "motion-smoothing" => Self::MotionSmoothing,
+      "arcane-bg" => Self::ArcaneBg,
+      "anim-rate-fixes" => Self::AnimRate,
+      "weather-smoothing" => Self::Weather,
+      _ => return None,
+    })
+  }
+
   pub fn iter() -> impl ExactSizeIterator<Item = FeatureId> {
     (0u8..6u8).map(|x| unsafe { transmute(x) })
   }
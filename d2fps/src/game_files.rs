@@ -0,0 +1,58 @@
+//! Checks the patch tables against copies of the game's files.
+//!
+//! Set `D2FPS_GAME_FILES` to a directory containing a subdirectory for each
+//! version named after it (e.g. `v1.10`) which holds that version's modules.
+//! Versions without a directory are skipped, and the test does nothing when
+//! the variable isn't set.
+
+use crate::hooks::version_patches;
+use bin_patch::ModuleImage;
+use d2interface as d2;
+use std::{env, fs, path::Path};
+
+/// Loads a module file from disk. Returns `None` if the file is missing or
+/// isn't a PE image.
+fn load_module(dir: &Path, module: d2::Module) -> Option<ModuleImage<Vec<u8>>> {
+  ModuleImage::from_pe_file(&fs::read(dir.join(module.as_str())).ok()?)
+}
+
+#[test]
+fn patches_match_game_files() {
+  let Some(root) = env::var_os("D2FPS_GAME_FILES") else {
+    return;
+  };
+  let mut errors = Vec::new();
+  for (version, patches) in version_patches() {
+    let dir = Path::new(&root).join(version);
+    if !dir.is_dir() {
+      continue;
+    }
+    let mut images: Vec<(d2::Module, Option<ModuleImage<Vec<u8>>>)> = Vec::new();
+    for (feature, mod_patches) in patches.iter() {
+      for mod_patches in mod_patches {
+        let module = mod_patches.module;
+        let idx = match images.iter().position(|&(m, _)| m == module) {
+          Some(idx) => idx,
+          None => {
+            let image = load_module(&dir, module);
+            if image.is_none() {
+              errors.push(format!("{version}: failed to load `{module}`"));
+            }
+            images.push((module, image));
+            images.len() - 1
+          }
+        };
+        let Some(image) = &images[idx].1 else {
+          continue;
+        };
+        // Each image is at its preferred base address.
+        for p in mod_patches.patches {
+          if let Err(e) = p.check(image, 0) {
+            errors.push(format!("{version} {feature} {module}: {e}"));
+          }
+        }
+      }
+    }
+  }
+  assert!(errors.is_empty(), "{}", errors.join("\n"));
+}
@@ -1,8 +1,9 @@
 use crate::{
-  features::{FeaturePatches, Features, ModulePatches},
-  util::{hash_module_file, read_file_version, FileVersion},
+  features::{FeatureId, FeaturePatches, Features, ModulePatches},
+  util::{hash_module_file, read_file_version},
   InstanceSync, GAME_FPS, INSTANCE,
 };
+use bin_patch::{parse_patches, AppliedPatchSet, Hook, LoadedModule, PatchError, PatchSet};
 use core::{
   hash::Hash,
   mem::{replace, take},
@@ -12,7 +13,7 @@ use core::{
 use d2interface::{self as d2, IntoSys};
 use fxhash::FxHashSet as HashSet;
 use num::{M2d, WrappingAdd, WrappingFrom, WrappingInto, WrappingSub};
-use std::collections::hash_map::Entry;
+use std::{collections::hash_map::Entry, fs};
 use windows_sys::{
   w,
   Win32::{
@@ -27,9 +28,11 @@ use windows_sys::{
 
 mod v100;
 mod v101;
+mod v101_v103;
 mod v102;
 mod v103;
 mod v104b;
+mod v104b_v106b;
 mod v105;
 mod v106a;
 mod v106b;
@@ -52,61 +55,221 @@ const GAME_EXE: *const u16 = w!("game.exe");
 
 struct Hooks {
   patches: FeaturePatches,
-  addresses: d2::Addresses,
-  base_addresses: d2::BaseAddresses,
-  load_modules: fn() -> Option<d2::Modules>,
+  /// Gets a hook function by name for patches loaded from a file.
+  named_hook: fn(&str) -> Option<Hook>,
   helper_fns: HelperFns,
 }
 impl Hooks {
   const UNKNOWN: &'static Hooks = &Hooks {
     patches: FeaturePatches::empty(),
-    addresses: d2::Addresses::ZERO,
-    base_addresses: d2::BaseAddresses::ZERO,
-    load_modules: d2::Modules::load_split_modules,
+    named_hook: |_| None,
     helper_fns: HelperFns::INIT,
   };
 
-  fn from_game_file_version(version: FileVersion) -> (&'static str, &'static Hooks, bool) {
-    match (version.ms, version.ls) {
-      (0x0001_0000, 0x0000_0001) => match hash_module_file(unsafe { GetModuleHandleW(GAME_EXE) }) {
-        Some(0x5215437ecc8b67b9) => ("v1.00", &v100::HOOKS, false),
-        Some(0x1b093efaa009e78b) => ("v1.01", &v101::HOOKS, false),
-        _ => ("unknown", Self::UNKNOWN, false),
-      },
-      (0x0001_0000, 0x0002_0000) => ("v1.02", &v102::HOOKS, false),
-      (0x0001_0000, 0x0003_0000) => ("v1.03", &v103::HOOKS, false),
-      (0x0001_0000, 0x0004_0001) => ("v1.04b", &v104b::HOOKS, false),
-      (0x0001_0000, 0x0004_0002) => ("v1.04c", &v104b::HOOKS, false),
-      (0x0001_0000, 0x0005_0000) => ("v1.05a", &v105::HOOKS, false),
-      (0x0001_0000, 0x0005_0001) => ("v1.05b", &v105::HOOKS, false),
-      (0x0001_0000, 0x0006_0000) => match hash_module_file(unsafe { GetModuleHandleW(GAME_EXE) }) {
-        Some(0x73645dbfe51df9ae) => ("v1.06a", &v106a::HOOKS, false),
-        Some(0x62fea87b064aec9e) => ("v1.06b", &v106b::HOOKS, false),
-        _ => ("unknown", Self::UNKNOWN, false),
-      },
-      (0x0001_0000, 0x0007_0000) => ("v1.07", &v107::HOOKS, true),
-      (0x0001_0000, 0x0008_001c) => ("v1.08", &v108::HOOKS, true),
-      (0x0001_0000, 0x0009_0013) => ("v1.09a", &v109a::HOOKS, true),
-      (0x0001_0000, 0x0009_0014) => ("v1.09b", &v109a::HOOKS, true),
-      (0x0001_0000, 0x0009_0016) => ("v1.09d", &v109d::HOOKS, true),
-      // (0x0001_0000, 0x000a_0009) => ("1.10b", &v110b::HOOKS, true),
-      // (0x0001_0000, 0x000a_000a) => ("1.10s", &v110s::HOOKS, true),
-      (0x0001_0000, 0x000a_0027) => ("v1.10", &v110::HOOKS, true),
-      (0x0001_0000, 0x000b_002d) => ("v1.11a", &v111a::HOOKS, true),
-      (0x0001_0000, 0x000b_002e) => ("v1.11b", &v111b::HOOKS, true),
-      (0x0001_0000, 0x000c_0031) => ("v1.12", &v112::HOOKS, true),
-      // (0x0001_0000, 0x000d_0037) => ("1.13a", &v113a::HOOKS, true),
-      (0x0001_0000, 0x000d_003c) => ("v1.13c", &v113c::HOOKS, true),
-      (0x0001_0000, 0x000d_0040) => ("v1.13d", &v113d::HOOKS, true),
-      (0x0001_000e, 0x0000_0040) => ("v1.14a", &v114a::HOOKS, true),
-      (0x0001_000e, 0x0001_0044) => ("v1.14b", &v114b::HOOKS, true),
-      (0x0001_000e, 0x0002_0046) => ("v1.14c", &v114c::HOOKS, true),
-      (0x0001_000e, 0x0003_0047) => ("v1.14d", &v114d::HOOKS, true),
-      _ => ("unknown", Self::UNKNOWN, false),
+  /// Gets the hooks for a game version.
+  fn for_version(version: d2::GameVersion) -> &'static Hooks {
+    use d2::GameVersion as V;
+    match version {
+      V::V100 => &v100::HOOKS,
+      V::V101 => &v101::HOOKS,
+      V::V102 => &v102::HOOKS,
+      V::V103 => &v103::HOOKS,
+      V::V104b | V::V104c => &v104b::HOOKS,
+      V::V105a | V::V105b => &v105::HOOKS,
+      V::V106a => &v106a::HOOKS,
+      V::V106b => &v106b::HOOKS,
+      V::V107 => &v107::HOOKS,
+      V::V108 => &v108::HOOKS,
+      V::V109a | V::V109b => &v109a::HOOKS,
+      V::V109d => &v109d::HOOKS,
+      V::V110 => &v110::HOOKS,
+      V::V111a => &v111a::HOOKS,
+      V::V111b => &v111b::HOOKS,
+      V::V112 => &v112::HOOKS,
+      V::V113c => &v113c::HOOKS,
+      V::V113d => &v113d::HOOKS,
+      V::V114a => &v114a::HOOKS,
+      V::V114b => &v114b::HOOKS,
+      V::V114c => &v114c::HOOKS,
+      V::V114d => &v114d::HOOKS,
     }
   }
 }
 
+/// Gets the patches used by each supported game version.
+#[cfg(test)]
+pub fn version_patches() -> impl Iterator<Item = (&'static str, &'static FeaturePatches)> {
+  d2::GameVersion::ALL
+    .iter()
+    .map(|&v| (v.name(), &Hooks::for_version(v).patches))
+}
+
+/// Gets the hook function with the given name. Hooks which depend on the layout
+/// of the game's entities use the given type.
+fn named_hook<E: Entity>(name: &str) -> Option<Hook> {
+  Some(match name {
+    "draw_game" => Hook::c(draw_game::<E>),
+    "draw_game_paused" => Hook::c(draw_game_paused),
+    "draw_arcane_bg" => Hook::c(draw_arcane_bg),
+    "game_loop_sleep_hook" => Hook::c(game_loop_sleep_hook),
+    "entity_iso_xpos" => Hook::std1(entity_iso_xpos::<E>),
+    "entity_iso_ypos" => Hook::std1(entity_iso_ypos::<E>),
+    "entity_linear_xpos" => Hook::std1(entity_linear_xpos::<E>),
+    "entity_linear_ypos" => Hook::std1(entity_linear_ypos::<E>),
+    "draw_menu_100_asm_stub" => Hook::c(v100::draw_menu_100_asm_stub),
+    "draw_menu_110_asm_stub" => Hook::c(v110::draw_menu_110_asm_stub),
+    "draw_menu_114d_asm_stub" => Hook::c(v114d::draw_menu_114d_asm_stub),
+    "update_menu_char_frame_100_asm_stub" => Hook::c(v100::update_menu_char_frame_100_asm_stub),
+    "update_menu_char_frame_110_asm_stub" => Hook::c(v110::update_menu_char_frame_110_asm_stub),
+    "update_menu_char_frame_111_asm_stub" => Hook::c(v111a::update_menu_char_frame_111_asm_stub),
+    "update_menu_char_frame_114a_asm_stub" => Hook::c(v114a::update_menu_char_frame_114a_asm_stub),
+    "intercept_teleport_100_asm_stub" => Hook::c(v100::intercept_teleport_100_asm_stub),
+    "intercept_teleport_110_asm_stub" => Hook::c(v110::intercept_teleport_110_asm_stub),
+    "intercept_teleport_111_asm_stub" => Hook::c(v111a::intercept_teleport_111_asm_stub),
+    "intercept_teleport_114c_asm_stub" => Hook::c(v114c::intercept_teleport_114c_asm_stub),
+    "should_update_cursor_100_asm_stub" => Hook::c(v100::should_update_cursor_100_asm_stub),
+    "should_update_cursor_111_asm_stub" => Hook::c(v111a::should_update_cursor_111_asm_stub),
+    "draw_arcane_bg_100_asm_stub" => Hook::c(v100::draw_arcane_bg_100_asm_stub),
+    "move_summit_cloud_114a_asm_stub" => Hook::c(v114a::move_summit_cloud_114a_asm_stub),
+    "summit_cloud_move_amount_107" => Hook::detour(v107::summit_cloud_move_amount_107),
+    "summit_cloud_move_amount_110" => Hook::detour(v110::summit_cloud_move_amount_110),
+    "summit_cloud_move_amount_111" => Hook::detour(v111a::summit_cloud_move_amount_111),
+    _ => return None,
+  })
+}
+
+/// Parses a number written in either hex with a `0x` prefix, or decimal.
+fn parse_number(s: &str) -> Option<usize> {
+  match s.strip_prefix("0x") {
+    Some(s) => usize::from_str_radix(s, 16).ok(),
+    None => s.parse().ok(),
+  }
+}
+
+/// A section of a patch file.
+enum Section {
+  /// Overrides for the game version's addresses.
+  Addresses,
+  /// Overrides for the base address of each module.
+  BaseAddresses,
+  /// The patches for a feature in a single module.
+  Patches(FeatureId, d2::Module, String),
+}
+
+/// Loads a patch file. The file starts with the game version whose hooks and
+/// addresses are used, followed by a section for each feature and module. The
+/// `addresses` and `base-addresses` sections replace the version's addresses
+/// for builds which have moved them.
+///
+/// ```text
+/// version = v1.10
+/// [addresses]
+/// player = 0x11c3d0
+/// draw_menu = #10019
+/// [base-addresses]
+/// D2Client.dll = 0x6fab0000
+/// [menu-fps D2Win.dll]
+/// 0xd00c call_c draw_menu_110_asm_stub
+///   ffd5
+///   8bf0
+/// ```
+fn load_patch_file(path: &str) -> Result<GamePatches, String> {
+  let file = fs::read_to_string(path).map_err(|e| format!("failed to read `{path}`: {e}"))?;
+  let mut version = None;
+  let mut addresses = Vec::new();
+  let mut base_addresses = Vec::new();
+  // The patches are parsed once the game version is known.
+  let mut sections: Vec<Section> = Vec::new();
+  for (i, line) in file.lines().enumerate().map(|(i, x)| (i + 1, x)) {
+    let content = line.split_once(';').map_or(line, |(x, _)| x).trim_end();
+    if let Some(header) = content.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+      let mut words = header.split_whitespace();
+      let section = match (words.next(), words.next(), words.next()) {
+        (Some("addresses"), None, None) => Section::Addresses,
+        (Some("base-addresses"), None, None) => Section::BaseAddresses,
+        (Some(feature), Some(module), None) => {
+          let Some(feature) = FeatureId::from_config_name(feature) else {
+            return Err(format!("line {i}: unknown feature `{feature}`"));
+          };
+          let Some(module) = d2::Module::from_file_name(module) else {
+            return Err(format!("line {i}: unknown module `{module}`"));
+          };
+          // Pad the source so errors report the line number within the file.
+          Section::Patches(feature, module, "\n".repeat(i))
+        }
+        _ => return Err(format!("line {i}: expected `[feature module]`")),
+      };
+      sections.push(section);
+    } else if let Some(Section::Patches(_, _, src)) = sections.last_mut() {
+      src.push_str(line);
+      src.push('\n');
+    } else if content.trim().is_empty() {
+      continue;
+    } else if let Some((k, v)) = content.split_once('=') {
+      let (k, v) = (k.trim(), v.trim());
+      match sections.last() {
+        None if k == "version" => version = Some(v),
+        None => return Err(format!("line {i}: unknown key `{k}`")),
+        Some(Section::Addresses) => {
+          let value = match v.strip_prefix('#') {
+            Some(x) => x.parse().ok().map(d2::AddressValue::Ordinal),
+            None => parse_number(v).map(d2::AddressValue::Offset),
+          };
+          let Some(value) = value else {
+            return Err(format!("line {i}: invalid address `{v}`"));
+          };
+          addresses.push((i, k, value));
+        }
+        Some(Section::BaseAddresses) => {
+          let Some(module) = d2::Module::from_file_name(k) else {
+            return Err(format!("line {i}: unknown module `{k}`"));
+          };
+          let Some(value) = parse_number(v) else {
+            return Err(format!("line {i}: invalid address `{v}`"));
+          };
+          base_addresses.push((module, value));
+        }
+        Some(Section::Patches(..)) => unreachable!(),
+      }
+    } else {
+      return Err(format!("line {i}: expected `key = value` or a section"));
+    }
+  }
+
+  let version = version.ok_or("missing game version")?;
+  let version = d2::GameVersion::from_name(version)
+    .ok_or_else(|| format!("unknown game version `{version}`"))?;
+  let hooks = Hooks::for_version(version);
+
+  let mut patch_addresses = version.addresses().clone();
+  for (i, name, value) in addresses {
+    if !patch_addresses.set(name, value) {
+      return Err(format!(
+        "line {i}: can't set address `{name}` to `{value:?}`"
+      ));
+    }
+  }
+  let mut patch_base_addresses = version.base_addresses().clone();
+  for (module, value) in base_addresses {
+    patch_base_addresses[module] = value;
+  }
+
+  let mut patches: [Vec<ModulePatches>; 6] = Default::default();
+  for section in sections {
+    if let Section::Patches(feature, module, src) = section {
+      let p = parse_patches(&src, hooks.named_hook).map_err(|e| e.to_string())?;
+      patches[feature as usize].push(ModulePatches::new(module, Vec::leak(p)));
+    }
+  }
+  let [a, b, c, d, e, f] = patches.map(Vec::leak);
+  Ok(GamePatches {
+    version,
+    addresses: Box::leak(Box::new(patch_addresses)),
+    base_addresses: Box::leak(Box::new(patch_base_addresses)),
+    patches: Box::leak(Box::new(FeaturePatches::new(a, b, c, d, e, f))),
+  })
+}
+
 macro_rules! decl_fns {
   (
     $fname:ident: $sname:ident:
@@ -319,19 +482,57 @@ impl GameAccessor {
   }
 }
 
+/// The patches and addresses used for the running game.
+pub struct GamePatches {
+  pub version: d2::GameVersion,
+  pub addresses: &'static d2::Addresses,
+  pub base_addresses: &'static d2::BaseAddresses,
+  pub patches: &'static FeaturePatches,
+}
+
+/// Gets the game version, its addresses and its patches, either from the
+/// configured patch file or by detecting the version of `game.exe`. `file_hash`
+/// hashes `game.exe` for the versions which share a version number.
+pub fn game_patches(file_hash: impl FnOnce() -> Option<u64>) -> Result<GamePatches, String> {
+  let patch_file = INSTANCE.config.patch_file.lock().clone();
+  if let Some(path) = patch_file {
+    let patches =
+      load_patch_file(&path).map_err(|e| format!("failed to load the patch file: {e}"))?;
+    log!(
+      "Loaded patch file `{path}` for game version: {}",
+      patches.version
+    );
+    Ok(patches)
+  } else {
+    let version = unsafe { read_file_version(GAME_EXE) }
+      .map_err(|()| "failed to detect the game version".to_owned())?;
+    let Some(version) = d2::GameVersion::from_file_version(version, file_hash) else {
+      return Err(format!("unknown game version {version}"));
+    };
+    log!("Detected game version: {version}");
+    Ok(GamePatches {
+      version,
+      addresses: version.addresses(),
+      base_addresses: version.base_addresses(),
+      patches: &Hooks::for_version(version).patches,
+    })
+  }
+}
+
 impl InstanceSync {
   pub fn attach(&mut self) {
-    let (version, hooks, is_expansion) = match unsafe { read_file_version(GAME_EXE) } {
-      Ok(version) => Hooks::from_game_file_version(version),
-      Err(_) => {
-        log!("Error detecting game version");
-        INSTANCE.config.features.store_relaxed(Features::empty());
-        return;
-      }
-    };
-    log!("Detected game version: {}", version);
+    let GamePatches { version, addresses, base_addresses, patches } =
+      match game_patches(|| hash_module_file(unsafe { GetModuleHandleW(GAME_EXE) })) {
+        Ok(x) => x,
+        Err(e) => {
+          log!("Disabling all features: {e}");
+          INSTANCE.config.features.store_relaxed(Features::empty());
+          return;
+        }
+      };
+    let hooks = Hooks::for_version(version);
 
-    let Some(modules) = (hooks.load_modules)() else {
+    let Some(modules) = version.module_layout().load() else {
       log!("Disabling all features: failed to load game modules");
       INSTANCE.config.features.store_relaxed(Features::empty());
       return;
@@ -339,7 +540,12 @@ impl InstanceSync {
     if unsafe {
       self
         .accessor
-        .load(&modules, &hooks.addresses, is_expansion, &hooks.helper_fns)
+        .load(
+          &modules,
+          addresses,
+          version.is_expansion(),
+          &hooks.helper_fns,
+        )
         .is_err()
     } {
       log!("Disabling all features: failed to load game addresses");
@@ -347,8 +553,12 @@ impl InstanceSync {
       return;
     }
 
-    for (feature, patches) in hooks
-      .patches
+    if INSTANCE.static_patches.load(Relaxed) {
+      log!("Using the patches in the game's files");
+      return;
+    }
+
+    for (feature, patches) in patches
       .iter()
       .filter(|(f, _)| INSTANCE.config.features.load_relaxed().intersects(f.as_flag()))
     {
@@ -359,65 +569,96 @@ impl InstanceSync {
         );
       } else if patches.is_empty() {
         log!("Disabling feature `{feature}`: unsupported version");
-      } else if unsafe { try_apply_patch_set(&modules, &hooks.base_addresses, patches).is_err() } {
-        log!("Disabling feature `{feature}`: failed to apply patches");
-      } else {
+      } else if let Ok(applied) = unsafe { try_apply_patch_set(&modules, base_addresses, patches) }
+      {
         log!("Applied feature `{feature}`");
+        applied.keep();
         continue;
+      } else {
+        log!("Disabling feature `{feature}`: failed to apply patches");
       }
       INSTANCE.config.features.remove_relaxed(feature.as_flag());
     }
 
     if INSTANCE.config.reapply_patches.load(Relaxed) {
-      self.reapply_patches = Some((&hooks.patches, modules));
+      self.reapply_patches = Some((patches, base_addresses, modules));
     }
   }
 
   fn reapply_patches(&mut self) {
-    if let Some((patches, modules)) = self.reapply_patches.take() {
+    if let Some((patches, base_addresses, modules)) = self.reapply_patches.take() {
       log!("Reapplying all patches");
       let features = INSTANCE.config.features.load_relaxed();
-      for (_, patches) in patches.iter().filter(|(f, _)| features.intersects(f.as_flag())) {
-        unsafe {
-          apply_patch_set(&modules, patches);
+      for (feature, patches) in patches.iter().filter(|(f, _)| features.intersects(f.as_flag())) {
+        // The patches from the first application keep the game's original code
+        // so there's no need to track these.
+        match unsafe { patch_set(&modules, base_addresses, patches).apply_unchecked() } {
+          Ok(applied) => applied.keep(),
+          Err(_) => log!("Failed to reapply feature `{feature}`"),
         }
       }
     }
   }
 }
 
-unsafe fn try_apply_patch_set(
+/// Creates a patch set containing the patches for each of the game's modules.
+unsafe fn patch_set<'a>(
   modules: &d2::Modules,
   base_addresses: &d2::BaseAddresses,
-  mod_patches: &[ModulePatches],
-) -> Result<(), ()> {
-  if INSTANCE.config.integrity_checks.load(Relaxed) {
-    let mut success = true;
-    for m in mod_patches {
-      let d2mod = modules[m.module];
-      let reloc_dist = d2mod.wrapping_sub(base_addresses[m.module] as isize);
-      for p in m.patches {
-        if !p.has_expected(d2mod, reloc_dist) {
-          success = false;
-          log!("Failed to apply patch at: {}+{:#x}", m.module, p.offset);
-        }
-      }
-    }
-    if !success {
-      return Err(());
-    }
+  mod_patches: &'a [ModulePatches],
+) -> PatchSet<'a, LoadedModule> {
+  let mut set = PatchSet::new();
+  for m in mod_patches {
+    let d2mod = LoadedModule::new(modules[m.module]);
+    let reloc_dist = d2mod.handle().wrapping_sub(base_addresses[m.module] as isize);
+    set.add(d2mod, reloc_dist, m.patches);
   }
-  apply_patch_set(modules, mod_patches);
-  Ok(())
+  set
 }
 
-unsafe fn apply_patch_set(modules: &d2::Modules, mod_patches: &[ModulePatches]) {
-  for m in mod_patches {
-    let d2mod = modules[m.module];
-    for p in m.patches {
-      p.apply(d2mod)
+unsafe fn try_apply_patch_set(
+  modules: &d2::Modules,
+  base_addresses: &d2::BaseAddresses,
+  mod_patches: &[ModulePatches],
+) -> Result<AppliedPatchSet<LoadedModule>, ()> {
+  let set = patch_set(modules, base_addresses, mod_patches);
+  let res = if INSTANCE.config.integrity_checks.load(Relaxed) {
+    set.apply()
+  } else {
+    set.apply_unchecked()
+  };
+  if let Ok(applied) = &res {
+    for c in applied.chained_hooks() {
+      log!(
+        "Chained patch at: {}+{:#x} with the `{}` to {:#x} from another mod",
+        mod_patches[c.group].module,
+        c.offset,
+        c.hook.kind.name(),
+        c.hook.target,
+      );
     }
   }
+  res.map_err(|errors| {
+    for e in errors {
+      let m = &mod_patches[e.group];
+      log!(
+        "Failed to apply patch at: {}+{:#x}: {}",
+        m.module,
+        e.offset,
+        e.error
+      );
+      if let PatchError::Mismatch(_) = e.error {
+        let d2mod = LoadedModule::new(modules[m.module]);
+        let reloc_dist = d2mod.handle().wrapping_sub(base_addresses[m.module] as isize);
+        let p = m.patches.iter().find(|p| p.offset == e.offset);
+        match p.map(|p| p.locate(&d2mod, reloc_dist)) {
+          Some(Ok(offset)) => log!("Expected code found at: {}+{offset:#x}", m.module),
+          Some(Err(e)) => log!("Failed to find expected code: {e}"),
+          None => {}
+        }
+      }
+    }
+  })
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
@@ -860,7 +1101,7 @@ unsafe extern "fastcall" fn should_update_cursor(cursor: d2::CursorId) -> bool {
     && INSTANCE.sync.lock().accessor.cursor_table()[cursor.0 as usize].is_anim != 0
 }
 
-extern "fastcall" fn summit_cloud_move_amount(x: d2::FU4) -> d2::FU4 {
+fn summit_cloud_move_amount(x: d2::FU4) -> d2::FU4 {
   (f64::from(x) * INSTANCE.update_time_fract.load(Relaxed)).into()
 }
 
@@ -8,18 +8,12 @@ use crate::{
 };
 use bin_patch::{patch_source, Patch};
 use core::arch::global_asm;
-use d2interface::{
-  self as d2,
-  v100::{Entity, ADDRESSES, BASE_ADDRESSES},
-  IntoSys,
-};
+use d2interface::{self as d2, v100::Entity, IntoSys};
 use num::WrappingInto;
 
 #[rustfmt::skip]
 pub(super) const HOOKS: Hooks = Hooks {
-  addresses: ADDRESSES,
-  base_addresses: BASE_ADDRESSES,
-  load_modules: d2::Modules::load_split_modules,
+  named_hook: super::named_hook::<Entity>,
   patches: FeaturePatches::new(
     &[ModulePatches::new(
       d2::Module::Win,
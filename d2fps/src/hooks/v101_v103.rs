@@ -0,0 +1,62 @@
+//! Patches which only differ in location between 1.01 and 1.03.
+
+use crate::hooks::{
+  draw_game_paused, entity_iso_xpos, entity_iso_ypos, entity_linear_xpos, entity_linear_ypos,
+  game_loop_sleep_hook, v100,
+};
+use bin_patch::patch_versions;
+use d2interface::v100::Entity;
+
+patch_versions! {
+  versions: [v101, v102, v103];
+
+  /// Menu char frame rate
+  MENU_CHAR_FRAME = call_c(v100::update_menu_char_frame_100_asm_stub) {
+    _ => 0x26ea, "8b4e10 8b4608 8b560c 03c1 894608";
+  }
+
+  /// Game loop sleep patch
+  GAME_LOOP_SLEEP = call_c(game_loop_sleep_hook) {
+    v101 => 0x75ec, "a1 $f0560f10 85c0 7508 6a00 ff15 $701d1810";
+    v102 => 0x762c, "a1 $f0560f10 85c0 7508 6a00 ff15 $941d1810";
+    v103 => 0x767c, "a1 $f0560f10 85c0 7508 6a00 ff15 $241e1810";
+  }
+
+  /// Draw paused game framerate
+  DRAW_GAME_PAUSED = call_c(draw_game_paused) {
+    v101 => 0xf957, "ff15 $1cea1210";
+    v102 => 0xf4e5, "ff15 $5ce91210";
+    v103 => 0xf535, "ff15 $bce81210";
+  }
+
+  /// Npc mouse over perspective
+  NPC_MOUSE_OVER_LINEAR_X = call_std1(entity_linear_xpos::<Entity>) {
+    _ => 0xd520e, "e8f90f0000";
+    v102 => 0xd566e;
+    v103 => 0xd5cee;
+  }
+  NPC_MOUSE_OVER_LINEAR_Y = call_std1(entity_linear_ypos::<Entity>) {
+    _ => 0xd5207, "e8fa0f0000";
+    v102 => 0xd5667;
+    v103 => 0xd5ce7;
+  }
+
+  /// Npc mouse over
+  NPC_MOUSE_OVER_X = call_std1(entity_iso_xpos::<Entity>) {
+    _ => 0xd523a, "e857100000";
+    v102 => 0xd569a;
+    v103 => 0xd5d1a;
+  }
+  NPC_MOUSE_OVER_Y = call_std1(entity_iso_ypos::<Entity>) {
+    _ => 0xd524d, "e83e100000";
+    v102 => 0xd56ad;
+    v103 => 0xd5d2d;
+  }
+
+  /// Weather update
+  WEATHER_UPDATE = nop() {
+    _ => 0xd3fb, "e860030000";
+    v102 => 0xd12b;
+    v103 => 0xd17b;
+  }
+}
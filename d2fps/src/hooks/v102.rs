@@ -1,21 +1,13 @@
 use crate::{
   features::{FeaturePatches, ModulePatches},
-  hooks::{
-    draw_game, draw_game_paused, entity_iso_xpos, entity_iso_ypos, entity_linear_xpos,
-    entity_linear_ypos, game_loop_sleep_hook, HelperFns, Hooks,
-  },
+  hooks::{draw_game, entity_iso_xpos, entity_iso_ypos, HelperFns, Hooks},
 };
 use bin_patch::{patch_source, Patch};
-use d2interface::{
-  self as d2,
-  v102::{Entity, ADDRESSES, BASE_ADDRESSES},
-};
+use d2interface::{self as d2, v102::Entity};
 
 #[rustfmt::skip]
 pub(super) const HOOKS: Hooks = Hooks {
-  addresses: ADDRESSES,
-  base_addresses: BASE_ADDRESSES,
-  load_modules: d2::Modules::load_split_modules,
+  named_hook: super::named_hook::<Entity>,
   patches: FeaturePatches::new(
     &[ModulePatches::new(
       d2::Module::Win,
@@ -57,29 +49,14 @@ pub(super) const HOOKS: Hooks = Hooks {
           ffd0
           e87a26ffff
         "), super::v100::draw_menu_100_asm_stub),
-        // Menu char frame rate
-        Patch::call_c(0x26ea, patch_source!("
-          8b4e10
-          8b4608
-          8b560c
-          03c1
-          894608
-        "), super::v100::update_menu_char_frame_100_asm_stub),
+        super::v101_v103::v102::MENU_CHAR_FRAME,
       ],
     )],
     &[ModulePatches::new(
       d2::Module::Client,
       &[
-        // Game loop sleep patch
-        Patch::call_c(0x762c, patch_source!("
-          a1 $f0560f10
-          85c0
-          7508
-          6a00
-          ff15 $941d1810
-        "), game_loop_sleep_hook),
-        // Draw paused game framerate
-        Patch::call_c(0xf4e5, patch_source!("ff15 $5ce91210"), draw_game_paused),
+        super::v101_v103::v102::GAME_LOOP_SLEEP,
+        super::v101_v103::v102::DRAW_GAME_PAUSED,
         // Draw game framerate & entity sync
         Patch::call_c(0xfc5d, patch_source!("
           391d $e8eb1210
@@ -112,12 +89,10 @@ pub(super) const HOOKS: Hooks = Hooks {
           // Animated entity mouse detection refinement
           Patch::call_std1(0x9da86, patch_source!("e86b8c0300"), entity_iso_xpos::<Entity>),
           Patch::call_std1(0x9daad, patch_source!("e83e8c0300"), entity_iso_ypos::<Entity>),
-          // Npc mouse over perspective
-          Patch::call_std1(0xd566e, patch_source!("e8f90f0000"), entity_linear_xpos::<Entity>),
-          Patch::call_std1(0xd5667, patch_source!("e8fa0f0000"), entity_linear_ypos::<Entity>),
-          // Npc mouse over
-          Patch::call_std1(0xd569a, patch_source!("e857100000"), entity_iso_xpos::<Entity>),
-          Patch::call_std1(0xd56ad, patch_source!("e83e100000"), entity_iso_ypos::<Entity>),
+          super::v101_v103::v102::NPC_MOUSE_OVER_LINEAR_X,
+          super::v101_v103::v102::NPC_MOUSE_OVER_LINEAR_Y,
+          super::v101_v103::v102::NPC_MOUSE_OVER_X,
+          super::v101_v103::v102::NPC_MOUSE_OVER_Y,
         ],
       ),
       ModulePatches::new(
@@ -284,7 +259,7 @@ pub(super) const HOOKS: Hooks = Hooks {
       ModulePatches::new(
         d2::Module::Client,
         &[
-          Patch::nop(0xd12b, patch_source!("e860030000")),
+          super::v101_v103::v102::WEATHER_UPDATE,
         ]
       )
     ],
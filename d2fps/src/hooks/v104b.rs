@@ -6,16 +6,11 @@ use crate::{
   },
 };
 use bin_patch::{patch_source, Patch};
-use d2interface::{
-  self as d2,
-  v104b::{Entity, ADDRESSES, BASE_ADDRESSES},
-};
+use d2interface::{self as d2, v104b::Entity};
 
 #[rustfmt::skip]
 pub(super) const HOOKS: Hooks = Hooks {
-  addresses: ADDRESSES,
-  base_addresses: BASE_ADDRESSES,
-  load_modules: d2::Modules::load_split_modules,
+  named_hook: super::named_hook::<Entity>,
   patches: FeaturePatches::new(
     &[ModulePatches::new(
       d2::Module::Win,
@@ -57,14 +52,7 @@ pub(super) const HOOKS: Hooks = Hooks {
           ffd0
           e85e060000
         "), super::v100::draw_menu_100_asm_stub),
-        // Menu char frame rate
-        Patch::call_c(0x1b6a, patch_source!("
-          8b4e10
-          8b4608
-          8b560c
-          03c1
-          894608
-        "), super::v100::update_menu_char_frame_100_asm_stub),
+        super::v104b_v106b::v104b::MENU_CHAR_FRAME,
       ],
     )],
     &[ModulePatches::new(
@@ -282,7 +270,7 @@ pub(super) const HOOKS: Hooks = Hooks {
       ModulePatches::new(
         d2::Module::Client,
         &[
-          Patch::nop(0x61db, patch_source!("e800020000")),
+          super::v104b_v106b::v104b::WEATHER_UPDATE,
         ]
       )
     ],
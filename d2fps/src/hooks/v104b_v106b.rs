@@ -0,0 +1,18 @@
+//! Patches which only differ in location between 1.04b and 1.06b.
+
+use crate::hooks::v100;
+use bin_patch::patch_versions;
+
+patch_versions! {
+  versions: [v104b, v105, v106a, v106b];
+
+  /// Menu char frame rate
+  MENU_CHAR_FRAME = call_c(v100::update_menu_char_frame_100_asm_stub) {
+    _ => 0x1b6a, "8b4e10 8b4608 8b560c 03c1 894608";
+  }
+
+  /// Weather update
+  WEATHER_UPDATE = nop() {
+    _ => 0x61db, "e800020000";
+  }
+}
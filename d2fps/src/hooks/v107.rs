@@ -5,20 +5,13 @@ use crate::{
     entity_linear_ypos, game_loop_sleep_hook, summit_cloud_move_amount, HelperFns, Hooks, UnitId,
   },
 };
-use bin_patch::{patch_source, Patch};
-use core::arch::global_asm;
-use d2interface::{
-  self as d2,
-  v107::{Entity, ADDRESSES, BASE_ADDRESSES},
-  IntoSys,
-};
+use bin_patch::{patch_source, Patch, Registers};
+use d2interface::{self as d2, v107::Entity, IntoSys};
 use num::WrappingInto;
 
 #[rustfmt::skip]
 pub(super) const HOOKS: Hooks = Hooks {
-  addresses: ADDRESSES,
-  base_addresses: BASE_ADDRESSES,
-  load_modules: d2::Modules::load_split_modules,
+  named_hook: super::named_hook::<Entity>,
   patches: FeaturePatches::new(
     &[ModulePatches::new(
       d2::Module::Win,
@@ -262,10 +255,10 @@ pub(super) const HOOKS: Hooks = Hooks {
             39a8 $6081bd6f
           "), super::v100::should_update_cursor_100_asm_stub),
           // Summit cloud move speed
-          Patch::call_c(0x16f86, patch_source!("
+          Patch::detour(0x16f86, patch_source!("
             03e9
             81c270010000
-          "), summit_cloud_move_amount_107_asm_stub),
+          "), summit_cloud_move_amount_107),
         ],
       ),
     ],
@@ -432,17 +425,6 @@ impl super::Entity for Entity {
   }
 }
 
-global_asm! {
-  ".global _summit_cloud_move_amount_107_asm_stub",
-  "_summit_cloud_move_amount_107_asm_stub:",
-  "add edx, 0x170",
-  "push edx",
-  "call {}",
-  "add ebp, eax",
-  "pop edx",
-  "ret",
-  sym summit_cloud_move_amount,
-}
-extern "C" {
-  pub fn summit_cloud_move_amount_107_asm_stub();
+pub(super) unsafe extern "C" fn summit_cloud_move_amount_107(regs: &mut Registers) {
+  regs.ecx = summit_cloud_move_amount(d2::FU4::from_repr(regs.ecx)).repr();
 }
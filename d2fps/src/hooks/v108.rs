@@ -6,16 +6,11 @@ use crate::{
   },
 };
 use bin_patch::{patch_source, Patch};
-use d2interface::{
-  self as d2,
-  v108::{Entity, ADDRESSES, BASE_ADDRESSES},
-};
+use d2interface::{self as d2, v108::Entity};
 
 #[rustfmt::skip]
 pub(super) const HOOKS: Hooks = Hooks {
-  addresses: ADDRESSES,
-  base_addresses: BASE_ADDRESSES,
-  load_modules: d2::Modules::load_split_modules,
+  named_hook: super::named_hook::<Entity>,
   patches: FeaturePatches::new(
     &[ModulePatches::new(
       d2::Module::Win,
@@ -259,10 +254,10 @@ pub(super) const HOOKS: Hooks = Hooks {
             39a8 $d8dcbc6f
           "), super::v100::should_update_cursor_100_asm_stub),
           // Summit cloud move speed
-          Patch::call_c(0x16836, patch_source!("
+          Patch::detour(0x16836, patch_source!("
             03e9
             81c270010000
-          "), super::v107::summit_cloud_move_amount_107_asm_stub),
+          "), super::v107::summit_cloud_move_amount_107),
         ],
       ),
     ],
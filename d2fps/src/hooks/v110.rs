@@ -6,51 +6,48 @@ use crate::{
     update_menu_char_frame, HelperFns, Hooks, UnitId,
   },
 };
-use bin_patch::{patch_source, Patch};
+use bin_patch::{patch_source, Patch, Registers};
 use core::arch::global_asm;
-use d2interface::{
-  self as d2,
-  v110::{Entity, ADDRESSES, BASE_ADDRESSES},
-  IntoSys,
-};
+use d2interface::{self as d2, v110::Entity, IntoSys};
 use num::WrappingInto;
 
 #[rustfmt::skip]
 pub(super) const HOOKS: Hooks = Hooks {
-  addresses: ADDRESSES,
-  base_addresses: BASE_ADDRESSES,
-  load_modules: d2::Modules::load_split_modules,
+  named_hook: super::named_hook::<Entity>,
   patches: FeaturePatches::new(
     &[ModulePatches::new(
       d2::Module::Win,
       &[
         // Draw menu framerate
         Patch::call_c(0xd00c, patch_source!("
-          ffd5
-          8bf0
-          2bf3
-          ffd5
-          81fee8030000
-          8bd8
-          7605
-          bee8030000
-          2bfe
-          85ff
-          7f28
+        elapsed:
+          ffd5          ; call ebp
+          8bf0          ; mov esi, eax
+          2bf3          ; sub esi, ebx
+          ffd5          ; call ebp
+          81fee8030000  ; cmp esi, 1000
+          8bd8          ; mov ebx, eax
+          7605          ; jbe +5
+          bee8030000    ; mov esi, 1000
+        budget:
+          2bfe          ; sub edi, esi
+          85ff          ; test edi, edi
+          7f28          ; jg +28
           xxxxxx
-          81ff18fcffff
-          7d02
-          33ff
-          8b542434
-          85d2
-          740e
-          8b4c2410
-          8bc1
-          41
-          50
-          894c2414
-          ffd2
-          e89f060000
+          81ff18fcffff  ; cmp edi, -1000
+          7d02          ; jge +2
+          33ff          ; xor edi, edi
+        draw:
+          8b542434      ; mov edx, [esp+34]
+          85d2          ; test edx, edx
+          740e          ; je +e
+          8b4c2410      ; mov ecx, [esp+10]
+          8bc1          ; mov eax, ecx
+          41            ; inc ecx
+          50            ; push eax
+          894c2414      ; mov [esp+14], ecx
+          ffd2          ; call edx
+          e89f060000    ; call +69f
         "), draw_menu_110_asm_stub),
         // Menu char frame rate
         Patch::call_c(0x1abf, patch_source!("
@@ -271,10 +268,10 @@ pub(super) const HOOKS: Hooks = Hooks {
             39a8 $586bb96f
           "), super::v100::should_update_cursor_100_asm_stub),
           // Summit cloud move speed
-          Patch::call_c(0x17b75, patch_source!("
+          Patch::detour(0x17b75, patch_source!("
             03d8
             81c170010000
-          "), summit_cloud_move_amount_110_asm_stub),
+          "), summit_cloud_move_amount_110),
         ],
       ),
     ],
@@ -482,18 +479,6 @@ extern "C" {
   pub fn intercept_teleport_110_asm_stub();
 }
 
-global_asm! {
-  ".global _summit_cloud_move_amount_110_asm_stub",
-  "_summit_cloud_move_amount_110_asm_stub:",
-  "add ecx, 0x170",
-  "push ecx",
-  "mov ecx, eax",
-  "call {}",
-  "add ebx, eax",
-  "pop ecx",
-  "ret",
-  sym summit_cloud_move_amount,
-}
-extern "C" {
-  pub fn summit_cloud_move_amount_110_asm_stub();
+pub(super) unsafe extern "C" fn summit_cloud_move_amount_110(regs: &mut Registers) {
+  regs.eax = summit_cloud_move_amount(d2::FU4::from_repr(regs.eax)).repr();
 }
@@ -6,18 +6,13 @@ use crate::{
     should_update_cursor, summit_cloud_move_amount, update_menu_char_frame, HelperFns, Hooks,
   },
 };
-use bin_patch::{patch_source, Patch};
+use bin_patch::{patch_source, Patch, Registers};
 use core::arch::global_asm;
-use d2interface::{
-  self as d2,
-  v111a::{Entity, ADDRESSES, BASE_ADDRESSES},
-};
+use d2interface::{self as d2, v111a::Entity};
 
 #[rustfmt::skip]
 pub(super) const HOOKS: Hooks = Hooks {
-  addresses: ADDRESSES,
-  base_addresses: BASE_ADDRESSES,
-  load_modules: d2::Modules::load_split_modules,
+  named_hook: super::named_hook::<Entity>,
   patches: FeaturePatches::new(
     &[ModulePatches::new(
       d2::Module::Win,
@@ -159,11 +154,11 @@ pub(super) const HOOKS: Hooks = Hooks {
             85c9
           "), should_update_cursor_111_asm_stub),
           // Summit cloud move speed
-          Patch::call_c(0x5bc8c, patch_source!("
+          Patch::detour(0x5bc8c, patch_source!("
             03da
             8bc3
             3bc7
-          "), summit_cloud_move_amount_111_asm_stub),
+          "), summit_cloud_move_amount_111),
         ],
       ),
     ],
@@ -226,21 +221,8 @@ extern "C" {
   pub fn should_update_cursor_111_asm_stub();
 }
 
-global_asm! {
-  ".global _summit_cloud_move_amount_111_asm_stub",
-  "_summit_cloud_move_amount_111_asm_stub:",
-  "push ecx",
-  "mov ecx, edx",
-  "call {}",
-  "add eax, ebx",
-  "mov ebx, eax",
-  "cmp eax, edi",
-  "pop ecx",
-  "ret",
-  sym summit_cloud_move_amount,
-}
-extern "C" {
-  pub fn summit_cloud_move_amount_111_asm_stub();
+pub(super) unsafe extern "C" fn summit_cloud_move_amount_111(regs: &mut Registers) {
+  regs.edx = summit_cloud_move_amount(d2::FU4::from_repr(regs.edx)).repr();
 }
 
 global_asm! {
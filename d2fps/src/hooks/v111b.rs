@@ -6,16 +6,11 @@ use crate::{
   },
 };
 use bin_patch::{patch_source, Patch};
-use d2interface::{
-  self as d2,
-  v111b::{Entity, ADDRESSES, BASE_ADDRESSES},
-};
+use d2interface::{self as d2, v111b::Entity};
 
 #[rustfmt::skip]
 pub(super) const HOOKS: Hooks = Hooks {
-  addresses: ADDRESSES,
-  base_addresses: BASE_ADDRESSES,
-  load_modules: d2::Modules::load_split_modules,
+  named_hook: super::named_hook::<Entity>,
   patches: FeaturePatches::new(
     &[ModulePatches::new(
       d2::Module::Win,
@@ -157,11 +152,11 @@ pub(super) const HOOKS: Hooks = Hooks {
             85c9
           "), super::v111a::should_update_cursor_111_asm_stub),
           // Summit cloud move speed
-          Patch::call_c(0x92dcc, patch_source!("
+          Patch::detour(0x92dcc, patch_source!("
             03da
             8bc3
             3bc7
-          "), super::v111a::summit_cloud_move_amount_111_asm_stub),
+          "), super::v111a::summit_cloud_move_amount_111),
         ],
       ),
     ],
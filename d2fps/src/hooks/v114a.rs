@@ -8,16 +8,11 @@ use crate::{
 use bin_patch::{patch_source, Patch};
 use core::arch::global_asm;
 use core::sync::atomic::Ordering::Relaxed;
-use d2interface::{
-  self as d2,
-  v114a::{Entity, ADDRESSES, BASE_ADDRESSES},
-};
+use d2interface::{self as d2, v114a::Entity};
 
 #[rustfmt::skip]
 pub(super) const HOOKS: Hooks = Hooks {
-  addresses: ADDRESSES,
-  base_addresses: BASE_ADDRESSES,
-  load_modules: d2::Modules::load_combined_module,
+  named_hook: super::named_hook::<Entity>,
   patches: FeaturePatches::new(
     &[ModulePatches::new(
       d2::Module::GameExe,
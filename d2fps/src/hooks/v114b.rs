@@ -3,16 +3,11 @@ use crate::{
   hooks::{draw_game, draw_game_paused, game_loop_sleep_hook, HelperFns, Hooks},
 };
 use bin_patch::{patch_source, Patch};
-use d2interface::{
-  self as d2,
-  v114b::{Entity, ADDRESSES, BASE_ADDRESSES},
-};
+use d2interface::{self as d2, v114b::Entity};
 
 #[rustfmt::skip]
 pub(super) const HOOKS: Hooks = Hooks {
-  addresses: ADDRESSES,
-  base_addresses: BASE_ADDRESSES,
-  load_modules: d2::Modules::load_combined_module,
+  named_hook: super::named_hook::<Entity>,
   patches: FeaturePatches::new(
     &[ModulePatches::new(
       d2::Module::GameExe,
@@ -7,16 +7,11 @@ use crate::{
 };
 use bin_patch::{patch_source, Patch};
 use core::arch::global_asm;
-use d2interface::{
-  self as d2,
-  v114d::{Entity, ADDRESSES, BASE_ADDRESSES},
-};
+use d2interface::{self as d2, v114d::Entity};
 
 #[rustfmt::skip]
 pub(super) const HOOKS: Hooks = Hooks {
-  addresses: ADDRESSES,
-  base_addresses: BASE_ADDRESSES,
-  load_modules: d2::Modules::load_combined_module,
+  named_hook: super::named_hook::<Entity>,
   patches: FeaturePatches::new(
     &[ModulePatches::new(
       d2::Module::GameExe,
@@ -136,7 +131,7 @@ pub(super) const HOOKS: Hooks = Hooks {
         d2::Module::GameExe,
         &[
           // Cursor animation speed
-          Patch::raw(0x6836d, patch_source!("10"), &[0x28]),
+          Patch::raw(0x6836d, patch_source!(data "10"), &[0x28]),
           // Summit cloud move speed
           Patch::call_c(0x768cd, patch_source!("
             0197 $e85b7b00
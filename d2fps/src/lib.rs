@@ -45,10 +45,15 @@ macro_rules! log {
 
 mod arcane;
 mod config;
+mod export;
 mod features;
+#[cfg(test)]
+mod game_files;
 mod hooks;
 mod limiter;
 mod logger;
+#[cfg(test)]
+mod support;
 mod util;
 mod weather;
 mod window;
@@ -90,7 +95,11 @@ struct InstanceSync {
   weather_particles: Vec<weather::Particle>,
   /// Patches to reapply once the menu is loaded. Helps compatibility with other
   /// mods that patch code without validating the patch location's data.
-  reapply_patches: Option<(&'static FeaturePatches, d2::Modules)>,
+  reapply_patches: Option<(
+    &'static FeaturePatches,
+    &'static d2::BaseAddresses,
+    d2::Modules,
+  )>,
   delayed: Option<InstanceDelayed>,
 }
 struct Instance {
@@ -124,6 +133,9 @@ struct Instance {
   update_time_fract: AtomicF64,
   /// The number of QPC ticks that have occurred since the previous drawn frame.
   update_ticks: AtomicU64,
+  /// Whether the game's files were patched with the patches written by
+  /// `ExportPatches`. No patches are applied when attaching.
+  static_patches: AtomicBool,
 }
 impl Instance {
   unsafe fn frame_rate_from_window(&self, hwnd: HWND) {
@@ -166,11 +178,17 @@ static INSTANCE: Instance = Instance {
   client_updated: AtomicBool::new(true),
   update_time_fract: AtomicF64::new(0.0),
   update_ticks: AtomicU64::new(0),
+  static_patches: AtomicBool::new(false),
 };
 
+/// The handle of this library.
+static DLL_MODULE: AtomicIsize = AtomicIsize::new(0);
+
 #[no_mangle]
 pub extern "system" fn DllMain(module: HMODULE, reason: u32, _: *mut c_void) -> BOOL {
   if reason == DLL_PROCESS_ATTACH {
+    DLL_MODULE.store(module, Relaxed);
+
     // Should never fail starting with Windows XP
     if !INSTANCE.perf_freq.init() {
       return FALSE;
@@ -0,0 +1,125 @@
+//! Generates the table of which features are supported by each game version
+//! from the patch tables.
+//!
+//! The generated tables are kept in `SUPPORT.md` and `support.json`. Run the
+//! tests with `UPDATE_SUPPORT=1` set to regenerate them after changing a
+//! version's patches.
+
+use crate::{
+  features::{FeatureId, FeaturePatches},
+  hooks::version_patches,
+};
+use core::fmt::Write;
+use d2interface as d2;
+use std::{env, fs, path::Path};
+
+/// The patches used by a feature in a single game version.
+struct FeatureSupport {
+  patch_count: usize,
+  /// The modules patched in the order they're listed.
+  modules: Vec<d2::Module>,
+}
+impl FeatureSupport {
+  fn new(patches: &FeaturePatches, feature: FeatureId) -> Self {
+    let (_, mod_patches) = patches.iter().nth(feature as usize).unwrap();
+    let mut modules = Vec::new();
+    for p in mod_patches {
+      if !modules.contains(&p.module) {
+        modules.push(p.module);
+      }
+    }
+    Self {
+      patch_count: mod_patches.iter().map(|p| p.patches.len()).sum(),
+      modules,
+    }
+  }
+}
+
+struct VersionSupport {
+  version: &'static str,
+  features: Vec<FeatureSupport>,
+}
+
+fn support_matrix() -> Vec<VersionSupport> {
+  version_patches()
+    .map(|(version, patches)| VersionSupport {
+      version,
+      features: FeatureId::iter().map(|f| FeatureSupport::new(patches, f)).collect(),
+    })
+    .collect()
+}
+
+fn to_markdown(matrix: &[VersionSupport]) -> String {
+  let mut s = String::from(
+    "# Support matrix\n\n\
+    The number of patches each feature uses in each game version along with the \
+    modules they patch. `-` marks a feature which isn't supported.\n\n\
+    Generated from the patch tables by the `support_matrix_is_current` test. Set \
+    `UPDATE_SUPPORT=1` when running it to regenerate this file.\n\n| Version |",
+  );
+  for f in FeatureId::iter() {
+    let _ = write!(s, " {f} |");
+  }
+  s.push_str("\n|---|");
+  s.push_str(&"---|".repeat(FeatureId::iter().len()));
+  for v in matrix {
+    let _ = write!(s, "\n| {} |", v.version);
+    for f in &v.features {
+      if f.patch_count == 0 {
+        s.push_str(" - |");
+      } else {
+        let modules: Vec<_> = f.modules.iter().map(|m| m.as_str()).collect();
+        let _ = write!(s, " {} ({}) |", f.patch_count, modules.join(", "));
+      }
+    }
+  }
+  s.push('\n');
+  s
+}
+
+fn to_json(matrix: &[VersionSupport]) -> String {
+  let mut s = String::from("[");
+  for (i, v) in matrix.iter().enumerate() {
+    let sep = if i == 0 { "" } else { "," };
+    let _ = write!(
+      s,
+      "{sep}\n  {{\n    \"version\": \"{}\",\n    \"features\": {{",
+      v.version
+    );
+    for (j, (id, f)) in FeatureId::iter().zip(&v.features).enumerate() {
+      let sep = if j == 0 { "" } else { "," };
+      let modules: Vec<_> = f.modules.iter().map(|m| format!("\"{m}\"")).collect();
+      let _ = write!(
+        s,
+        "{sep}\n      \"{id}\": {{ \"patches\": {}, \"modules\": [{}] }}",
+        f.patch_count,
+        modules.join(", "),
+      );
+    }
+    s.push_str("\n    }\n  }");
+  }
+  s.push_str("\n]\n");
+  s
+}
+
+#[test]
+fn support_matrix_is_current() {
+  let matrix = support_matrix();
+  let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+  let update = env::var_os("UPDATE_SUPPORT").is_some();
+  for (name, contents) in [
+    ("SUPPORT.md", to_markdown(&matrix)),
+    ("support.json", to_json(&matrix)),
+  ] {
+    let path = dir.join(name);
+    if update {
+      fs::write(&path, contents).unwrap();
+    } else {
+      let current = fs::read_to_string(&path).unwrap_or_default();
+      assert!(
+        current.replace("\r\n", "\n") == contents,
+        "`{name}` is out of date; rerun with `UPDATE_SUPPORT=1` set",
+      );
+    }
+  }
+}
@@ -7,6 +7,7 @@ use core::{
   str::FromStr,
   sync::atomic::{AtomicBool, AtomicU64, Ordering::Relaxed},
 };
+use d2interface::FileVersion;
 use gcd::Gcd;
 use std::{
   ffi::{OsStr, OsString},
@@ -32,7 +33,7 @@ use windows_sys::{
       ProcessStatus::{EnumProcessModules, GetModuleFileNameExW},
       Threading::GetCurrentProcess,
     },
-    UI::WindowsAndMessaging::{MessageBoxW, MB_ICONERROR},
+    UI::WindowsAndMessaging::{MessageBoxW, MB_ICONERROR, MB_ICONINFORMATION, MESSAGEBOX_STYLE},
   },
 };
 use xxhash_rust::xxh3::xxh3_64;
@@ -289,14 +290,27 @@ pub unsafe fn monitor_refresh_rate(mon: HMONITOR) -> Option<Ratio> {
     })
 }
 
-pub fn message_box_error(msg: &str) {
+fn message_box(msg: &str, icon: MESSAGEBOX_STYLE) {
   let mut msg: Vec<u16> = OsStr::new(&msg).encode_wide().collect();
   msg.push(0);
+  let title = if icon == MB_ICONERROR {
+    w!("D2fps Error")
+  } else {
+    w!("D2fps")
+  };
   unsafe {
-    MessageBoxW(0, msg.as_ptr(), w!("D2fps Error"), MB_ICONERROR);
+    MessageBoxW(0, msg.as_ptr(), title, icon);
   }
 }
 
+pub fn message_box_error(msg: &str) {
+  message_box(msg, MB_ICONERROR);
+}
+
+pub fn message_box_info(msg: &str) {
+  message_box(msg, MB_ICONINFORMATION);
+}
+
 pub fn log_loaded_modules() {
   let process = unsafe { GetCurrentProcess() };
   let mut modules = [0; 256];
@@ -334,38 +348,22 @@ pub fn log_loaded_modules() {
   }
 }
 
-pub fn hash_module_file(module: HMODULE) -> Option<u64> {
+/// Gets the path of a loaded module's file.
+pub fn module_file_name(module: HMODULE) -> Option<String> {
   if module == 0 {
     return None;
   }
   let process = unsafe { GetCurrentProcess() };
   let mut buf = [0; 260];
   let len = unsafe { GetModuleFileNameExW(process, module, buf.as_mut_ptr(), 260) };
-  if len != 0 {
-    if let Some(name) = OsString::from_wide(&buf[..len as usize]).to_str() {
-      if let Ok(buf) = fs::read(name) {
-        return Some(xxh3_64(&buf));
-      }
-    }
+  if len == 0 {
+    return None;
   }
-  None
+  OsString::from_wide(&buf[..len as usize]).into_string().ok()
 }
 
-pub struct FileVersion {
-  pub ms: u32,
-  pub ls: u32,
-}
-impl fmt::Display for FileVersion {
-  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    write!(
-      f,
-      "{}.{}.{}.{}",
-      self.ms >> 16,
-      self.ms & 0xFFFF,
-      self.ls >> 16,
-      self.ls & 0xFFFF
-    )
-  }
+pub fn hash_module_file(module: HMODULE) -> Option<u64> {
+  fs::read(module_file_name(module)?).ok().map(|buf| xxh3_64(&buf))
 }
 
 pub unsafe fn read_file_version(file: *const u16) -> Result<FileVersion, ()> {
@@ -391,5 +389,5 @@ pub unsafe fn read_file_version(file: *const u16) -> Result<FileVersion, ()> {
   }
 
   let info = &*out.cast::<VS_FIXEDFILEINFO>();
-  Ok(FileVersion { ls: info.dwFileVersionLS, ms: info.dwFileVersionMS })
+  Ok(FileVersion::new(info.dwFileVersionMS, info.dwFileVersionLS))
 }
@@ -6,6 +6,19 @@ use num::M2d;
 #[repr(transparent)]
 pub struct SId<T, Id>(T, PhantomData<Id>);
 
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize, Id> serde::Serialize for SId<T, Id> {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    self.0.serialize(serializer)
+  }
+}
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>, Id> serde::Deserialize<'de> for SId<T, Id> {
+  fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    T::deserialize(deserializer).map(|x| Self(x, PhantomData))
+  }
+}
+
 pub type Id16<Id> = SId<i16, Id>;
 pub type Id8<Id> = SId<i8, Id>;
 
@@ -44,6 +57,18 @@ macro_rules! make_bool {
         *self == other.bool()
       }
     }
+    #[cfg(feature = "serde")]
+    impl serde::Serialize for $name {
+      fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bool(self.bool())
+      }
+    }
+    #[cfg(feature = "serde")]
+    impl<'de> serde::Deserialize<'de> for $name {
+      fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <bool as serde::Deserialize>::deserialize(deserializer).map(Self::from)
+      }
+    }
     impl ops::Not for $name {
       type Output = Self;
       fn not(self) -> Self::Output {
@@ -213,6 +238,7 @@ impl<T> EnvArray<T> {
   }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 pub struct EnvImage {
   pub active: Bool16,
@@ -224,6 +250,7 @@ pub struct EnvImage {
 }
 pub type EnvImages = EnvArray<EnvImage>;
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 pub struct EnvParticle {
   pub active: Bool16,
@@ -249,6 +276,7 @@ pub struct ClientEnvEffects {
   pub particles: *mut EnvParticles,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 pub struct ClientFpsTimer {
   /// The most recently calculated fps
@@ -263,6 +291,7 @@ pub struct ClientFpsTimer {
   pub last_update: u32,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 pub struct ClientPingTimer {
   /// The time of the next ping update.
@@ -296,6 +325,7 @@ pub struct ClientLoopGlobals {
   pub last_loading_update: u32,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 pub struct Rng([u32; 2]);
 impl Default for Rng {
@@ -592,6 +622,7 @@ decl_enum! { ItemHitClass(u8) {
 }}
 
 #[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 pub struct RgbColor {
   pub r: u8,
@@ -644,6 +675,7 @@ pub mod dtbl {
   decl_id!(UMon(i16));
 
   #[derive(Clone, Copy)]
+  #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
   #[repr(C)]
   pub struct ByLvl<T> {
     pub lvl1: T,
@@ -661,6 +693,7 @@ pub mod dtbl {
   }
 
   #[derive(Clone, Copy)]
+  #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
   #[repr(C)]
   pub struct AccByLvl3<T> {
     pub lvl2: T,
@@ -677,6 +710,7 @@ pub mod dtbl {
   }
 
   #[derive(Clone, Copy)]
+  #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
   #[repr(C)]
   pub struct AccByLvl5<T> {
     pub lvl2: T,
@@ -701,6 +735,7 @@ pub mod dtbl {
   }
 
   #[derive(Clone, Copy)]
+  #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
   #[repr(C)]
   pub struct ByNgLvl<T> {
     pub values: [T; 3],
@@ -712,6 +747,7 @@ pub mod dtbl {
   }
 
   #[derive(Clone, Copy)]
+  #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
   #[repr(C)]
   pub struct ByObjState<T> {
     pub values: [T; 8],
@@ -723,6 +759,7 @@ pub mod dtbl {
   }
 
   #[derive(Clone, Copy)]
+  #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
   #[repr(C)]
   pub struct ByNpcState<T> {
     pub values: [T; 16],
@@ -734,6 +771,7 @@ pub mod dtbl {
   }
 
   #[derive(Clone, Copy)]
+  #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
   #[repr(C)]
   pub struct ByComponent<T> {
     pub values: [T; 16],
@@ -745,6 +783,7 @@ pub mod dtbl {
   }
 
   #[derive(Clone, Copy)]
+  #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
   #[repr(C)]
   pub struct ByEqComponent<T> {
     pub rarm: T,
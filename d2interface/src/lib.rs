@@ -9,12 +9,67 @@ macro_rules! decl_enum {
     impl $name {
       $(pub const $vname: Self = Self($value);)*
     }
+
+    /// Serialized by name in human readable formats when the value is known.
+    #[cfg(feature = "serde")]
+    impl ::serde::Serialize for $name {
+      #[allow(unreachable_patterns)]
+      fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+          $(Self::$vname if serializer.is_human_readable() => {
+            serializer.serialize_str(stringify!($vname))
+          })*
+          _ => ::serde::Serialize::serialize(&self.0, serializer),
+        }
+      }
+    }
+
+    #[cfg(feature = "serde")]
+    impl<'de> ::serde::Deserialize<'de> for $name {
+      fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use ::serde::de::{Error, Unexpected};
+        struct Visitor;
+        impl ::serde::de::Visitor<'_> for Visitor {
+          type Value = $name;
+          fn expecting(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+            f.write_str(concat!("a `", stringify!($name), "` name or number"))
+          }
+          fn visit_str<E: Error>(self, v: &str) -> Result<$name, E> {
+            match v {
+              $(stringify!($vname) => Ok($name::$vname),)*
+              _ => Err(E::unknown_variant(v, &[$(stringify!($vname)),*])),
+            }
+          }
+          fn visit_u64<E: Error>(self, v: u64) -> Result<$name, E> {
+            <$ty>::try_from(v)
+              .map($name)
+              .map_err(|_| E::invalid_value(Unexpected::Unsigned(v), &self))
+          }
+          fn visit_i64<E: Error>(self, v: i64) -> Result<$name, E> {
+            <$ty>::try_from(v)
+              .map($name)
+              .map_err(|_| E::invalid_value(Unexpected::Signed(v), &self))
+          }
+        }
+
+        if deserializer.is_human_readable() {
+          deserializer.deserialize_any(Visitor)
+        } else {
+          <$ty as ::serde::Deserialize>::deserialize(deserializer).map(Self)
+        }
+      }
+    }
   }
 }
 
 macro_rules! decl_id {
   ($name:ident($ty:ty)) => {
     #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(
+      feature = "serde",
+      derive(serde::Serialize, serde::Deserialize),
+      serde(transparent)
+    )]
     #[repr(transparent)]
     pub struct $name(pub $ty);
     impl<T> From<$crate::common::SId<T, Self>> for $name
@@ -30,6 +85,7 @@ macro_rules! decl_id {
 
 mod common;
 mod module;
+mod version;
 
 pub mod v100;
 pub mod v101;
@@ -62,11 +118,17 @@ pub use crate::{
     GameCursor, GameType, Id16, Id8, InRoom, ItemHitClass, LinkedList, NgLvl, NpcSpawnTy, NpcState,
     ObjState, Pc, PcState, RgbColor, Rng, SkRange, StorePage, StrId,
   },
-  module::{Addresses, BaseAddresses, Client, Common, Game, Gfx, Module, Modules, Win},
+  module::{
+    AddressValue, Addresses, BaseAddresses, Client, Common, Game, Gfx, Module, Modules, Win,
+  },
+  version::{FileVersion, GameVersion, ModuleLayout, UnknownVersion},
 };
 
 use common::dtbl::{AccByLvl3, AccByLvl5, ByNgLvl};
-use num::{Fixed, M2d, Measure};
+use core::ops::{Add, Sub};
+use num::{
+  Fixed, M2d, Measure, SaturatingAdd, SaturatingSub, WrappingAdd, WrappingFrom, WrappingSub,
+};
 
 pub type EnvArray = common::EnvArray<()>;
 
@@ -79,7 +141,37 @@ pub type FU16 = Fixed<u32, 16>;
 pub type FU8 = Fixed<u32, 8>;
 pub type FU4 = Fixed<u32, 4>;
 
+/// An integer which can be stepped through one at a time. Also implemented for
+/// `Measure`s of integers.
+pub trait StepInt:
+  Copy
+  + Ord
+  + Add<Output = Self>
+  + Sub<Output = Self>
+  + SaturatingAdd<Output = Self>
+  + SaturatingSub<Output = Self>
+{
+  const ZERO: Self;
+  const ONE: Self;
+}
+macro_rules! impl_step_int {
+  ($($ty:ty),*) => {$(
+    impl StepInt for $ty {
+      const ZERO: Self = 0;
+      const ONE: Self = 1;
+    }
+  )*};
+}
+impl_step_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+impl<T: StepInt, S> StepInt for Measure<T, S> {
+  const ZERO: Self = Measure::new(T::ZERO);
+  const ONE: Self = Measure::new(T::ONE);
+}
+
+/// A range of values. Both bounds are inclusive, so a range is empty when
+/// `min` is greater than `max`.
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 pub struct Range<T> {
   pub min: T,
@@ -94,6 +186,112 @@ impl<T> Range<T> {
     Range::new(f(self.min), f(self.max))
   }
 }
+impl<T: Copy + Ord> Range<T> {
+  pub fn is_empty(&self) -> bool {
+    self.min > self.max
+  }
+
+  pub fn contains(&self, x: T) -> bool {
+    self.min <= x && x <= self.max
+  }
+
+  /// Restricts the value to be within the range. The range must not be empty.
+  pub fn clamp(&self, x: T) -> T {
+    x.clamp(self.min, self.max)
+  }
+
+  /// Gets the values contained in both ranges.
+  pub fn intersect(&self, other: &Self) -> Option<Self> {
+    let r = Range::new(self.min.max(other.min), self.max.min(other.max));
+    (!r.is_empty()).then_some(r)
+  }
+
+  /// Gets the smallest range containing both ranges.
+  pub fn union(&self, other: &Self) -> Self {
+    Range::new(self.min.min(other.min), self.max.max(other.max))
+  }
+
+  /// Moves both bounds outwards by the given amount. Each bound stops at the
+  /// limits of `T`.
+  pub fn expand(&self, by: T) -> Self
+  where
+    T: SaturatingAdd<Output = T> + SaturatingSub<Output = T>,
+  {
+    Range::new(self.min.ssub(by), self.max.sadd(by))
+  }
+
+  /// Moves both bounds inwards by the given amount. Each bound stops at the
+  /// limits of `T`.
+  pub fn shrink(&self, by: T) -> Self
+  where
+    T: SaturatingAdd<Output = T> + SaturatingSub<Output = T>,
+  {
+    Range::new(self.min.sadd(by), self.max.ssub(by))
+  }
+}
+impl<T: StepInt> Range<T> {
+  /// Creates the range starting at `pos` which contains `size` values. Empty
+  /// if the size isn't positive. The end stops at the largest value of `T`.
+  pub fn from_pos_size(pos: T, size: T) -> Self {
+    if size > T::ZERO {
+      Range::new(pos, pos.sadd(size - T::ONE))
+    } else if pos > T::ZERO {
+      Range::new(pos, pos - T::ONE)
+    } else {
+      // Avoids underflowing when `pos` is the smallest value of `T`.
+      Range::new(pos + T::ONE, pos)
+    }
+  }
+
+  /// Gets the number of values in the range. Zero if the range is empty and
+  /// the largest value of `T` if the size doesn't fit.
+  pub fn size(&self) -> T {
+    if self.is_empty() {
+      T::ZERO
+    } else {
+      self.max.ssub(self.min).sadd(T::ONE)
+    }
+  }
+
+  pub fn iter(&self) -> RangeIter<T> {
+    RangeIter {
+      next: self.min,
+      max: self.max,
+      done: self.is_empty(),
+    }
+  }
+}
+impl<T: StepInt> IntoIterator for Range<T> {
+  type Item = T;
+  type IntoIter = RangeIter<T>;
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+/// An iterator over the values in a `Range`.
+#[derive(Clone)]
+pub struct RangeIter<T> {
+  next: T,
+  max: T,
+  done: bool,
+}
+impl<T: StepInt> Iterator for RangeIter<T> {
+  type Item = T;
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.done {
+      return None;
+    }
+    let x = self.next;
+    // Checking before stepping avoids overflowing at the type's maximum.
+    if x < self.max {
+      self.next = x + T::ONE;
+    } else {
+      self.done = true;
+    }
+    Some(x)
+  }
+}
 impl<T: Copy> Range<ByNgLvl<T>> {
   pub fn at_ng_lvl(&self, lvl: NgLvl) -> Option<Range<T>> {
     Some(Range::new(
@@ -139,6 +337,9 @@ pub struct TileSys;
 pub type TileM<T> = Measure<T, TileSys>;
 pub type TileM2d<T> = M2d<TileM<T>>;
 
+/// The number of linear units along each side of a tile.
+pub const SUBTILES_PER_TILE: u32 = 5;
+
 pub trait FromSys<T> {
   fn from_sys(_: T) -> Self;
 }
@@ -152,6 +353,36 @@ impl<const N: u8> FromSys<LinearM2d<Fixed<u32, N>>> for IsoP2d<i32> {
     )
   }
 }
+/// Isometric positions have less precision than linear ones, so the low bits
+/// dropped by the conversion to isometric can't be recovered. Converting an
+/// isometric position to linear and back is exact, but converting a linear
+/// position to isometric and back rounds it down to the isometric grid.
+impl<const N: u8> FromSys<IsoP2d<i32>> for LinearM2d<Fixed<u32, N>> {
+  fn from_sys(p: IsoP2d<i32>) -> Self {
+    let x = p.x.0.wrapping_add(p.y.0 << 1);
+    let y = (p.y.0 << 1).wrapping_sub(p.x.0);
+    LinearM2d::new(
+      Measure::new(Fixed::<u32, 5>::from_repr(x as u32).with_prec()),
+      Measure::new(Fixed::<u32, 5>::from_repr(y as u32).with_prec()),
+    )
+  }
+}
+impl<const N: u8> FromSys<LinearM2d<Fixed<u32, N>>> for TileM2d<u32> {
+  fn from_sys(p: LinearM2d<Fixed<u32, N>>) -> Self {
+    TileM2d::new(
+      Measure::new(p.x.0.trunc() / SUBTILES_PER_TILE),
+      Measure::new(p.y.0.trunc() / SUBTILES_PER_TILE),
+    )
+  }
+}
+impl<const N: u8> FromSys<TileM2d<u32>> for LinearM2d<Fixed<u32, N>> {
+  fn from_sys(p: TileM2d<u32>) -> Self {
+    LinearM2d::new(
+      Measure::new(Fixed::wfrom(p.x.0.wrapping_mul(SUBTILES_PER_TILE))),
+      Measure::new(Fixed::wfrom(p.y.0.wrapping_mul(SUBTILES_PER_TILE))),
+    )
+  }
+}
 
 pub trait IntoSys<T> {
   fn into_sys(self) -> T;
@@ -162,30 +393,222 @@ impl<T, U: FromSys<T>> IntoSys<U> for T {
   }
 }
 
-/// A rectangle defined by two points.
+/// The part of the world drawn to the screen.
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Viewport {
+  /// The isometric position drawn at the center of the viewport.
+  pub center: IsoP2d<i32>,
+  pub size: ScreenM2d<u32>,
+  /// The horizontal offset applied while a side panel is open. Stored in the
+  /// client's `viewport_shift` global.
+  pub shift: ScreenM<i32>,
+}
+impl Viewport {
+  /// Gets the offset from isometric coordinates to screen coordinates.
+  fn offset(&self) -> ScreenM2d<i32> {
+    ScreenM2d::new(
+      Measure::new(((self.size.x.0 >> 1) as i32).wrapping_add(self.shift.0)),
+      Measure::new((self.size.y.0 >> 1) as i32),
+    )
+    .wsub(self.center.map(|x| x.with_sys()))
+  }
+
+  pub fn iso_to_screen(&self, p: IsoP2d<i32>) -> ScreenM2d<i32> {
+    p.map(|x| x.with_sys()).wadd(self.offset())
+  }
+
+  pub fn screen_to_iso(&self, p: ScreenM2d<i32>) -> IsoP2d<i32> {
+    p.wsub(self.offset()).map(|x| x.with_sys())
+  }
+
+  pub fn linear_to_screen<const N: u8>(&self, p: LinearM2d<Fixed<u32, N>>) -> ScreenM2d<i32> {
+    self.iso_to_screen(p.into_sys())
+  }
+
+  pub fn screen_to_linear<const N: u8>(&self, p: ScreenM2d<i32>) -> LinearM2d<Fixed<u32, N>> {
+    self.screen_to_iso(p).into_sys()
+  }
+}
+
+/// A rectangle defined by two points. Both points are inside the rectangle
+/// unless it's empty.
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 pub struct Rect<T> {
   pub upper_left: M2d<T>,
   pub lower_right: M2d<T>,
 }
+impl<T> Rect<T> {
+  pub const fn new(upper_left: M2d<T>, lower_right: M2d<T>) -> Self {
+    Self { upper_left, lower_right }
+  }
+}
+impl<T: Copy + Ord> Rect<T> {
+  pub fn contains(&self, p: M2d<T>) -> bool {
+    RectLr::from(*self).contains(p)
+  }
+}
+impl<T> From<RectLr<T>> for Rect<T> {
+  fn from(r: RectLr<T>) -> Self {
+    Rect::new(M2d::new(r.x.min, r.y.min), M2d::new(r.x.max, r.y.max))
+  }
+}
+impl<T: StepInt + WrappingFrom<U>, U> From<RectS<T, U>> for Rect<T> {
+  fn from(r: RectS<T, U>) -> Self {
+    RectLr::from(r).into()
+  }
+}
 
-/// A rectangle defined by the x-bounds and y-bounds.
+/// A rectangle defined by the x-bounds and y-bounds. Both bounds are
+/// inclusive, so the rectangle is empty when either range is.
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 pub struct RectLr<T> {
   pub x: Range<T>,
   pub y: Range<T>,
 }
+impl<T> RectLr<T> {
+  pub const fn new(x: Range<T>, y: Range<T>) -> Self {
+    Self { x, y }
+  }
+}
+impl<T: Copy + Ord> RectLr<T> {
+  pub fn is_empty(&self) -> bool {
+    self.x.is_empty() || self.y.is_empty()
+  }
+
+  pub fn contains(&self, p: M2d<T>) -> bool {
+    self.x.contains(p.x) && self.y.contains(p.y)
+  }
+
+  /// Gets the closest point inside the rectangle. The rectangle must not be
+  /// empty.
+  pub fn clamp(&self, p: M2d<T>) -> M2d<T> {
+    M2d::new(self.x.clamp(p.x), self.y.clamp(p.y))
+  }
+
+  /// Gets the area covered by both rectangles.
+  pub fn intersect(&self, other: &Self) -> Option<Self> {
+    Some(RectLr::new(
+      self.x.intersect(&other.x)?,
+      self.y.intersect(&other.y)?,
+    ))
+  }
+
+  /// Gets the smallest rectangle containing both rectangles.
+  pub fn union(&self, other: &Self) -> Self {
+    RectLr::new(self.x.union(&other.x), self.y.union(&other.y))
+  }
+
+  /// Moves each edge outwards by the given amount. Each edge stops at the
+  /// limits of `T`.
+  pub fn expand(&self, by: M2d<T>) -> Self
+  where
+    T: SaturatingAdd<Output = T> + SaturatingSub<Output = T>,
+  {
+    RectLr::new(self.x.expand(by.x), self.y.expand(by.y))
+  }
+
+  /// Moves each edge inwards by the given amount. Each edge stops at the
+  /// limits of `T`.
+  pub fn shrink(&self, by: M2d<T>) -> Self
+  where
+    T: SaturatingAdd<Output = T> + SaturatingSub<Output = T>,
+  {
+    RectLr::new(self.x.shrink(by.x), self.y.shrink(by.y))
+  }
+}
+impl<T: StepInt> RectLr<T> {
+  /// Iterates over each point in the rectangle one row at a time.
+  pub fn points(&self) -> RectPoints<T> {
+    let mut ys = self.y.iter();
+    RectPoints {
+      x: self.x,
+      xs: self.x.iter(),
+      y: if self.x.is_empty() { None } else { ys.next() },
+      ys,
+    }
+  }
+}
+impl<T> From<Rect<T>> for RectLr<T> {
+  fn from(r: Rect<T>) -> Self {
+    RectLr::new(
+      Range::new(r.upper_left.x, r.lower_right.x),
+      Range::new(r.upper_left.y, r.lower_right.y),
+    )
+  }
+}
+impl<T: StepInt + WrappingFrom<U>, U> From<RectS<T, U>> for RectLr<T> {
+  fn from(r: RectS<T, U>) -> Self {
+    RectLr::new(
+      Range::from_pos_size(r.pos.x, T::wfrom(r.size.x)),
+      Range::from_pos_size(r.pos.y, T::wfrom(r.size.y)),
+    )
+  }
+}
 
 pub type ScreenRectLr<T> = RectLr<ScreenM<T>>;
 
+/// An iterator over the points in a `RectLr`.
+#[derive(Clone)]
+pub struct RectPoints<T> {
+  x: Range<T>,
+  xs: RangeIter<T>,
+  y: Option<T>,
+  ys: RangeIter<T>,
+}
+impl<T: StepInt> Iterator for RectPoints<T> {
+  type Item = M2d<T>;
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      let y = self.y?;
+      if let Some(x) = self.xs.next() {
+        return Some(M2d::new(x, y));
+      }
+      self.y = self.ys.next();
+      self.xs = self.x.iter();
+    }
+  }
+}
+
 /// A rectangle defined by a position and size.
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 pub struct RectS<T, U> {
   pub pos: M2d<T>,
   pub size: M2d<U>,
 }
+impl<T, U> RectS<T, U> {
+  pub const fn new(pos: M2d<T>, size: M2d<U>) -> Self {
+    Self { pos, size }
+  }
+}
+impl<T: StepInt + WrappingFrom<U>, U: Copy> RectS<T, U> {
+  pub fn is_empty(&self) -> bool {
+    RectLr::from(*self).is_empty()
+  }
+
+  pub fn contains(&self, p: M2d<T>) -> bool {
+    RectLr::from(*self).contains(p)
+  }
+}
+impl<T: StepInt, U: WrappingFrom<T>> From<RectLr<T>> for RectS<T, U> {
+  /// An empty rectangle becomes one with a size of zero.
+  fn from(r: RectLr<T>) -> Self {
+    RectS::new(
+      M2d::new(r.x.min, r.y.min),
+      M2d::new(U::wfrom(r.x.size()), U::wfrom(r.y.size())),
+    )
+  }
+}
+impl<T: StepInt, U: WrappingFrom<T>> From<Rect<T>> for RectS<T, U> {
+  fn from(r: Rect<T>) -> Self {
+    RectLr::from(r).into()
+  }
+}
 
 pub type ScreenRectS<T, U> = RectS<ScreenM<T>, ScreenM<U>>;
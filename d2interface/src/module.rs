@@ -1,5 +1,10 @@
 use crate::{Bool32, ClientEnvEffects, ClientLoopGlobals, Cursor, EnvArray, GameType, FI4, FU8};
-use core::{fmt, mem::transmute, ops::Index, ptr::NonNull};
+use core::{
+  fmt,
+  mem::transmute,
+  ops::{Index, IndexMut},
+  ptr::NonNull,
+};
 use windows_sys::{
   w,
   Win32::{
@@ -95,6 +100,7 @@ impl Index<Module> for Modules {
   }
 }
 
+#[derive(Clone, Copy)]
 pub(crate) enum Ordinal {
   Ordinal(u16),
   Address(usize),
@@ -116,6 +122,20 @@ macro_rules! decl_addresses_init {
     Ordinal::Address(0usize)
   };
 }
+macro_rules! decl_addresses_set {
+  ($field:expr, $value:expr) => {
+    $field = match $value {
+      AddressValue::Offset(x) => x,
+      AddressValue::Ordinal(_) => return false,
+    }
+  };
+  ($field:expr, $value:expr, ordinal) => {
+    $field = match $value {
+      AddressValue::Offset(x) => Ordinal::Address(x),
+      AddressValue::Ordinal(x) => Ordinal::Ordinal(x),
+    }
+  };
+}
 macro_rules! decl_addresses_impl {
   ($(#[$meta:meta])* $module:ident::$item:ident: $ty:ty) => {
     $(#[$meta])*
@@ -136,8 +156,18 @@ macro_rules! decl_addresses_impl {
   };
 }
 
+/// A value for one of the game's addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressValue {
+  /// An offset from the module's base address.
+  Offset(usize),
+  /// A function exported by ordinal.
+  Ordinal(u16),
+}
+
 macro_rules! decl_addresses {
   ($($(#[$meta:meta])* $(#$ordinal:ident)? $module:ident::$item:ident: $ty:ty),* $(,)?) => {
+    #[derive(Clone)]
     pub struct Addresses {$(
       $(#[$meta])*
       pub(crate) $item: decl_addresses_ty!($($ordinal)?)
@@ -146,6 +176,17 @@ macro_rules! decl_addresses {
       pub const ZERO: Self = Self {
         $($item: decl_addresses_init!($($ordinal)?),)*
       };
+
+      /// Sets an address by its name. Returns `false` if there is no such
+      /// address, or if an ordinal is given for an address which can't be
+      /// exported by ordinal.
+      pub fn set(&mut self, name: &str, value: AddressValue) -> bool {
+        match name {
+          $(stringify!($item) => decl_addresses_set!(self.$item, value $(, $ordinal)?),)*
+          _ => return false,
+        }
+        true
+      }
       $(decl_addresses_impl! {
         $(#[$meta])* $($ordinal)? $module::$item: $ty
       })*
@@ -207,7 +248,7 @@ decl_addresses! {
   #ordinal Fog::env_array_remove: unsafe extern "fastcall" fn(*mut EnvArray, id: u32),
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Module {
   /// The v1.14* monolithic game.exe
   GameExe,
@@ -221,6 +262,21 @@ pub enum Module {
   Win,
 }
 impl Module {
+  /// Gets a module by its file name. Ignores case.
+  pub fn from_file_name(name: &str) -> Option<Self> {
+    [
+      Self::GameExe,
+      Self::Client,
+      Self::Common,
+      Self::Fog,
+      Self::Game,
+      Self::Gfx,
+      Self::Win,
+    ]
+    .into_iter()
+    .find(|m| m.as_str().eq_ignore_ascii_case(name))
+  }
+
   pub fn as_str(&self) -> &'static str {
     match *self {
       Self::GameExe => "game.exe",
@@ -239,6 +295,7 @@ impl fmt::Display for Module {
   }
 }
 
+#[derive(Clone)]
 pub struct BaseAddresses {
   pub client: usize,
   pub common: usize,
@@ -270,3 +327,15 @@ impl Index<Module> for BaseAddresses {
     }
   }
 }
+impl IndexMut<Module> for BaseAddresses {
+  fn index_mut(&mut self, index: Module) -> &mut Self::Output {
+    match index {
+      Module::GameExe | Module::Client => &mut self.client,
+      Module::Common => &mut self.common,
+      Module::Fog => &mut self.fog,
+      Module::Game => &mut self.game,
+      Module::Gfx => &mut self.gfx,
+      Module::Win => &mut self.win,
+    }
+  }
+}
@@ -0,0 +1,232 @@
+use crate::{v100, v101, v102, v103, v104b, v105, v106a, v106b, v107, v108, v109a, v109d};
+use crate::{v110, v111a, v111b, v112, v113c, v113d, v114a, v114b, v114c, v114d};
+use crate::{Addresses, BaseAddresses, Modules};
+use core::{fmt, str::FromStr};
+use xxhash_rust::xxh3::xxh3_64;
+
+/// The UTF-16 key identifying a `VS_VERSIONINFO` resource.
+const VERSION_INFO_KEY: &[u8; 32] = b"V\0S\0_\0V\0E\0R\0S\0I\0O\0N\0_\0I\0N\0F\0O\0\0\0";
+
+/// The version number stored in a module's `VS_FIXEDFILEINFO` resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileVersion {
+  pub ms: u32,
+  pub ls: u32,
+}
+impl FileVersion {
+  pub const fn new(ms: u32, ls: u32) -> Self {
+    Self { ms, ls }
+  }
+
+  /// Reads the version from a raw `VS_VERSIONINFO` resource.
+  pub fn from_version_info(data: &[u8]) -> Option<Self> {
+    // The size of `VS_FIXEDFILEINFO`.
+    const FIXED_INFO_LEN: usize = 52;
+    const SIGNATURE: u32 = 0xfeef04bd;
+
+    let read_u16 = |i: usize| Some(u16::from_le_bytes(data.get(i..i + 2)?.try_into().ok()?));
+    let len = usize::from(read_u16(0)?);
+    let value_len = usize::from(read_u16(2)?);
+    let data = data.get(..len)?;
+    if data.get(6..6 + VERSION_INFO_KEY.len())? != VERSION_INFO_KEY || value_len < FIXED_INFO_LEN {
+      return None;
+    }
+    // The value is aligned to four bytes from the start of the resource.
+    let info = (6 + VERSION_INFO_KEY.len() + 3) & !3;
+    let info = data.get(info..info + FIXED_INFO_LEN)?;
+    let read_u32 = |i: usize| u32::from_le_bytes(info[i..i + 4].try_into().unwrap());
+    (read_u32(0) == SIGNATURE).then(|| Self::new(read_u32(8), read_u32(12)))
+  }
+
+  /// Reads the version from the bytes of a PE file by searching for the
+  /// `VS_VERSIONINFO` resource.
+  pub fn from_file(data: &[u8]) -> Option<Self> {
+    // The key follows the resource's three `u16` header fields.
+    data
+      .windows(VERSION_INFO_KEY.len())
+      .enumerate()
+      .filter(|&(i, x)| i >= 6 && x == VERSION_INFO_KEY)
+      .find_map(|(i, _)| Self::from_version_info(&data[i - 6..]))
+  }
+}
+impl fmt::Display for FileVersion {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{}.{}.{}.{}",
+      self.ms >> 16,
+      self.ms & 0xFFFF,
+      self.ls >> 16,
+      self.ls & 0xFFFF
+    )
+  }
+}
+
+/// How the game's code is split between modules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModuleLayout {
+  /// Each module is a separate dll.
+  Split,
+  /// Every module is contained within `game.exe`.
+  Combined,
+}
+impl ModuleLayout {
+  pub fn load(self) -> Option<Modules> {
+    match self {
+      Self::Split => Modules::load_split_modules(),
+      Self::Combined => Modules::load_combined_module(),
+    }
+  }
+}
+
+macro_rules! decl_versions {
+  ($($version:ident($name:literal, $module:ident, $layout:ident, $is_expansion:literal)),* $(,)?) => {
+    /// A known version of the game.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub enum GameVersion {
+      $($version),*
+    }
+    impl GameVersion {
+      /// Every known version from oldest to newest.
+      pub const ALL: &'static [Self] = &[$(Self::$version),*];
+
+      /// Gets the version's name (e.g. `v1.10`).
+      pub const fn name(self) -> &'static str {
+        match self {
+          $(Self::$version => $name),*
+        }
+      }
+
+      pub const fn addresses(self) -> &'static Addresses {
+        match self {
+          $(Self::$version => &$module::ADDRESSES),*
+        }
+      }
+
+      pub const fn base_addresses(self) -> &'static BaseAddresses {
+        match self {
+          $(Self::$version => &$module::BASE_ADDRESSES),*
+        }
+      }
+
+      pub const fn module_layout(self) -> ModuleLayout {
+        match self {
+          $(Self::$version => ModuleLayout::$layout),*
+        }
+      }
+
+      /// Whether the version was released alongside the expansion (1.07 and
+      /// later).
+      pub const fn is_expansion(self) -> bool {
+        match self {
+          $(Self::$version => $is_expansion),*
+        }
+      }
+    }
+  };
+}
+decl_versions! {
+  V100("v1.00", v100, Split, false),
+  V101("v1.01", v101, Split, false),
+  V102("v1.02", v102, Split, false),
+  V103("v1.03", v103, Split, false),
+  V104b("v1.04b", v104b, Split, false),
+  V104c("v1.04c", v104b, Split, false),
+  V105a("v1.05a", v105, Split, false),
+  V105b("v1.05b", v105, Split, false),
+  V106a("v1.06a", v106a, Split, false),
+  V106b("v1.06b", v106b, Split, false),
+  V107("v1.07", v107, Split, true),
+  V108("v1.08", v108, Split, true),
+  V109a("v1.09a", v109a, Split, true),
+  V109b("v1.09b", v109a, Split, true),
+  V109d("v1.09d", v109d, Split, true),
+  V110("v1.10", v110, Split, true),
+  V111a("v1.11a", v111a, Split, true),
+  V111b("v1.11b", v111b, Split, true),
+  V112("v1.12", v112, Split, true),
+  V113c("v1.13c", v113c, Split, true),
+  V113d("v1.13d", v113d, Split, true),
+  V114a("v1.14a", v114a, Combined, true),
+  V114b("v1.14b", v114b, Combined, true),
+  V114c("v1.14c", v114c, Combined, true),
+  V114d("v1.14d", v114d, Combined, true),
+}
+
+impl GameVersion {
+  /// Detects the version from `game.exe`'s file version. Some versions share a
+  /// file version and can only be told apart by the xxh3 hash of `game.exe`,
+  /// which is only requested when needed.
+  pub fn from_file_version(
+    version: FileVersion,
+    file_hash: impl FnOnce() -> Option<u64>,
+  ) -> Option<Self> {
+    Some(match (version.ms, version.ls) {
+      (0x0001_0000, 0x0000_0001) => match file_hash()? {
+        0x5215437ecc8b67b9 => Self::V100,
+        0x1b093efaa009e78b => Self::V101,
+        _ => return None,
+      },
+      (0x0001_0000, 0x0002_0000) => Self::V102,
+      (0x0001_0000, 0x0003_0000) => Self::V103,
+      (0x0001_0000, 0x0004_0001) => Self::V104b,
+      (0x0001_0000, 0x0004_0002) => Self::V104c,
+      (0x0001_0000, 0x0005_0000) => Self::V105a,
+      (0x0001_0000, 0x0005_0001) => Self::V105b,
+      (0x0001_0000, 0x0006_0000) => match file_hash()? {
+        0x73645dbfe51df9ae => Self::V106a,
+        0x62fea87b064aec9e => Self::V106b,
+        _ => return None,
+      },
+      (0x0001_0000, 0x0007_0000) => Self::V107,
+      (0x0001_0000, 0x0008_001c) => Self::V108,
+      (0x0001_0000, 0x0009_0013) => Self::V109a,
+      (0x0001_0000, 0x0009_0014) => Self::V109b,
+      (0x0001_0000, 0x0009_0016) => Self::V109d,
+      // (0x0001_0000, 0x000a_0009) => 1.10b,
+      // (0x0001_0000, 0x000a_000a) => 1.10s,
+      (0x0001_0000, 0x000a_0027) => Self::V110,
+      (0x0001_0000, 0x000b_002d) => Self::V111a,
+      (0x0001_0000, 0x000b_002e) => Self::V111b,
+      (0x0001_0000, 0x000c_0031) => Self::V112,
+      // (0x0001_0000, 0x000d_0037) => 1.13a,
+      (0x0001_0000, 0x000d_003c) => Self::V113c,
+      (0x0001_0000, 0x000d_0040) => Self::V113d,
+      (0x0001_000e, 0x0000_0040) => Self::V114a,
+      (0x0001_000e, 0x0001_0044) => Self::V114b,
+      (0x0001_000e, 0x0002_0046) => Self::V114c,
+      (0x0001_000e, 0x0003_0047) => Self::V114d,
+      _ => return None,
+    })
+  }
+
+  /// Detects the version from the contents of `game.exe`.
+  pub fn from_file(data: &[u8]) -> Option<Self> {
+    Self::from_file_version(FileVersion::from_file(data)?, || Some(xxh3_64(data)))
+  }
+
+  /// Gets a version by its name (e.g. `v1.10`).
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::ALL.iter().copied().find(|x| x.name() == name)
+  }
+}
+impl fmt::Display for GameVersion {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.name())
+  }
+}
+impl FromStr for GameVersion {
+  type Err = UnknownVersion;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Self::from_name(s).ok_or(UnknownVersion)
+  }
+}
+
+/// The error returned when parsing the name of an unknown game version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownVersion;
+impl fmt::Display for UnknownVersion {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("unknown game version")
+  }
+}
@@ -0,0 +1,114 @@
+use d2interface::{
+  IntoSys, IsoP2d, LinearM2d, ScreenM, ScreenM2d, TileM2d, Viewport, SUBTILES_PER_TILE,
+};
+use num::{Fixed, Measure};
+
+fn iso(x: i32, y: i32) -> IsoP2d<i32> {
+  IsoP2d::new(Measure::new(x), Measure::new(y))
+}
+
+fn screen(x: i32, y: i32) -> ScreenM2d<i32> {
+  ScreenM2d::new(Measure::new(x), Measure::new(y))
+}
+
+/// Creates a linear position from its representation with eight fractional bits.
+fn linear(x: u32, y: u32) -> LinearM2d<Fixed<u32, 8>> {
+  LinearM2d::new(
+    Measure::new(Fixed::from_repr(x)),
+    Measure::new(Fixed::from_repr(y)),
+  )
+}
+
+fn tile(x: u32, y: u32) -> TileM2d<u32> {
+  TileM2d::new(Measure::new(x), Measure::new(y))
+}
+
+const ISO_POINTS: [(i32, i32); 7] = [
+  (0, 0),
+  (1, 0),
+  (0, 1),
+  (-1, 3),
+  (160, -240),
+  (-12345, 6789),
+  (0x7ff_ffff, -0x7ff_ffff),
+];
+
+#[test]
+fn iso_linear() {
+  // Ten linear units along each axis.
+  let p = linear(0xa00, 0x1400);
+  let i: IsoP2d<i32> = p.into_sys();
+  assert_eq!(i, iso(-160, 240));
+  assert_eq!(IntoSys::<LinearM2d<Fixed<u32, 8>>>::into_sys(i), p);
+
+  // Every isometric position fits when linear positions have five fractional
+  // bits, wrapping around when negative.
+  for (x, y) in ISO_POINTS {
+    let p: LinearM2d<Fixed<u32, 5>> = iso(x, y).into_sys();
+    assert_eq!(IntoSys::<IsoP2d<i32>>::into_sys(p), iso(x, y), "({x}, {y})");
+  }
+  // Extra precision leaves less room for the integer part.
+  for (x, y) in [(0, 0), (2, 1), (-2, 1), (1, 3), (-160, 240), (12345, 6789)] {
+    let p: LinearM2d<Fixed<u32, 8>> = iso(x, y).into_sys();
+    assert_eq!(IntoSys::<IsoP2d<i32>>::into_sys(p), iso(x, y), "({x}, {y})");
+  }
+
+  // The low bits are lost when converting from linear to isometric.
+  let i: IsoP2d<i32> = linear(0xa07, 0x1400).into_sys();
+  assert_eq!(i, iso(-160, 240));
+  let i: IsoP2d<i32> = linear(0xa08, 0x1400).into_sys();
+  assert_eq!(i, iso(-160, 240));
+  assert_eq!(
+    IntoSys::<LinearM2d<Fixed<u32, 8>>>::into_sys(i),
+    linear(0xa00, 0x1400)
+  );
+}
+
+#[test]
+fn linear_tile() {
+  let t: TileM2d<u32> = linear(0x1180, 0x1b00).into_sys();
+  assert_eq!(t, tile(3, 5));
+  let t: TileM2d<u32> = linear(0xeff, 0x1400).into_sys();
+  assert_eq!(t, tile(2, 4));
+
+  for (x, y) in [(0, 0), (1, 2), (3, 5), (1000, 2000)] {
+    let p: LinearM2d<Fixed<u32, 8>> = tile(x, y).into_sys();
+    assert_eq!(
+      p,
+      linear((x * SUBTILES_PER_TILE) << 8, (y * SUBTILES_PER_TILE) << 8)
+    );
+    assert_eq!(IntoSys::<TileM2d<u32>>::into_sys(p), tile(x, y));
+  }
+}
+
+#[test]
+fn viewport() {
+  let viewport = Viewport {
+    center: iso(1000, -500),
+    size: ScreenM2d::new(Measure::new(800), Measure::new(600)),
+    shift: ScreenM::new(-160),
+  };
+  assert_eq!(viewport.iso_to_screen(iso(1000, -500)), screen(240, 300));
+  assert_eq!(viewport.iso_to_screen(iso(1010, -480)), screen(250, 320));
+  assert_eq!(viewport.screen_to_iso(screen(0, 0)), iso(760, -800));
+
+  for (x, y) in ISO_POINTS {
+    let p = iso(x, y);
+    assert_eq!(
+      viewport.screen_to_iso(viewport.iso_to_screen(p)),
+      p,
+      "({x}, {y})"
+    );
+    let s = screen(x, y);
+    assert_eq!(
+      viewport.iso_to_screen(viewport.screen_to_iso(s)),
+      s,
+      "({x}, {y})"
+    );
+  }
+
+  let p = linear(0xa00, 0x1400);
+  let s = viewport.linear_to_screen(p);
+  assert_eq!(s, viewport.iso_to_screen(iso(-160, 240)));
+  assert_eq!(viewport.screen_to_linear::<8>(s), p);
+}
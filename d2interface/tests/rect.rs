@@ -0,0 +1,175 @@
+use d2interface::{Range, Rect, RectLr, RectS, ScreenM, ScreenRectS};
+use num::{M2d, Measure};
+
+fn bounds<T: Copy>(r: Range<T>) -> (T, T) {
+  (r.min, r.max)
+}
+
+fn lr_bounds<T: Copy>(r: RectLr<T>) -> ((T, T), (T, T)) {
+  (bounds(r.x), bounds(r.y))
+}
+
+fn screen<T>(x: T, y: T) -> M2d<ScreenM<T>> {
+  M2d::new(Measure::new(x), Measure::new(y))
+}
+
+#[test]
+fn range() {
+  let r = Range::new(2u32, 5);
+  assert!(!r.is_empty());
+  assert!(r.contains(2) && r.contains(5));
+  assert!(!r.contains(1) && !r.contains(6));
+  assert_eq!(r.clamp(9), 5);
+  assert_eq!(r.size(), 4);
+  assert_eq!(r.iter().collect::<Vec<_>>(), [2, 3, 4, 5]);
+  assert_eq!(r.intersect(&Range::new(4, 8)).map(bounds), Some((4, 5)));
+  assert!(r.intersect(&Range::new(6, 8)).is_none());
+  assert_eq!(bounds(r.union(&Range::new(7, 8))), (2, 8));
+
+  let empty = Range::new(1u32, 0);
+  assert!(empty.is_empty());
+  assert_eq!(empty.size(), 0);
+  assert_eq!(empty.iter().count(), 0);
+
+  // Iterating up to the type's maximum doesn't overflow.
+  assert_eq!(
+    Range::new(254u8, 255).iter().collect::<Vec<_>>(),
+    [254, 255]
+  );
+}
+
+#[test]
+fn range_expand() {
+  assert_eq!(bounds(Range::new(2u32, 5).expand(1)), (1, 6));
+  assert_eq!(bounds(Range::new(2u32, 5).shrink(1)), (3, 4));
+  // Bounds stop at the limits of the type.
+  assert_eq!(bounds(Range::new(1u32, 5).expand(3)), (0, 8));
+  assert_eq!(bounds(Range::new(0u8, 254).expand(3)), (0, 255));
+  assert_eq!(bounds(Range::new(-126i8, 0).expand(3)), (-128, 3));
+  let r = Range::new(0u32, 1).shrink(3);
+  assert_eq!(bounds(r), (3, 0));
+  assert!(r.is_empty());
+}
+
+#[test]
+fn range_from_pos_size() {
+  assert_eq!(bounds(Range::from_pos_size(3u32, 4)), (3, 6));
+  assert_eq!(bounds(Range::from_pos_size(3u32, 1)), (3, 3));
+  assert_eq!(bounds(Range::from_pos_size(200u8, 56)), (200, 255));
+  // A size of zero or less gives an empty range without underflowing.
+  for (pos, size) in [(0u32, 0), (5, 0), (u32::MAX, 0)] {
+    assert!(
+      Range::from_pos_size(pos, size).is_empty(),
+      "({pos}, {size})"
+    );
+  }
+  for (pos, size) in [(0i32, 0), (i32::MIN, 0), (i32::MAX, -1), (-5, -5)] {
+    assert!(
+      Range::from_pos_size(pos, size).is_empty(),
+      "({pos}, {size})"
+    );
+  }
+}
+
+#[test]
+fn range_size_limits() {
+  // Sizes which don't fit saturate instead of overflowing.
+  assert_eq!(Range::new(i32::MIN, i32::MAX).size(), i32::MAX);
+  assert_eq!(Range::new(-1i32, i32::MAX).size(), i32::MAX);
+  assert_eq!(Range::new(0i32, i32::MAX - 1).size(), i32::MAX);
+  assert_eq!(Range::new(0u8, 255).size(), 255);
+  assert_eq!(Range::new(1u8, 255).size(), 255);
+  assert_eq!(
+    bounds(Range::from_pos_size(i32::MAX - 1, 10)),
+    (i32::MAX - 1, i32::MAX)
+  );
+  assert_eq!(
+    bounds(Range::from_pos_size(i32::MAX, i32::MAX)),
+    (i32::MAX, i32::MAX)
+  );
+
+  let s = RectS::new(screen(i32::MAX - 2, 0), screen(10, i32::MAX));
+  let lr = RectLr::from(s);
+  assert_eq!(
+    (bounds(lr.x.map(|x| x.0)), bounds(lr.y.map(|y| y.0))),
+    ((i32::MAX - 2, i32::MAX), (0, i32::MAX - 1))
+  );
+  assert!(s.contains(screen(i32::MAX, i32::MAX - 1)));
+  let s2: ScreenRectS<i32, i32> = lr.into();
+  assert_eq!(s2.size, screen(3, i32::MAX));
+}
+
+#[test]
+fn rect_lr() {
+  let r = RectLr::new(Range::new(1u32, 3), Range::new(10, 11));
+  assert!(!r.is_empty());
+  assert!(r.contains(M2d::new(1, 11)) && r.contains(M2d::new(3, 10)));
+  assert!(!r.contains(M2d::new(4, 10)) && !r.contains(M2d::new(2, 12)));
+  assert_eq!(r.clamp(M2d::new(0, 20)), M2d::new(1, 11));
+  assert_eq!(
+    r.points().collect::<Vec<_>>(),
+    [(1, 10), (2, 10), (3, 10), (1, 11), (2, 11), (3, 11)].map(|(x, y)| M2d::new(x, y))
+  );
+
+  let other = RectLr::new(Range::new(3, 8), Range::new(0, 10));
+  assert_eq!(r.intersect(&other).map(lr_bounds), Some(((3, 3), (10, 10))));
+  assert_eq!(lr_bounds(r.union(&other)), ((1, 8), (0, 11)));
+  assert!(r
+    .intersect(&RectLr::new(Range::new(4, 5), Range::new(10, 11)))
+    .is_none());
+
+  assert_eq!(lr_bounds(r.expand(M2d::new(2, 1))), ((0, 5), (9, 12)));
+  assert_eq!(lr_bounds(r.shrink(M2d::new(1, 0))), ((2, 2), (10, 11)));
+
+  let empty = RectLr::new(Range::new(1u32, 3), Range::new(1, 0));
+  assert!(empty.is_empty());
+  assert_eq!(empty.points().count(), 0);
+  assert!(!empty.contains(M2d::new(1, 0)));
+}
+
+#[test]
+fn rect_conversions() {
+  let s = RectS::new(M2d::new(1u32, 10), M2d::new(3u32, 2));
+  let lr = RectLr::from(s);
+  assert_eq!(lr_bounds(lr), ((1, 3), (10, 11)));
+  let r = Rect::from(s);
+  assert_eq!(
+    (r.upper_left, r.lower_right),
+    (M2d::new(1, 10), M2d::new(3, 11))
+  );
+  let s2: RectS<u32, u32> = lr.into();
+  assert_eq!((s2.pos, s2.size), (s.pos, s.size));
+  let s2: RectS<u32, u32> = r.into();
+  assert_eq!((s2.pos, s2.size), (s.pos, s.size));
+
+  // Zero sized rectangles are empty rather than underflowing.
+  let s = RectS::new(M2d::new(0u32, 0), M2d::new(0u32, 0));
+  assert!(s.is_empty());
+  assert!(RectLr::from(s).is_empty());
+  assert!(!s.contains(M2d::new(0, 0)));
+  let s2: RectS<u32, u32> = RectLr::from(s).into();
+  assert_eq!(s2.size, M2d::new(0, 0));
+}
+
+#[test]
+fn rect_mixed_types() {
+  // Positions and sizes can have different types.
+  let r: ScreenRectS<i32, u8> = RectS::new(screen(-10, 20), screen(200, 0));
+  assert!(r.is_empty());
+  assert!(!r.contains(screen(-10, 20)));
+
+  let r: ScreenRectS<i32, u8> = RectS::new(screen(-10, 20), screen(200, 5));
+  assert!(!r.is_empty());
+  assert!(r.contains(screen(-10, 20)) && r.contains(screen(189, 24)));
+  assert!(!r.contains(screen(190, 24)) && !r.contains(screen(-10, 25)));
+  let lr = RectLr::from(r);
+  assert_eq!(
+    lr_bounds(lr),
+    (
+      (Measure::new(-10), Measure::new(189)),
+      (Measure::new(20), Measure::new(24))
+    )
+  );
+  let r2: ScreenRectS<i32, u8> = lr.into();
+  assert_eq!((r2.pos, r2.size), (r.pos, r.size));
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "serde")]
+
+use d2interface::{CursorState, EntityKind, GameType};
+
+#[test]
+fn enum_names() {
+  assert_eq!(serde_json::to_string(&EntityKind::Npc).unwrap(), r#""Npc""#);
+  assert_eq!(
+    serde_json::to_string(&GameType::OpenBnetHost).unwrap(),
+    r#""OpenBnetHost""#
+  );
+  assert_eq!(
+    serde_json::from_str::<EntityKind>(r#""Missile""#).unwrap(),
+    EntityKind::Missile
+  );
+  assert_eq!(
+    serde_json::from_str::<CursorState>(r#""MouseDown""#).unwrap(),
+    CursorState::MouseDown
+  );
+  // Names are case sensitive.
+  assert!(serde_json::from_str::<EntityKind>(r#""npc""#).is_err());
+  let e = serde_json::from_str::<EntityKind>(r#""Player""#).unwrap_err();
+  assert!(e.to_string().contains("unknown variant `Player`"), "{e}");
+}
+
+#[test]
+fn enum_numbers() {
+  // Unknown values are serialized as numbers.
+  assert_eq!(serde_json::to_string(&EntityKind(42)).unwrap(), "42");
+  assert_eq!(serde_json::to_string(&CursorState(0)).unwrap(), "0");
+  assert_eq!(
+    serde_json::from_str::<EntityKind>("42").unwrap(),
+    EntityKind(42)
+  );
+  // Known values can still be deserialized from numbers.
+  assert_eq!(
+    serde_json::from_str::<EntityKind>("1").unwrap(),
+    EntityKind::Npc
+  );
+  for json in ["-1", "4294967296", "1.5", "null", "[1]"] {
+    assert!(
+      serde_json::from_str::<EntityKind>(json).is_err(),
+      "`{json}`"
+    );
+  }
+}
+
+#[test]
+fn enum_round_trip() {
+  let values = [
+    EntityKind::Pc,
+    EntityKind::Tile,
+    EntityKind(6),
+    EntityKind(u32::MAX),
+  ];
+  let json = serde_json::to_string(&values).unwrap();
+  assert_eq!(json, r#"["Pc","Tile",6,4294967295]"#);
+  assert_eq!(
+    serde_json::from_str::<[EntityKind; 4]>(&json).unwrap(),
+    values
+  );
+}
@@ -0,0 +1,127 @@
+use d2interface::{
+  AddressValue, Client, FileVersion, GameVersion, Module, ModuleLayout, UnknownVersion,
+};
+
+/// Builds a `VS_VERSIONINFO` resource containing only the fixed file info.
+fn version_info(ms: u32, ls: u32) -> Vec<u8> {
+  let mut data = Vec::new();
+  data.extend_from_slice(&[0x5c, 0, 0x34, 0, 0, 0]);
+  data.extend("VS_VERSION_INFO\0".encode_utf16().flat_map(u16::to_le_bytes));
+  data.extend_from_slice(&[0, 0]);
+  data.extend_from_slice(&0xfeef04bdu32.to_le_bytes());
+  data.extend_from_slice(&0x0001_0000u32.to_le_bytes());
+  data.extend_from_slice(&ms.to_le_bytes());
+  data.extend_from_slice(&ls.to_le_bytes());
+  data.extend_from_slice(&[0; 0x24]);
+  data
+}
+
+#[test]
+fn file_version() {
+  let info = version_info(0x0001_0000, 0x000a_0027);
+  let version = FileVersion::from_version_info(&info).unwrap();
+  assert_eq!(version, FileVersion::new(0x0001_0000, 0x000a_0027));
+  assert_eq!(version.to_string(), "1.0.10.39");
+
+  let mut file = vec![0xcc; 0x123];
+  file.extend_from_slice(&info);
+  assert_eq!(FileVersion::from_file(&file), Some(version));
+  assert_eq!(FileVersion::from_file(&file[..0x150]), None);
+  assert_eq!(FileVersion::from_file(&[0; 0x100]), None);
+
+  // The key can appear elsewhere in the file without a valid resource.
+  let mut file = vec![0xcc; 8];
+  file.extend("VS_VERSION_INFO\0".encode_utf16().flat_map(u16::to_le_bytes));
+  file.extend_from_slice(&[0xcc; 0x11]);
+  file.extend_from_slice(&info);
+  assert_eq!(FileVersion::from_file(&file), Some(version));
+}
+
+#[test]
+fn invalid_version_info() {
+  let info = version_info(0x0001_0000, 0x000a_0027);
+  let with = |i: usize, bytes: &[u8]| {
+    let mut info = info.clone();
+    info[i..i + bytes.len()].copy_from_slice(bytes);
+    FileVersion::from_version_info(&info)
+  };
+  // The resource is shorter than its length.
+  assert_eq!(FileVersion::from_version_info(&info[..0x5b]), None);
+  assert_eq!(with(0, &[0x40, 0]), None);
+  // There's no fixed file info.
+  assert_eq!(with(2, &[0, 0]), None);
+  // The key is wrong.
+  assert_eq!(with(6, b"X"), None);
+  // The fixed file info doesn't start at the aligned position.
+  let mut shifted = info.clone();
+  shifted.remove(38);
+  shifted.push(0);
+  assert_eq!(FileVersion::from_version_info(&shifted), None);
+  assert_eq!(with(40, &[0; 4]), None);
+  // Trailing data past the resource's length is ignored.
+  let mut info = info.clone();
+  info.extend_from_slice(&[0xcc; 8]);
+  assert!(FileVersion::from_version_info(&info).is_some());
+}
+
+#[test]
+fn game_version() {
+  let detect = |ms, ls, hash| GameVersion::from_file_version(FileVersion::new(ms, ls), || hash);
+  assert_eq!(
+    detect(0x0001_0000, 0x000a_0027, None),
+    Some(GameVersion::V110)
+  );
+  assert_eq!(
+    detect(0x0001_000e, 0x0003_0047, None),
+    Some(GameVersion::V114d)
+  );
+  assert_eq!(detect(0x0001_0000, 0x0000_0001, None), None);
+  assert_eq!(
+    detect(0x0001_0000, 0x0000_0001, Some(0x1b093efaa009e78b)),
+    Some(GameVersion::V101),
+  );
+  assert_eq!(
+    detect(0x0001_0000, 0x0006_0000, Some(0x73645dbfe51df9ae)),
+    Some(GameVersion::V106a),
+  );
+  assert_eq!(detect(0x0001_0000, 0x0006_0000, Some(0)), None);
+  assert_eq!(detect(0x0002_0000, 0x0000_0000, None), None);
+
+  let file = version_info(0x0001_0000, 0x000c_0031);
+  assert_eq!(GameVersion::from_file(&file), Some(GameVersion::V112));
+
+  for &version in GameVersion::ALL {
+    assert_eq!(GameVersion::from_name(version.name()), Some(version));
+    assert_eq!(version.to_string(), version.name());
+  }
+  assert_eq!(GameVersion::from_name("v1.13a"), None);
+  assert_eq!("v1.10".parse(), Ok(GameVersion::V110));
+  assert_eq!("v1.13a".parse::<GameVersion>(), Err(UnknownVersion));
+  assert_eq!(UnknownVersion.to_string(), "unknown game version");
+
+  assert!(!GameVersion::V106b.is_expansion());
+  assert!(GameVersion::V107.is_expansion());
+  assert_eq!(GameVersion::V113d.module_layout(), ModuleLayout::Split);
+  assert_eq!(GameVersion::V114a.module_layout(), ModuleLayout::Combined);
+}
+
+#[test]
+fn set_addresses() {
+  let mut addresses = GameVersion::V110.addresses().clone();
+  assert!(addresses.set("player", AddressValue::Offset(0x1234)));
+  assert_eq!(
+    unsafe { addresses.player(Client::default()) }.as_ptr() as usize,
+    0x1234
+  );
+  assert!(addresses.set("draw_menu", AddressValue::Offset(0x10)));
+  assert!(addresses.set("draw_menu", AddressValue::Ordinal(10019)));
+  // Only exported functions can be found by ordinal.
+  assert!(!addresses.set("player", AddressValue::Ordinal(10019)));
+  assert!(!addresses.set("missing", AddressValue::Offset(0)));
+
+  let mut base = GameVersion::V110.base_addresses().clone();
+  base[Module::Win] = 0x6f8e0000;
+  assert_eq!(base.win, 0x6f8e0000);
+  base[Module::GameExe] = 0x400000;
+  assert_eq!(base[Module::Client], 0x400000);
+}
@@ -0,0 +1,195 @@
+use crate::Fixed;
+use core::{
+  f64::consts::PI,
+  ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+/// An angle in the game's format. Stored as a multiple of π radians with eight
+/// fractional bits, so a full turn is `0x200`. Arithmetic wraps around.
+///
+/// The representation isn't limited to a single turn; only the low nine bits
+/// are used when calculating with the angle.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(
+  feature = "serde",
+  derive(serde::Serialize, serde::Deserialize),
+  serde(transparent)
+)]
+#[repr(transparent)]
+pub struct Angle(Fixed<u32, 8>);
+impl Angle {
+  /// The size of a full turn.
+  pub const TURN: u32 = 0x200;
+
+  #[inline]
+  pub const fn from_repr(x: u32) -> Self {
+    Self(Fixed::from_repr(x))
+  }
+
+  #[inline]
+  pub fn repr(self) -> u32 {
+    self.0.repr()
+  }
+
+  /// Gets the angle's representation within a single turn.
+  #[inline]
+  pub fn normalized(self) -> u32 {
+    self.repr() & (Self::TURN - 1)
+  }
+
+  /// Gets the sine of the angle using `SINE_TABLE`.
+  #[inline]
+  pub fn sin(self) -> f32 {
+    SINE_TABLE[self.normalized() as usize]
+  }
+
+  /// Gets the cosine of the angle using `SINE_TABLE`.
+  #[inline]
+  pub fn cos(self) -> f32 {
+    (self + Self::from_repr(Self::TURN / 4)).sin()
+  }
+
+  #[inline]
+  pub fn to_radians(self) -> f64 {
+    f64::from(self.normalized()) * (PI / 256.0)
+  }
+}
+
+impl From<Fixed<u32, 8>> for Angle {
+  #[inline]
+  fn from(x: Fixed<u32, 8>) -> Self {
+    Self(x)
+  }
+}
+impl From<Angle> for Fixed<u32, 8> {
+  #[inline]
+  fn from(x: Angle) -> Self {
+    x.0
+  }
+}
+
+impl Add for Angle {
+  type Output = Self;
+  #[inline]
+  fn add(self, rhs: Self) -> Self::Output {
+    Self::from_repr(self.repr().wrapping_add(rhs.repr()))
+  }
+}
+impl AddAssign for Angle {
+  #[inline]
+  fn add_assign(&mut self, rhs: Self) {
+    *self = *self + rhs;
+  }
+}
+
+impl Sub for Angle {
+  type Output = Self;
+  #[inline]
+  fn sub(self, rhs: Self) -> Self::Output {
+    Self::from_repr(self.repr().wrapping_sub(rhs.repr()))
+  }
+}
+impl SubAssign for Angle {
+  #[inline]
+  fn sub_assign(&mut self, rhs: Self) {
+    *self = *self - rhs;
+  }
+}
+
+impl Neg for Angle {
+  type Output = Self;
+  #[inline]
+  fn neg(self) -> Self::Output {
+    Self::from_repr(self.repr().wrapping_neg())
+  }
+}
+
+impl Mul<u32> for Angle {
+  type Output = Self;
+  #[inline]
+  fn mul(self, rhs: u32) -> Self::Output {
+    Self::from_repr(self.repr().wrapping_mul(rhs))
+  }
+}
+impl MulAssign<u32> for Angle {
+  #[inline]
+  fn mul_assign(&mut self, rhs: u32) {
+    *self = *self * rhs;
+  }
+}
+
+/// A copy of the game's sine table (`Fog::sine_table`). The game computes each
+/// entry as the `f64` sine of `i * π / 256` rounded to an `f32`. Every entry is
+/// far enough from an `f32` rounding boundary that any accurate `f64` sine gives
+/// the same table, so this matches the game's bit for bit.
+#[rustfmt::skip]
+pub static SINE_TABLE: [f32; Angle::TURN as usize] = [
+  0.0, 0.012271538, 0.024541229, 0.036807224, 0.049067676, 0.061320737, 0.07356457, 0.08579731,
+  0.09801714, 0.110222206, 0.12241068, 0.1345807, 0.14673047, 0.15885815, 0.17096189, 0.18303989,
+  0.19509032, 0.20711137, 0.21910124, 0.2310581, 0.24298018, 0.25486565, 0.26671275, 0.2785197,
+  0.29028466, 0.30200595, 0.31368175, 0.3253103, 0.33688986, 0.34841868, 0.35989505, 0.3713172,
+  0.38268343, 0.39399204, 0.4052413, 0.41642955, 0.42755508, 0.43861625, 0.44961134, 0.46053872,
+  0.47139674, 0.48218378, 0.4928982, 0.50353837, 0.51410276, 0.52458966, 0.53499764, 0.545325,
+  0.55557024, 0.5657318, 0.57580817, 0.58579785, 0.5956993, 0.60551107, 0.6152316, 0.6248595,
+  0.6343933, 0.64383155, 0.65317285, 0.6624158, 0.671559, 0.680601, 0.68954057, 0.69837624,
+  0.70710677, 0.71573085, 0.7242471, 0.7326543, 0.7409511, 0.7491364, 0.7572088, 0.76516724,
+  0.77301043, 0.7807372, 0.7883464, 0.7958369, 0.8032075, 0.81045717, 0.8175848, 0.8245893,
+  0.8314696, 0.8382247, 0.8448536, 0.8513552, 0.8577286, 0.86397284, 0.87008697, 0.8760701,
+  0.8819213, 0.88763964, 0.8932243, 0.8986745, 0.9039893, 0.909168, 0.9142098, 0.9191139,
+  0.9238795, 0.9285061, 0.9329928, 0.937339, 0.94154406, 0.9456073, 0.94952816, 0.953306,
+  0.95694035, 0.9604305, 0.96377605, 0.96697646, 0.97003126, 0.97293997, 0.9757021, 0.9783174,
+  0.98078525, 0.9831055, 0.98527765, 0.9873014, 0.9891765, 0.99090266, 0.99247956, 0.993907,
+  0.9951847, 0.9963126, 0.99729043, 0.9981181, 0.99879545, 0.99932235, 0.9996988, 0.9999247, 1.0,
+  0.9999247, 0.9996988, 0.99932235, 0.99879545, 0.9981181, 0.99729043, 0.9963126, 0.9951847,
+  0.993907, 0.99247956, 0.99090266, 0.9891765, 0.9873014, 0.98527765, 0.9831055, 0.98078525,
+  0.9783174, 0.9757021, 0.97293997, 0.97003126, 0.96697646, 0.96377605, 0.9604305, 0.95694035,
+  0.953306, 0.94952816, 0.9456073, 0.94154406, 0.937339, 0.9329928, 0.9285061, 0.9238795,
+  0.9191139, 0.9142098, 0.909168, 0.9039893, 0.8986745, 0.8932243, 0.88763964, 0.8819213,
+  0.8760701, 0.87008697, 0.86397284, 0.8577286, 0.8513552, 0.8448536, 0.8382247, 0.8314696,
+  0.8245893, 0.8175848, 0.81045717, 0.8032075, 0.7958369, 0.7883464, 0.7807372, 0.77301043,
+  0.76516724, 0.7572088, 0.7491364, 0.7409511, 0.7326543, 0.7242471, 0.71573085, 0.70710677,
+  0.69837624, 0.68954057, 0.680601, 0.671559, 0.6624158, 0.65317285, 0.64383155, 0.6343933,
+  0.6248595, 0.6152316, 0.60551107, 0.5956993, 0.58579785, 0.57580817, 0.5657318, 0.55557024,
+  0.545325, 0.53499764, 0.52458966, 0.51410276, 0.50353837, 0.4928982, 0.48218378, 0.47139674,
+  0.46053872, 0.44961134, 0.43861625, 0.42755508, 0.41642955, 0.4052413, 0.39399204, 0.38268343,
+  0.3713172, 0.35989505, 0.34841868, 0.33688986, 0.3253103, 0.31368175, 0.30200595, 0.29028466,
+  0.2785197, 0.26671275, 0.25486565, 0.24298018, 0.2310581, 0.21910124, 0.20711137, 0.19509032,
+  0.18303989, 0.17096189, 0.15885815, 0.14673047, 0.1345807, 0.12241068, 0.110222206, 0.09801714,
+  0.08579731, 0.07356457, 0.061320737, 0.049067676, 0.036807224, 0.024541229, 0.012271538,
+  1.2246469e-16, -0.012271538, -0.024541229, -0.036807224, -0.049067676, -0.061320737, -0.07356457,
+  -0.08579731, -0.09801714, -0.110222206, -0.12241068, -0.1345807, -0.14673047, -0.15885815,
+  -0.17096189, -0.18303989, -0.19509032, -0.20711137, -0.21910124, -0.2310581, -0.24298018,
+  -0.25486565, -0.26671275, -0.2785197, -0.29028466, -0.30200595, -0.31368175, -0.3253103,
+  -0.33688986, -0.34841868, -0.35989505, -0.3713172, -0.38268343, -0.39399204, -0.4052413,
+  -0.41642955, -0.42755508, -0.43861625, -0.44961134, -0.46053872, -0.47139674, -0.48218378,
+  -0.4928982, -0.50353837, -0.51410276, -0.52458966, -0.53499764, -0.545325, -0.55557024,
+  -0.5657318, -0.57580817, -0.58579785, -0.5956993, -0.60551107, -0.6152316, -0.6248595,
+  -0.6343933, -0.64383155, -0.65317285, -0.6624158, -0.671559, -0.680601, -0.68954057, -0.69837624,
+  -0.70710677, -0.71573085, -0.7242471, -0.7326543, -0.7409511, -0.7491364, -0.7572088,
+  -0.76516724, -0.77301043, -0.7807372, -0.7883464, -0.7958369, -0.8032075, -0.81045717,
+  -0.8175848, -0.8245893, -0.8314696, -0.8382247, -0.8448536, -0.8513552, -0.8577286, -0.86397284,
+  -0.87008697, -0.8760701, -0.8819213, -0.88763964, -0.8932243, -0.8986745, -0.9039893, -0.909168,
+  -0.9142098, -0.9191139, -0.9238795, -0.9285061, -0.9329928, -0.937339, -0.94154406, -0.9456073,
+  -0.94952816, -0.953306, -0.95694035, -0.9604305, -0.96377605, -0.96697646, -0.97003126,
+  -0.97293997, -0.9757021, -0.9783174, -0.98078525, -0.9831055, -0.98527765, -0.9873014,
+  -0.9891765, -0.99090266, -0.99247956, -0.993907, -0.9951847, -0.9963126, -0.99729043, -0.9981181,
+  -0.99879545, -0.99932235, -0.9996988, -0.9999247, -1.0, -0.9999247, -0.9996988, -0.99932235,
+  -0.99879545, -0.9981181, -0.99729043, -0.9963126, -0.9951847, -0.993907, -0.99247956,
+  -0.99090266, -0.9891765, -0.9873014, -0.98527765, -0.9831055, -0.98078525, -0.9783174,
+  -0.9757021, -0.97293997, -0.97003126, -0.96697646, -0.96377605, -0.9604305, -0.95694035,
+  -0.953306, -0.94952816, -0.9456073, -0.94154406, -0.937339, -0.9329928, -0.9285061, -0.9238795,
+  -0.9191139, -0.9142098, -0.909168, -0.9039893, -0.8986745, -0.8932243, -0.88763964, -0.8819213,
+  -0.8760701, -0.87008697, -0.86397284, -0.8577286, -0.8513552, -0.8448536, -0.8382247, -0.8314696,
+  -0.8245893, -0.8175848, -0.81045717, -0.8032075, -0.7958369, -0.7883464, -0.7807372, -0.77301043,
+  -0.76516724, -0.7572088, -0.7491364, -0.7409511, -0.7326543, -0.7242471, -0.71573085,
+  -0.70710677, -0.69837624, -0.68954057, -0.680601, -0.671559, -0.6624158, -0.65317285,
+  -0.64383155, -0.6343933, -0.6248595, -0.6152316, -0.60551107, -0.5956993, -0.58579785,
+  -0.57580817, -0.5657318, -0.55557024, -0.545325, -0.53499764, -0.52458966, -0.51410276,
+  -0.50353837, -0.4928982, -0.48218378, -0.47139674, -0.46053872, -0.44961134, -0.43861625,
+  -0.42755508, -0.41642955, -0.4052413, -0.39399204, -0.38268343, -0.3713172, -0.35989505,
+  -0.34841868, -0.33688986, -0.3253103, -0.31368175, -0.30200595, -0.29028466, -0.2785197,
+  -0.26671275, -0.25486565, -0.24298018, -0.2310581, -0.21910124, -0.20711137, -0.19509032,
+  -0.18303989, -0.17096189, -0.15885815, -0.14673047, -0.1345807, -0.12241068, -0.110222206,
+  -0.09801714, -0.08579731, -0.07356457, -0.061320737, -0.049067676, -0.036807224, -0.024541229,
+  -0.012271538,
+];
@@ -1,11 +1,13 @@
 use crate::{
-  CheckedAdd, ExInt, MulTrunc, WithLargestBitSize, WrappingAbs, WrappingAdd, WrappingDiv,
-  WrappingFrom, WrappingInto, WrappingMul, WrappingSub,
+  CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, ExInt, MulTrunc, SaturatingAdd, SaturatingDiv,
+  SaturatingMul, SaturatingSub, Scale, Unitless, WithLargestBitSize, WrappingAbs, WrappingAdd,
+  WrappingDiv, WrappingFrom, WrappingInto, WrappingMul, WrappingSub,
 };
 use bytemuck::TransparentWrapper;
 use core::{
   fmt,
   ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Shl, Shr, Sub, SubAssign},
+  str::FromStr,
 };
 
 /// A fixed-point number with `N` bits of precision.
@@ -184,7 +186,15 @@ impl_op_rev!(
 );
 impl_op_rev!(MulTrunc, mul_trunc, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
 
+impl_op!(SaturatingAdd, sadd, Self, 0);
+impl_op!(SaturatingSub, ssub, Self, 0);
+impl_op!(SaturatingMul, smul, T);
+impl_op!(SaturatingDiv, sdiv, T);
+
 impl_cop!(CheckedAdd, cadd, Self, 0);
+impl_cop!(CheckedSub, csub, Self, 0);
+impl_cop!(CheckedMul, cmul, T);
+impl_cop!(CheckedDiv, cdiv, T);
 
 impl_op_assign!(AddAssign, add_assign, Self, 0);
 impl_op_assign!(SubAssign, sub_assign, Self, 0);
@@ -247,6 +257,33 @@ where
   }
 }
 
+impl<T: ExInt + TryFrom<T::ExInt>, const N: u8> CheckedMul for Fixed<T, N>
+where
+  T::ExInt: Mul<Output = T::ExInt> + Shr<u8, Output = T::ExInt> + WrappingFrom<T>,
+{
+  type Output = Self;
+  #[inline]
+  fn cmul(self, rhs: Self) -> Option<Self::Output> {
+    T::try_from((T::ExInt::wfrom(self.0) * T::ExInt::wfrom(rhs.0)) >> N)
+      .ok()
+      .map(Self)
+  }
+}
+
+impl<T: ExInt + TryFrom<T::ExInt>, const N: u8> CheckedDiv for Fixed<T, N>
+where
+  T::ExInt: CheckedDiv<Output = T::ExInt> + Shl<u8, Output = T::ExInt> + WrappingFrom<T>,
+{
+  type Output = Self;
+  #[inline]
+  fn cdiv(self, rhs: Self) -> Option<Self::Output> {
+    (T::ExInt::wfrom(self.0) << N)
+      .cdiv(T::ExInt::wfrom(rhs.0))
+      .and_then(|x| T::try_from(x).ok())
+      .map(Self)
+  }
+}
+
 impl<T: ExInt + WrappingFrom<T::ExInt>, const N: u8, const M: u8> MulTrunc<Fixed<T, M>>
   for Fixed<T, N>
 where
@@ -270,6 +307,229 @@ where
   }
 }
 
+impl<T: Scale<Fixed<U, M>>, U, const N: u8, const M: u8> Scale<Fixed<U, M>> for Fixed<T, N> {
+  #[inline]
+  fn scale(self, rhs: Fixed<U, M>) -> Self {
+    Self(self.0.scale(rhs))
+  }
+}
+
+macro_rules! impl_int_scale {
+  ($($ty:ty),*) => {$(
+    impl<U, const N: u8> Scale<Fixed<U, N>> for $ty
+    where
+      <$ty as ExInt>::ExInt: From<U>,
+    {
+      #[inline]
+      fn scale(self, rhs: Fixed<U, N>) -> Self {
+        type Ex = <$ty as ExInt>::ExInt;
+        ((self as Ex * Ex::from(rhs.0)) >> N) as $ty
+      }
+    }
+  )*};
+}
+impl_int_scale!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl<T, const N: u8> Unitless for Fixed<T, N> {
+  type Output = Self;
+  #[inline]
+  fn unitless(self) -> Self {
+    self
+  }
+}
+
+/// Calculates the integer square root, rounded down.
+fn isqrt(x: u128) -> u128 {
+  if x < 2 {
+    return x;
+  }
+  // Newton's method starting from a power of two above the root.
+  let mut root = 1u128 << ((129 - x.leading_zeros()) / 2);
+  loop {
+    let next = (root + x / root) / 2;
+    if next >= root {
+      return root;
+    }
+    root = next;
+  }
+}
+
+/// An error which can be returned when parsing a fixed-point number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseFixedError {
+  /// The text isn't a decimal number.
+  Invalid,
+  /// The number is outside the range of the type.
+  Overflow,
+}
+impl fmt::Display for ParseFixedError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Self::Invalid => "invalid fixed-point number",
+      Self::Overflow => "number too large to fit in the fixed-point type",
+    })
+  }
+}
+
+/// Parses a decimal number into the representation of a fixed-point number
+/// with `n` fractional bits. The result is rounded to the nearest
+/// representable value.
+fn parse_repr(s: &str, n: u8) -> Result<i128, ParseFixedError> {
+  let (neg, digits) = match s.as_bytes().first() {
+    Some(b'-') => (true, &s[1..]),
+    Some(b'+') => (false, &s[1..]),
+    _ => (false, s),
+  };
+  let (int, fract) = digits.split_once('.').unwrap_or((digits, ""));
+  if (int.is_empty() && fract.is_empty())
+    || !int.bytes().chain(fract.bytes()).all(|c| c.is_ascii_digit())
+  {
+    return Err(ParseFixedError::Invalid);
+  }
+
+  let mut value = 0u128;
+  for c in int.bytes() {
+    value = value
+      .checked_mul(10)
+      .and_then(|x| x.checked_add(u128::from(c - b'0')))
+      .ok_or(ParseFixedError::Overflow)?;
+  }
+  // Convert the fraction to binary by repeated doubling, keeping one extra bit
+  // for rounding. Every multiple of `2^-(n + 1)` has at most `n + 1` decimal
+  // digits, so truncating the fraction there can't move it past one.
+  let mut buf = [0u8; 128];
+  let len = fract.len().min(usize::from(n) + 1).min(buf.len());
+  for (d, c) in buf.iter_mut().zip(fract.bytes().take(len)) {
+    *d = c - b'0';
+  }
+  let mut bits = 0u128;
+  for _ in 0..=n {
+    let mut carry = 0;
+    for d in buf[..len].iter_mut().rev() {
+      let x = *d * 2 + carry;
+      carry = u8::from(x >= 10);
+      *d = if x >= 10 { x - 10 } else { x };
+    }
+    bits = (bits << 1) | u128::from(carry);
+  }
+  let value = value
+    .checked_mul(1 << n)
+    .and_then(|x| x.checked_add((bits >> 1) + (bits & 1)))
+    .and_then(|x| i128::try_from(x).ok())
+    .ok_or(ParseFixedError::Overflow)?;
+  Ok(if neg { -value } else { value })
+}
+
+macro_rules! impl_int_fixed {
+  ($($ty:ty: $ex:ty),*) => {$(
+    impl<const N: u8> Fixed<$ty, N> {
+      /// The smallest value which can be represented.
+      pub const MIN: Self = Self(<$ty>::MIN);
+      /// The largest value which can be represented.
+      pub const MAX: Self = Self(<$ty>::MAX);
+      /// The mask of the bits used for the fractional part.
+      const FRACT_MASK: $ty = ((1 as $ty) << N).wrapping_sub(1);
+
+      /// Rounds down to an integer.
+      #[inline]
+      pub fn floor(self) -> $ty {
+        self.0 >> N
+      }
+
+      /// Rounds up to an integer.
+      #[inline]
+      pub fn ceil(self) -> $ty {
+        (self.0 >> N) + <$ty>::from(self.0 & Self::FRACT_MASK != 0)
+      }
+
+      /// Gets the fractional part. This is always positive.
+      #[inline]
+      pub fn fract(self) -> Self {
+        Self(self.0 & Self::FRACT_MASK)
+      }
+
+      /// Calculates the square root, rounded down.
+      ///
+      /// # Panics
+      /// Panics if the value is negative.
+      #[inline]
+      pub fn sqrt(self) -> Self {
+        let x = u128::try_from(self.0).expect("square root of a negative number");
+        Self(isqrt(x << N) as $ty)
+      }
+    }
+
+    impl<const N: u8> SaturatingMul for Fixed<$ty, N> {
+      type Output = Self;
+      #[inline]
+      fn smul(self, rhs: Self) -> Self::Output {
+        let x = (<$ex>::from(self.0) * <$ex>::from(rhs.0)) >> N;
+        Self(x.clamp(<$ty>::MIN.into(), <$ty>::MAX.into()) as $ty)
+      }
+    }
+
+    impl<const N: u8> SaturatingDiv for Fixed<$ty, N> {
+      type Output = Self;
+      #[inline]
+      fn sdiv(self, rhs: Self) -> Self::Output {
+        let x = (<$ex>::from(self.0) << N) / <$ex>::from(rhs.0);
+        Self(x.clamp(<$ty>::MIN.into(), <$ty>::MAX.into()) as $ty)
+      }
+    }
+
+    impl<const N: u8> FromStr for Fixed<$ty, N> {
+      type Err = ParseFixedError;
+      fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let x = parse_repr(s, N)?;
+        <$ty>::try_from(x).map(Self).map_err(|_| ParseFixedError::Overflow)
+      }
+    }
+  )*};
+}
+impl_int_fixed!(i8: i16, i16: i32, i32: i64, i64: i128, u8: u16, u16: u32, u32: u64, u64: u128);
+
+macro_rules! impl_signed_fixed {
+  ($($ty:ty),*) => {$(
+    impl<const N: u8> Fixed<$ty, N> {
+      /// Calculates the absolute value.
+      #[inline]
+      pub fn abs(self) -> Self {
+        Self(self.0.abs())
+      }
+
+      /// Rounds to the nearest integer. Halfway cases are rounded away from
+      /// zero.
+      #[inline]
+      pub fn round(self) -> $ty {
+        if N == 0 {
+          return self.0;
+        }
+        let half = 1 << (N - 1);
+        let fract = self.0 & Self::FRACT_MASK;
+        // The floor of a negative number is already away from zero.
+        self.floor() + <$ty>::from(fract > half || (fract == half && self.0 >= 0))
+      }
+    }
+  )*};
+}
+impl_signed_fixed!(i8, i16, i32, i64);
+
+macro_rules! impl_unsigned_fixed {
+  ($($ty:ty),*) => {$(
+    impl<const N: u8> Fixed<$ty, N> {
+      /// Rounds to the nearest integer. Halfway cases are rounded up.
+      #[inline]
+      pub fn round(self) -> $ty {
+        if N == 0 {
+          return self.0;
+        }
+        self.floor() + <$ty>::from(self.0 & Self::FRACT_MASK >= 1 << (N - 1))
+      }
+    }
+  )*};
+}
+impl_unsigned_fixed!(u8, u16, u32, u64);
+
 impl<T: Copy + Into<f64>, const N: u8> fmt::Display for Fixed<T, N> {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     f64::from(*self).fmt(f)
@@ -285,4 +545,68 @@ where
   }
 }
 
+/// Formats a fixed-point number as an exact decimal.
+#[cfg(feature = "serde")]
+struct ExactDecimal<const N: u8>(i128);
+#[cfg(feature = "serde")]
+impl<const N: u8> fmt::Display for ExactDecimal<N> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let mask = (1u128 << N) - 1;
+    let x = self.0.unsigned_abs();
+    if self.0 < 0 {
+      f.write_str("-")?;
+    }
+    write!(f, "{}", x >> N)?;
+    let mut fract = x & mask;
+    if fract != 0 {
+      f.write_str(".")?;
+    }
+    // Every binary fraction has a finite decimal expansion.
+    while fract != 0 {
+      fract *= 10;
+      write!(f, "{}", fract >> N)?;
+      fract &= mask;
+    }
+    Ok(())
+  }
+}
+
+/// Serialized as an exact decimal string in human readable formats and as the
+/// underlying representation otherwise.
+#[cfg(feature = "serde")]
+impl<T: Copy + Into<i128> + serde::Serialize, const N: u8> serde::Serialize for Fixed<T, N> {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+      serializer.collect_str(&ExactDecimal::<N>(self.0.into()))
+    } else {
+      self.0.serialize(serializer)
+    }
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>, const N: u8> serde::Deserialize<'de> for Fixed<T, N>
+where
+  Self: FromStr<Err = ParseFixedError>,
+{
+  fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct Visitor<T>(core::marker::PhantomData<T>);
+    impl<T: FromStr<Err = ParseFixedError>> serde::de::Visitor<'_> for Visitor<T> {
+      type Value = T;
+      fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a decimal number in a string")
+      }
+      fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<T, E> {
+        v.parse().map_err(E::custom)
+      }
+    }
+
+    if deserializer.is_human_readable() {
+      deserializer.deserialize_str(Visitor(core::marker::PhantomData))
+    } else {
+      T::deserialize(deserializer).map(Self)
+    }
+  }
+}
+
 unsafe impl<T, const N: u8> TransparentWrapper<T> for Fixed<T, N> {}
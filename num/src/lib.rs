@@ -1,10 +1,12 @@
 #![no_std]
 
+mod angle;
 mod fixed;
 mod m2d;
 mod measure;
 
-pub use fixed::Fixed;
+pub use angle::{Angle, SINE_TABLE};
+pub use fixed::{Fixed, ParseFixedError};
 pub use m2d::M2d;
 pub use measure::Measure;
 
@@ -14,6 +16,48 @@ pub trait CheckedAdd<T = Self> {
   fn cadd(self, rhs: T) -> Option<Self::Output>;
 }
 
+/// The subtraction operator, but returning `None` on overflow.
+pub trait CheckedSub<T = Self> {
+  type Output;
+  fn csub(self, rhs: T) -> Option<Self::Output>;
+}
+
+/// The multiplication operator, but returning `None` on overflow.
+pub trait CheckedMul<T = Self> {
+  type Output;
+  fn cmul(self, rhs: T) -> Option<Self::Output>;
+}
+
+/// The division operator, but returning `None` on overflow or division by zero.
+pub trait CheckedDiv<T = Self> {
+  type Output;
+  fn cdiv(self, rhs: T) -> Option<Self::Output>;
+}
+
+/// The addition operator, but saturating at the numeric bounds.
+pub trait SaturatingAdd<T = Self> {
+  type Output;
+  fn sadd(self, rhs: T) -> Self::Output;
+}
+
+/// The subtraction operator, but saturating at the numeric bounds.
+pub trait SaturatingSub<T = Self> {
+  type Output;
+  fn ssub(self, rhs: T) -> Self::Output;
+}
+
+/// The multiplication operator, but saturating at the numeric bounds.
+pub trait SaturatingMul<T = Self> {
+  type Output;
+  fn smul(self, rhs: T) -> Self::Output;
+}
+
+/// The division operator, but saturating at the numeric bounds.
+pub trait SaturatingDiv<T = Self> {
+  type Output;
+  fn sdiv(self, rhs: T) -> Self::Output;
+}
+
 /// Calculates the absolute value, wrapping on overflow.
 pub trait WrappingAbs<T = Self> {
   type Output;
@@ -51,6 +95,18 @@ pub trait MulTrunc<T = Self> {
   fn mul_trunc(self, rhs: T) -> Self::Output;
 }
 
+/// Multiplication by a fixed-point fraction, keeping the type of the left-hand
+/// side. Integers are rounded down and the result wraps on overflow.
+pub trait Scale<T> {
+  fn scale(self, rhs: T) -> Self;
+}
+
+/// Removes the unit from a value. Numbers without a unit are returned as is.
+pub trait Unitless {
+  type Output;
+  fn unitless(self) -> Self::Output;
+}
+
 /// Infallible numeric value conversion. Oversized values are wrapped into the
 /// target domain.
 pub trait WrappingFrom<T> {
@@ -119,7 +175,14 @@ macro_rules! impl_core_ops {
     impl_core_op!($ty, WrappingSub, wsub, wrapping_sub);
     impl_core_op!($ty, WrappingMul, wmul, wrapping_mul);
     impl_core_op!($ty, WrappingDiv, wdiv, wrapping_div);
+    impl_core_op!($ty, SaturatingAdd, sadd, saturating_add);
+    impl_core_op!($ty, SaturatingSub, ssub, saturating_sub);
+    impl_core_op!($ty, SaturatingMul, smul, saturating_mul);
+    impl_core_op!($ty, SaturatingDiv, sdiv, saturating_div);
     impl_core_cop!($ty, CheckedAdd, cadd, checked_add);
+    impl_core_cop!($ty, CheckedSub, csub, checked_sub);
+    impl_core_cop!($ty, CheckedMul, cmul, checked_mul);
+    impl_core_cop!($ty, CheckedDiv, cdiv, checked_div);
   };
 }
 
@@ -136,6 +199,19 @@ impl_core_ops!(u64);
 impl_core_ops!(u128);
 impl_core_ops!(usize);
 
+macro_rules! impl_unitless {
+  ($($ty:ty),*) => {$(
+    impl Unitless for $ty {
+      type Output = Self;
+      #[inline]
+      fn unitless(self) -> Self {
+        self
+      }
+    }
+  )*};
+}
+impl_unitless!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
 macro_rules! impl_core_signed_ops {
   ($ty:ty) => {
     impl_core_uop!($ty, WrappingAbs, wabs, wrapping_abs);
@@ -1,6 +1,6 @@
 use crate::{
-  CheckedAdd, Fixed, MulTrunc, WrappingAbs, WrappingAdd, WrappingDiv, WrappingFrom, WrappingInto,
-  WrappingMul, WrappingSub,
+  Angle, CheckedAdd, Fixed, MulTrunc, Scale, Unitless, WrappingAbs, WrappingAdd, WrappingDiv,
+  WrappingFrom, WrappingInto, WrappingMul, WrappingSub,
 };
 use bytemuck::{Pod, Zeroable};
 use core::{
@@ -10,6 +10,7 @@ use core::{
 
 #[repr(C)]
 #[derive(Default, Clone, Copy, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct M2d<T> {
   pub x: T,
   pub y: T,
@@ -26,6 +27,130 @@ impl<T> M2d<T> {
   }
 }
 
+impl<T: Copy> M2d<T> {
+  /// Calculates the dot product. Any units are removed from the elements
+  /// before multiplying.
+  #[inline]
+  pub fn dot<U>(self, rhs: Self) -> U
+  where
+    T: Unitless<Output = U>,
+    U: Mul<Output = U> + Add<Output = U>,
+  {
+    self.x.unitless() * rhs.x.unitless() + self.y.unitless() * rhs.y.unitless()
+  }
+
+  /// Calculates the squared length.
+  #[inline]
+  pub fn len_sq<U>(self) -> U
+  where
+    T: Unitless<Output = U>,
+    U: Mul<Output = U> + Add<Output = U>,
+  {
+    self.dot(self)
+  }
+
+  /// Approximates the length as the larger component plus half the smaller
+  /// one. Overestimates by at most 12%.
+  #[inline]
+  pub fn len_approx(self) -> T
+  where
+    T: WrappingAbs<Output = T> + PartialOrd + Add<Output = T> + Scale<Fixed<u8, 1>>,
+  {
+    const HALF: Fixed<u8, 1> = Fixed::from_repr(1);
+    let (x, y) = (self.x.wabs(), self.y.wabs());
+    if x < y {
+      y + x.scale(HALF)
+    } else {
+      x + y.scale(HALF)
+    }
+  }
+
+  /// Calculates the squared distance between two points.
+  #[inline]
+  pub fn dist_sq<U>(self, other: Self) -> U
+  where
+    T: Sub<Output = T> + Unitless<Output = U>,
+    U: Mul<Output = U> + Add<Output = U>,
+  {
+    (other - self).len_sq()
+  }
+
+  /// Approximates the distance between two points. See `len_approx`.
+  #[inline]
+  pub fn dist_approx(self, other: Self) -> T
+  where
+    T: Sub<Output = T>
+      + WrappingAbs<Output = T>
+      + PartialOrd
+      + Add<Output = T>
+      + Scale<Fixed<u8, 1>>,
+  {
+    (other - self).len_approx()
+  }
+
+  /// Linearly interpolates between two points. A fraction of zero gives
+  /// `self` and one gives `other`.
+  #[inline]
+  pub fn lerp<F: Copy>(self, other: Self, fract: F) -> Self
+  where
+    T: Add<Output = T> + Sub<Output = T> + Scale<F>,
+  {
+    M2d::new(
+      self.x + (other.x - self.x).scale(fract),
+      self.y + (other.y - self.y).scale(fract),
+    )
+  }
+
+  /// Takes the smaller value of each component.
+  #[inline]
+  pub fn min(self, other: Self) -> Self
+  where
+    T: PartialOrd,
+  {
+    M2d::new(
+      if other.x < self.x { other.x } else { self.x },
+      if other.y < self.y { other.y } else { self.y },
+    )
+  }
+
+  /// Takes the larger value of each component.
+  #[inline]
+  pub fn max(self, other: Self) -> Self
+  where
+    T: PartialOrd,
+  {
+    M2d::new(
+      if other.x > self.x { other.x } else { self.x },
+      if other.y > self.y { other.y } else { self.y },
+    )
+  }
+
+  /// Restricts each component to the range given by the components of `min`
+  /// and `max`.
+  #[inline]
+  pub fn clamp(self, min: Self, max: Self) -> Self
+  where
+    T: PartialOrd,
+  {
+    self.max(min).min(max)
+  }
+
+  /// Rotates counter-clockwise around the origin using `SINE_TABLE`.
+  /// Integer elements are rounded down.
+  #[inline]
+  pub fn rotate(self, angle: Angle) -> Self
+  where
+    T: Add<Output = T> + Sub<Output = T> + Scale<Fixed<i32, 16>>,
+  {
+    let sin = Fixed::<i32, 16>::from(f64::from(angle.sin()));
+    let cos = Fixed::<i32, 16>::from(f64::from(angle.cos()));
+    M2d::new(
+      self.x.scale(cos) - self.y.scale(sin),
+      self.x.scale(sin) + self.y.scale(cos),
+    )
+  }
+}
+
 impl<T: PartialEq<U>, U> PartialEq<M2d<U>> for M2d<T> {
   fn eq(&self, other: &M2d<U>) -> bool {
     self.x == other.x && self.y == other.y
@@ -1,6 +1,6 @@
 use crate::{
-  CheckedAdd, MulTrunc, WrappingAbs, WrappingAdd, WrappingDiv, WrappingFrom, WrappingInto,
-  WrappingMul, WrappingSub,
+  CheckedAdd, MulTrunc, SaturatingAdd, SaturatingSub, Scale, Unitless, WrappingAbs, WrappingAdd,
+  WrappingDiv, WrappingFrom, WrappingInto, WrappingMul, WrappingSub,
 };
 use bytemuck::TransparentWrapper;
 use core::{
@@ -97,6 +97,21 @@ impl<T: WrappingFrom<U>, U, S> WrappingFrom<Measure<U, S>> for Measure<T, S> {
   }
 }
 
+impl<T: Scale<U>, U, S> Scale<U> for Measure<T, S> {
+  #[inline]
+  fn scale(self, rhs: U) -> Self {
+    Self::new(self.0.scale(rhs))
+  }
+}
+
+impl<T: Unitless, S> Unitless for Measure<T, S> {
+  type Output = T::Output;
+  #[inline]
+  fn unitless(self) -> Self::Output {
+    self.0.unitless()
+  }
+}
+
 macro_rules! impl_uop {
   ($op:ident, $fn:ident) => {
     impl<T: $op, S> $op for Measure<T, S> {
@@ -157,6 +172,8 @@ impl_op!(WrappingAdd<Measure<U, S>>, wadd, .0);
 impl_op!(WrappingSub<Measure<U, S>>, wsub, .0);
 impl_op!(WrappingMul<U>, wmul);
 impl_op!(WrappingDiv<U>, wdiv);
+impl_op!(SaturatingAdd<Measure<U, S>>, sadd, .0);
+impl_op!(SaturatingSub<Measure<U, S>>, ssub, .0);
 
 impl_cop!(CheckedAdd<Measure<U, S>>, cadd, .0);
 
@@ -166,4 +183,19 @@ impl_op_assign!(MulAssign<U>, mul_assign);
 impl_op_assign!(DivAssign<U>, div_assign);
 impl_op_assign!(RemAssign<U>, rem_assign);
 
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize, S> serde::Serialize for Measure<T, S> {
+  #[inline]
+  fn serialize<Sr: serde::Serializer>(&self, serializer: Sr) -> Result<Sr::Ok, Sr::Error> {
+    self.0.serialize(serializer)
+  }
+}
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>, S> serde::Deserialize<'de> for Measure<T, S> {
+  #[inline]
+  fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    T::deserialize(deserializer).map(Self::new)
+  }
+}
+
 unsafe impl<T, S> TransparentWrapper<T> for Measure<T, S> {}
@@ -0,0 +1,57 @@
+use core::f64::consts::PI;
+use num::{Angle, SINE_TABLE};
+
+#[test]
+fn sine_table() {
+  for (i, &x) in SINE_TABLE.iter().enumerate() {
+    let y = (i as f64 * PI / 256.0).sin();
+    assert_eq!(x.to_bits(), (y as f32).to_bits(), "entry {i:#x}");
+    if y == 0.0 {
+      continue;
+    }
+    // The game uses the x87 `fsin` which can differ from `f64::sin` by an
+    // ulp. Make sure that can't change which way the entry rounds.
+    let (x, y) = (x.abs(), y.abs());
+    let ulp = f64::from_bits(y.to_bits() + 1) - y;
+    let up = (f64::from(x) + f64::from(f32::from_bits(x.to_bits() + 1))) / 2.0;
+    let down = (f64::from(x) + f64::from(f32::from_bits(x.to_bits() - 1))) / 2.0;
+    assert!((up - y).min(y - down) > 1000.0 * ulp, "entry {i:#x}");
+  }
+
+  // Entries known from the game's table.
+  assert_eq!(SINE_TABLE[0].to_bits(), 0.0f32.to_bits());
+  assert_eq!(SINE_TABLE[0x80].to_bits(), 1.0f32.to_bits());
+  assert_eq!(SINE_TABLE[0x100].to_bits(), 1.2246469e-16f32.to_bits());
+  assert_eq!(SINE_TABLE[0x180].to_bits(), (-1.0f32).to_bits());
+}
+
+#[test]
+fn sin_cos() {
+  assert_eq!(Angle::from_repr(0).sin(), 0.0);
+  assert_eq!(Angle::from_repr(0x80).sin(), 1.0);
+  assert_eq!(Angle::from_repr(0x180).sin(), -1.0);
+  assert_eq!(Angle::from_repr(0).cos(), 1.0);
+  assert_eq!(Angle::from_repr(0x100).cos(), -1.0);
+  for i in 0..0x200 {
+    let x = Angle::from_repr(i);
+    assert_eq!(x.cos(), Angle::from_repr(i + 0x80).sin());
+    assert_eq!(x.sin(), Angle::from_repr(i + 0x400).sin());
+  }
+}
+
+#[test]
+fn wrapping() {
+  let x = Angle::from_repr(0x1f0);
+  assert_eq!((x + Angle::from_repr(0x20)).normalized(), 0x10);
+  assert_eq!(
+    Angle::from_repr(0x10) - Angle::from_repr(0x20),
+    -Angle::from_repr(0x10)
+  );
+  assert_eq!((-Angle::from_repr(0x10)).normalized(), 0x1f0);
+  assert_eq!(
+    Angle::from_repr(u32::MAX) + Angle::from_repr(1),
+    Angle::from_repr(0)
+  );
+  assert_eq!((Angle::from_repr(0x80) * 5).normalized(), 0x80);
+  assert_eq!(Angle::from_repr(0x100).to_radians(), PI);
+}
@@ -0,0 +1,106 @@
+use num::{
+  CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, Fixed, ParseFixedError, SaturatingAdd,
+  SaturatingDiv, SaturatingMul, SaturatingSub,
+};
+
+type FI16 = Fixed<i32, 16>;
+type FU8 = Fixed<u16, 8>;
+
+fn fi16(x: &str) -> FI16 {
+  x.parse().unwrap()
+}
+
+#[test]
+fn parse() {
+  assert_eq!(fi16("1.5"), FI16::from_repr(0x1_8000));
+  assert_eq!(fi16("-2.25"), FI16::from_repr(-0x2_4000));
+  assert_eq!(fi16(".5"), FI16::from_repr(0x8000));
+  assert_eq!(fi16("+3."), FI16::from_repr(0x3_0000));
+  // 0.1 is rounded to the nearest representable value.
+  assert_eq!(fi16("0.1"), FI16::from_repr(0x199a));
+  assert_eq!(fi16("32767.99999"), FI16::from_repr(0x7fff_ffff));
+  assert_eq!("255.99".parse::<FU8>(), Ok(FU8::from_repr(0xfffd)));
+  assert_eq!("32768".parse::<FI16>(), Err(ParseFixedError::Overflow));
+  assert_eq!("-1".parse::<FU8>(), Err(ParseFixedError::Overflow));
+  assert_eq!("".parse::<FI16>(), Err(ParseFixedError::Invalid));
+  assert_eq!("-.".parse::<FI16>(), Err(ParseFixedError::Invalid));
+  assert_eq!("1e3".parse::<FI16>(), Err(ParseFixedError::Invalid));
+}
+
+#[test]
+fn parse_max_precision() {
+  type F = Fixed<u64, 63>;
+  // 7 * 2^-63
+  let x = "0.000000000000000000758941520739853103805216960608959197998046875";
+  assert_eq!(x.parse::<F>().map(F::repr), Ok(7));
+  // 6.5 * 2^-63 rounds up, anything less rounds down.
+  let x = "0.0000000000000000007047314121155778821048443205654621124267578125";
+  assert_eq!(x.parse::<F>().map(F::repr), Ok(7));
+  let x = "0.0000000000000000007047314121155778821048443205654621124267578124999";
+  assert_eq!(x.parse::<F>().map(F::repr), Ok(6));
+  assert_eq!(
+    "1.9999999999999999999".parse::<F>().map(F::repr),
+    Ok(u64::MAX)
+  );
+  assert_eq!(
+    "1.99999999999999999999999".parse::<F>().map(F::repr),
+    Err(ParseFixedError::Overflow)
+  );
+  assert_eq!(
+    "2".parse::<F>().map(F::repr),
+    Err(ParseFixedError::Overflow)
+  );
+}
+
+#[test]
+fn rounding() {
+  for (x, floor, ceil, round) in [
+    ("1.5", 1, 2, 2),
+    ("1.25", 1, 2, 1),
+    ("-1.5", -2, -1, -2),
+    ("-1.75", -2, -1, -2),
+    ("-1.25", -2, -1, -1),
+    ("3", 3, 3, 3),
+  ] {
+    let x = fi16(x);
+    assert_eq!(
+      (x.floor(), x.ceil(), x.round()),
+      (floor, ceil, round),
+      "{x}"
+    );
+  }
+  assert_eq!(fi16("-1.25").fract(), fi16("0.75"));
+  assert_eq!(fi16("-1.25").abs(), fi16("1.25"));
+  assert_eq!(FU8::from_repr(0x180).round(), 2);
+}
+
+#[test]
+fn checked_saturating() {
+  assert_eq!(FI16::MAX.cadd(fi16("1")), None);
+  assert_eq!(FI16::MIN.csub(fi16("1")), None);
+  assert_eq!(fi16("1.5").cmul(fi16("-2")), Some(fi16("-3")));
+  assert_eq!(fi16("200").cmul(fi16("200")), None);
+  assert_eq!(fi16("3").cmul(2), Some(fi16("6")));
+  assert_eq!(fi16("3").cdiv(fi16("0")), None);
+  assert_eq!(fi16("3").cdiv(fi16("2")), Some(fi16("1.5")));
+  assert_eq!(fi16("1").cdiv(fi16("0.00002")), None);
+
+  assert_eq!(FI16::MAX.sadd(fi16("1")), FI16::MAX);
+  assert_eq!(FI16::MIN.ssub(fi16("1")), FI16::MIN);
+  assert_eq!(fi16("200").smul(fi16("-200")), FI16::MIN);
+  assert_eq!(fi16("20000").smul(2), FI16::MAX);
+  assert_eq!(fi16("1").sdiv(fi16("0.00002")), FI16::MAX);
+  assert_eq!(
+    FU8::from_repr(0x100).ssub(FU8::from_repr(0x200)),
+    FU8::from_repr(0)
+  );
+}
+
+#[test]
+fn sqrt() {
+  assert_eq!(fi16("2.25").sqrt(), fi16("1.5"));
+  assert_eq!(fi16("0").sqrt(), fi16("0"));
+  assert_eq!(fi16("2").sqrt(), FI16::from_repr(0x1_6a09));
+  assert_eq!(FI16::MAX.sqrt(), FI16::from_repr(0xb5_04f3));
+  assert_eq!(Fixed::<u64, 32>::from_repr(u64::MAX).sqrt().floor(), 65535);
+}
@@ -0,0 +1,62 @@
+use num::{Angle, Fixed, M2d, Measure};
+
+type FI16 = Fixed<i32, 16>;
+type Pos = M2d<Measure<FI16, ()>>;
+
+fn fi16(x: &str) -> FI16 {
+  x.parse().unwrap()
+}
+
+fn pos(x: &str, y: &str) -> Pos {
+  M2d::new(Measure::new(fi16(x)), Measure::new(fi16(y)))
+}
+
+#[test]
+fn lengths() {
+  assert_eq!(M2d::new(3, -4).dot(M2d::new(2, 5)), -14);
+  assert_eq!(M2d::new(3, -4).len_sq(), 25);
+  assert_eq!(M2d::new(-8, 3).len_approx(), 9);
+  assert_eq!(M2d::new(1, 1).dist_sq(M2d::new(4, 5)), 25);
+  assert_eq!(M2d::new(1, 1).dist_approx(M2d::new(4, 5)), 5);
+
+  assert_eq!(pos("1.5", "-2").len_sq(), fi16("6.25"));
+  assert_eq!(pos("1", "1").dist_sq(pos("2.5", "3")), fi16("6.25"));
+  assert_eq!(pos("-3", "1.5").len_approx(), Measure::new(fi16("3.75")));
+}
+
+#[test]
+fn lerp() {
+  let (a, b) = (M2d::new(10, -10), M2d::new(20, 10));
+  assert_eq!(a.lerp(b, fi16("0")), a);
+  assert_eq!(a.lerp(b, fi16("1")), b);
+  assert_eq!(a.lerp(b, fi16("0.25")), M2d::new(12, -5));
+  assert_eq!(
+    pos("0", "1").lerp(pos("1", "-1"), fi16("0.75")),
+    pos("0.75", "-0.5")
+  );
+}
+
+#[test]
+fn min_max() {
+  let (a, b) = (M2d::new(1, 5), M2d::new(3, 2));
+  assert_eq!(a.min(b), M2d::new(1, 2));
+  assert_eq!(a.max(b), M2d::new(3, 5));
+  assert_eq!(
+    M2d::new(-4, 7).clamp(M2d::new(0, 0), M2d::new(5, 5)),
+    M2d::new(0, 5)
+  );
+  assert_eq!(
+    pos("0.5", "9").clamp(pos("1", "1"), pos("2", "2")),
+    pos("1", "2")
+  );
+}
+
+#[test]
+fn rotate() {
+  let x = M2d::new(100, 0);
+  assert_eq!(x.rotate(Angle::from_repr(0)), x);
+  assert_eq!(x.rotate(Angle::from_repr(0x80)), M2d::new(0, 100));
+  assert_eq!(x.rotate(Angle::from_repr(0x100)), M2d::new(-100, 0));
+  assert_eq!(x.rotate(Angle::from_repr(0x180)), M2d::new(0, -100));
+  assert_eq!(pos("2", "0").rotate(Angle::from_repr(0x80)), pos("0", "2"));
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "serde")]
+
+use num::{Angle, Fixed, M2d, Measure};
+
+type FI16 = Fixed<i32, 16>;
+
+fn round_trip(x: FI16, json: &str) {
+  assert_eq!(serde_json::to_string(&x).unwrap(), json);
+  assert_eq!(serde_json::from_str::<FI16>(json).unwrap(), x);
+}
+
+#[test]
+fn fixed() {
+  round_trip(FI16::from_repr(0x1_8000), r#""1.5""#);
+  round_trip(FI16::from_repr(-0x2_4000), r#""-2.25""#);
+  round_trip(FI16::from_repr(-0x8000), r#""-0.5""#);
+  round_trip(FI16::from_repr(0x3_0000), r#""3""#);
+  round_trip(FI16::from_repr(1), r#""0.0000152587890625""#);
+  round_trip(FI16::MIN, r#""-32768""#);
+  round_trip(FI16::MAX, r#""32767.9999847412109375""#);
+  assert!(serde_json::from_str::<FI16>(r#""1.5x""#).is_err());
+  assert!(serde_json::from_str::<FI16>(r#""40000""#).is_err());
+}
+
+#[test]
+fn fixed_max_precision() {
+  type U = Fixed<u64, 63>;
+  type I = Fixed<i64, 63>;
+  for x in [0, 1, 6, 7, 0x5555_5555_5555_5555, u64::MAX - 1, u64::MAX] {
+    let x = U::from_repr(x);
+    let json = serde_json::to_string(&x).unwrap();
+    assert_eq!(
+      serde_json::from_str::<U>(&json).unwrap().repr(),
+      x.repr(),
+      "{json}"
+    );
+  }
+  for x in [i64::MIN, -7, -1, 1, 7, i64::MAX] {
+    let x = I::from_repr(x);
+    let json = serde_json::to_string(&x).unwrap();
+    assert_eq!(
+      serde_json::from_str::<I>(&json).unwrap().repr(),
+      x.repr(),
+      "{json}"
+    );
+  }
+}
+
+#[test]
+fn wrappers() {
+  let p = M2d::new(
+    Measure::<_, ()>::new(FI16::from_repr(0x1_8000)),
+    Measure::new(FI16::from_repr(-0x2_0000)),
+  );
+  let json = serde_json::to_string(&p).unwrap();
+  assert_eq!(json, r#"{"x":"1.5","y":"-2"}"#);
+  assert_eq!(
+    serde_json::from_str::<M2d<Measure<FI16, ()>>>(&json).unwrap(),
+    p
+  );
+
+  let a = Angle::from_repr(0x80);
+  assert_eq!(serde_json::to_string(&a).unwrap(), r#""0.5""#);
+  assert_eq!(serde_json::from_str::<Angle>(r#""0.5""#).unwrap(), a);
+}